@@ -0,0 +1,292 @@
+use core::mem;
+
+use alloc::alloc::{Allocator, Global};
+use alloc::vec::Vec;
+
+use crate::{
+    ComponentContainer, Entity, HashComponentContainer, StorageKind, VecAllocator,
+    VecComponentContainer,
+};
+
+/// A container that stores its components in a hash map while they are rare
+/// and in a vec once most entities have one.
+///
+/// It switches to vec storage when at least half of the entity slots hold a
+/// component, and back to a hash map when fewer than one in eight do. The gap
+/// between the two thresholds keeps a component hovering around one of them
+/// from migrating back and forth. Each migration moves every component, so it
+/// costs as much as re-adding them all.
+///
+/// Queries always take the generic path for this container, whichever storage
+/// it currently uses.
+pub struct AdaptiveComponentContainer<C, A: Allocator + Clone = Global> {
+    storage: Storage<C, A>,
+    /// One past the highest entity index added.
+    slots: usize,
+    allocator: A,
+    /// Structural version, kept here since migrations replace the storage.
+    version: u64,
+}
+
+enum Storage<C, A: Allocator + Clone> {
+    Hash(HashComponentContainer<C, A>),
+    Vec(VecComponentContainer<C, A>),
+}
+
+impl<C> AdaptiveComponentContainer<C> {
+    pub fn new() -> Self {
+        Self::new_in(Global)
+    }
+}
+
+impl<C> Default for AdaptiveComponentContainer<C> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<C, A: VecAllocator> AdaptiveComponentContainer<C, A> {
+    pub fn new_in(allocator: A) -> Self {
+        Self {
+            storage: Storage::Hash(HashComponentContainer::new_in(allocator.clone())),
+            slots: 0,
+            allocator,
+            version: 0,
+        }
+    }
+
+    /// Returns the share of entity slots holding a component, from 0 to 1.
+    pub fn density(&self) -> f32 {
+        if self.slots == 0 {
+            return 0.0;
+        }
+
+        self.count() as f32 / self.slots as f32
+    }
+
+    /// Moves the components to whichever storage suits the current density
+    /// best, ignoring the gap between the automatic thresholds.
+    pub fn compact(&mut self) {
+        if self.count() * 4 >= self.slots {
+            self.migrate_to_vec();
+        } else {
+            self.migrate_to_hash();
+        }
+    }
+
+    /// Migrates if the density has crossed the threshold for the other storage.
+    fn adapt(&mut self) {
+        match self.storage {
+            Storage::Hash(ref hash) if hash.count() * 2 >= self.slots => self.migrate_to_vec(),
+            Storage::Vec(ref vec) if vec.count() * 8 < self.slots => self.migrate_to_hash(),
+            _ => {}
+        }
+    }
+
+    fn migrate_to_vec(&mut self) {
+        let Storage::Hash(hash) = &mut self.storage else {
+            return;
+        };
+
+        let mut vec = VecComponentContainer::with_capacity_in(self.slots, self.allocator.clone());
+        let indices: Vec<usize> = hash.iter_indices().collect();
+        for index in indices {
+            let entity = Entity::with_generation(index, hash.generation_at(index));
+            if let Some(component) = hash.remove(entity) {
+                vec.set(entity, component);
+            }
+        }
+        if let Some(last) = self.slots.checked_sub(1) {
+            vec.add_entity(Entity::new(last));
+        }
+
+        self.storage = Storage::Vec(vec);
+    }
+
+    fn migrate_to_hash(&mut self) {
+        let Storage::Vec(vec) = &mut self.storage else {
+            return;
+        };
+
+        let mut hash =
+            HashComponentContainer::with_capacity_in(vec.count(), self.allocator.clone());
+        let slots = mem::replace(&mut vec.container, Vec::new_in(self.allocator.clone()));
+        for (index, slot) in slots.into_iter().enumerate() {
+            if let Some(component) = slot {
+                hash.set(
+                    Entity::with_generation(index, vec.generations[index]),
+                    component,
+                );
+            }
+        }
+
+        self.storage = Storage::Hash(hash);
+    }
+}
+
+impl<C, A: VecAllocator> ComponentContainer<C> for AdaptiveComponentContainer<C, A> {
+    fn add_entity(&mut self, entity: Entity) {
+        self.slots = self.slots.max(entity.index + 1);
+
+        match &mut self.storage {
+            Storage::Hash(hash) => hash.add_entity(entity),
+            Storage::Vec(vec) => vec.add_entity(entity),
+        }
+        self.adapt();
+    }
+
+    fn remove_entity(&mut self, entity: Entity) {
+        self.remove(entity);
+    }
+
+    fn remove(&mut self, entity: Entity) -> Option<C> {
+        let component = match &mut self.storage {
+            Storage::Hash(hash) => hash.remove(entity),
+            Storage::Vec(vec) => vec.remove(entity),
+        };
+        if component.is_some() {
+            self.version += 1;
+        }
+        self.adapt();
+        component
+    }
+
+    fn clear(&mut self) {
+        self.storage = Storage::Hash(HashComponentContainer::new_in(self.allocator.clone()));
+        self.slots = 0;
+        self.version += 1;
+    }
+
+    fn merge_from(&mut self, mut other: Self, offset: usize) {
+        let indices: Vec<usize> = other.iter_indices().collect();
+        for index in indices {
+            let generation = other.generation_at(index);
+            if let Some(component) = other.remove(Entity::with_generation(index, generation)) {
+                let entity = Entity::with_generation(index + offset, generation);
+                self.add_entity(entity);
+                self.set(entity, component);
+            }
+        }
+        self.slots = self.slots.max(other.slots + offset);
+    }
+
+    fn clone_into(&self, other: &mut Self)
+    where
+        C: Clone,
+    {
+        other.clear();
+        other.slots = self.slots;
+        self.for_each(|index, component| {
+            let entity = Entity::with_generation(index, self.generation_at(index));
+            other.set(entity, component.clone());
+        });
+    }
+
+    fn set(&mut self, entity: Entity, component: C) {
+        self.slots = self.slots.max(entity.index + 1);
+
+        let count = self.count();
+        match &mut self.storage {
+            Storage::Hash(hash) => hash.set(entity, component),
+            Storage::Vec(vec) => vec.set(entity, component),
+        }
+        if self.count() != count {
+            self.version += 1;
+        }
+        self.adapt();
+    }
+
+    fn get(&self, entity: Entity) -> Option<&C> {
+        match &self.storage {
+            Storage::Hash(hash) => hash.get(entity),
+            Storage::Vec(vec) => vec.get(entity),
+        }
+    }
+
+    fn get_index(&self, entity: usize) -> Option<&C> {
+        match &self.storage {
+            Storage::Hash(hash) => hash.get_index(entity),
+            Storage::Vec(vec) => vec.get_index(entity),
+        }
+    }
+
+    fn get_mut(&mut self, entity: Entity) -> Option<&mut C> {
+        match &mut self.storage {
+            Storage::Hash(hash) => hash.get_mut(entity),
+            Storage::Vec(vec) => vec.get_mut(entity),
+        }
+    }
+
+    fn get_index_mut(&mut self, entity: usize) -> Option<&mut C> {
+        match &mut self.storage {
+            Storage::Hash(hash) => hash.get_index_mut(entity),
+            Storage::Vec(vec) => vec.get_index_mut(entity),
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.slots
+    }
+
+    fn count(&self) -> usize {
+        match &self.storage {
+            Storage::Hash(hash) => hash.count(),
+            Storage::Vec(vec) => vec.count(),
+        }
+    }
+
+    fn version(&self) -> u64 {
+        self.version
+    }
+
+    fn iter_indices(&self) -> impl Iterator<Item = usize> + '_ {
+        let (hash, vec) = match &self.storage {
+            Storage::Hash(hash) => (Some(hash.iter_indices()), None),
+            Storage::Vec(vec) => (None, Some(vec.iter_indices())),
+        };
+
+        hash.into_iter().flatten().chain(vec.into_iter().flatten())
+    }
+
+    fn for_each<F>(&self, f: F)
+    where
+        F: FnMut(usize, &C),
+    {
+        match &self.storage {
+            Storage::Hash(hash) => hash.for_each(f),
+            Storage::Vec(vec) => vec.for_each(f),
+        }
+    }
+
+    fn for_each_mut<F>(&mut self, f: F)
+    where
+        F: FnMut(usize, &mut C),
+    {
+        match &mut self.storage {
+            Storage::Hash(hash) => hash.for_each_mut(f),
+            Storage::Vec(vec) => vec.for_each_mut(f),
+        }
+    }
+
+    fn generation_at(&self, index: usize) -> u32 {
+        match &self.storage {
+            Storage::Hash(hash) => hash.generation_at(index),
+            Storage::Vec(vec) => vec.generation_at(index),
+        }
+    }
+
+    /// Reports the storage currently in use, `Hash` or one of the vec kinds.
+    fn storage_kind(&self) -> StorageKind {
+        match &self.storage {
+            Storage::Hash(hash) => hash.storage_kind(),
+            Storage::Vec(vec) => vec.storage_kind(),
+        }
+    }
+
+    fn check_invariants(&self) -> bool {
+        match &self.storage {
+            Storage::Hash(hash) => hash.check_invariants(),
+            Storage::Vec(vec) => vec.check_invariants(),
+        }
+    }
+}
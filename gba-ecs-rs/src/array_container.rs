@@ -0,0 +1,209 @@
+use crate::container::is_stale;
+use crate::ComponentContainer;
+use crate::Entity;
+use crate::SetError;
+use crate::StorageKind;
+
+/// A container backed by fixed-size arrays, for at most `N` entities.
+///
+/// It never allocates, so a world of these lives entirely wherever the world
+/// itself is placed, IWRAM included. Entity indices must stay below `N`:
+/// `set` panics otherwise. Entities past `N` may still exist in the world,
+/// they just cannot hold this component.
+///
+/// The `Option` discriminants double as the occupancy map; iteration walks
+/// the slots up to the highest one ever filled.
+pub struct ArrayComponentContainer<C, const N: usize> {
+    slots: [Option<C>; N],
+    /// Generation of the entity each slot's component was set for.
+    generations: [u32; N],
+    /// Number of occupied slots.
+    count: usize,
+    /// One past the highest slot filled since the last `clear`.
+    end: usize,
+    /// Structural version, see `ComponentContainer::version`.
+    version: u64,
+}
+
+impl<C, const N: usize> ArrayComponentContainer<C, N> {
+    pub const fn new() -> Self {
+        Self {
+            slots: [const { None }; N],
+            generations: [0; N],
+            count: 0,
+            end: 0,
+            version: 0,
+        }
+    }
+
+    /// Returns the slots up to the highest one filled, indexed by entity index.
+    pub fn raw_slots(&self) -> &[Option<C>] {
+        &self.slots[..self.end]
+    }
+}
+
+impl<C, const N: usize> Default for ArrayComponentContainer<C, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<C, const N: usize> ComponentContainer<C> for ArrayComponentContainer<C, N> {
+    /// Ascending index order.
+    const ORDERED: bool = true;
+
+    /// Does nothing: every slot exists from the start.
+    fn add_entity(&mut self, _entity: Entity) {}
+
+    fn remove_entity(&mut self, entity: Entity) {
+        self.remove(entity);
+    }
+
+    fn remove(&mut self, entity: Entity) -> Option<C> {
+        let component = self.slots.get_mut(entity.index)?.take()?;
+        self.count -= 1;
+        self.version += 1;
+        Some(component)
+    }
+
+    fn clear(&mut self) {
+        for slot in &mut self.slots[..self.end] {
+            *slot = None;
+        }
+        self.count = 0;
+        self.end = 0;
+        self.version += 1;
+    }
+
+    fn merge_from(&mut self, mut other: Self, offset: usize) {
+        for index in 0..other.end {
+            if let Some(component) = other.slots[index].take() {
+                let entity = Entity::with_generation(index + offset, other.generations[index]);
+                self.set(entity, component);
+            }
+        }
+    }
+
+    fn clone_into(&self, other: &mut Self)
+    where
+        C: Clone,
+    {
+        other.slots.clone_from(&self.slots);
+        other.generations = self.generations;
+        other.count = self.count;
+        other.end = self.end;
+        other.version += 1;
+    }
+
+    /// # Panics
+    ///
+    /// Panics if `entity`'s index is `N` or more.
+    fn set(&mut self, entity: Entity, component: C) {
+        let index = entity.index;
+        assert!(
+            index < N,
+            "entity index {index} does not fit in an ArrayComponentContainer of {N} slots"
+        );
+
+        if self.slots[index].replace(component).is_none() {
+            self.count += 1;
+            self.version += 1;
+        }
+        self.generations[index] = entity.generation;
+        self.end = self.end.max(index + 1);
+    }
+
+    fn try_set(&mut self, entity: Entity, component: C) -> Result<(), SetError> {
+        if entity.index >= N {
+            return Err(SetError::IndexOutOfRange(entity.index));
+        }
+        if is_stale(self, entity) {
+            return Err(SetError::StaleEntity);
+        }
+
+        self.set(entity, component);
+        Ok(())
+    }
+
+    fn get(&self, entity: Entity) -> Option<&C> {
+        if self.generations.get(entity.index) != Some(&entity.generation) {
+            return None;
+        }
+
+        self.get_index(entity.index)
+    }
+
+    fn get_index(&self, entity: usize) -> Option<&C> {
+        self.slots.get(entity)?.as_ref()
+    }
+
+    fn get_mut(&mut self, entity: Entity) -> Option<&mut C> {
+        if self.generations.get(entity.index) != Some(&entity.generation) {
+            return None;
+        }
+
+        self.get_index_mut(entity.index)
+    }
+
+    fn get_index_mut(&mut self, entity: usize) -> Option<&mut C> {
+        self.slots.get_mut(entity)?.as_mut()
+    }
+
+    fn len(&self) -> usize {
+        self.end
+    }
+
+    fn count(&self) -> usize {
+        self.count
+    }
+
+    fn version(&self) -> u64 {
+        self.version
+    }
+
+    fn iter_indices(&self) -> impl Iterator<Item = usize> + '_ {
+        self.raw_slots()
+            .iter()
+            .enumerate()
+            .filter_map(|(index, slot)| slot.as_ref().map(|_| index))
+    }
+
+    fn for_each<F>(&self, mut f: F)
+    where
+        F: FnMut(usize, &C),
+    {
+        for (index, slot) in self.raw_slots().iter().enumerate() {
+            if let Some(component) = slot {
+                f(index, component);
+            }
+        }
+    }
+
+    fn for_each_mut<F>(&mut self, mut f: F)
+    where
+        F: FnMut(usize, &mut C),
+    {
+        for (index, slot) in self.slots[..self.end].iter_mut().enumerate() {
+            if let Some(component) = slot {
+                f(index, component);
+            }
+        }
+    }
+
+    fn generation_at(&self, index: usize) -> u32 {
+        self.generations.get(index).copied().unwrap_or(0)
+    }
+
+    fn storage_kind(&self) -> StorageKind {
+        StorageKind::Array
+    }
+
+    fn check_invariants(&self) -> bool {
+        let live = self
+            .raw_slots()
+            .iter()
+            .filter(|slot| slot.is_some())
+            .count();
+        live == self.count && self.slots[self.end..].iter().all(Option::is_none)
+    }
+}
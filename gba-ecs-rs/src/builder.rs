@@ -0,0 +1,52 @@
+use crate::{Entity, GetComponentContainer, World, WorldContainer};
+
+/// Adds components to a freshly spawned entity, obtained through
+/// [`World::spawn_with`].
+///
+/// ```ignore
+/// let player = world
+///     .spawn_with()
+///     .with(Position { x: 0, y: 0 })
+///     .with(Velocity { dx: 1, dy: 0 })
+///     .build();
+/// ```
+///
+/// The entity exists as soon as the builder does; dropping the builder
+/// without calling `build` keeps it, with the components added so far.
+pub struct EntityBuilder<'w, WC: WorldContainer> {
+    world: &'w mut World<WC>,
+    entity: Entity,
+}
+
+impl<'w, WC: WorldContainer> EntityBuilder<'w, WC> {
+    /// Adds `component` to the entity, replacing any earlier one of the same type.
+    pub fn with<C>(self, component: C) -> Self
+    where
+        WC: GetComponentContainer<C>,
+    {
+        self.world.add(self.entity, component);
+        self
+    }
+
+    /// Returns the entity being built.
+    pub fn entity(&self) -> Entity {
+        self.entity
+    }
+
+    /// Finishes the entity and returns its handle.
+    pub fn build(self) -> Entity {
+        self.entity
+    }
+}
+
+impl<WC: WorldContainer> World<WC> {
+    /// Spawns an entity and returns a builder adding its components, all
+    /// through a single borrow of the world.
+    pub fn spawn_with(&mut self) -> EntityBuilder<'_, WC> {
+        let entity = self.spawn();
+        EntityBuilder {
+            world: self,
+            entity,
+        }
+    }
+}
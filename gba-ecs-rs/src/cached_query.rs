@@ -0,0 +1,101 @@
+use core::marker::PhantomData;
+
+use alloc::vec::Vec;
+
+use crate::instrumentation::record_visit;
+use crate::{Query, World, WorldContainer};
+
+/// A query that remembers which entities matched, for entity sets that rarely
+/// change shape.
+///
+/// The matches are stored together with [`Query::version`]. As long as no
+/// component the query reads is added or removed, `for_each` replays the
+/// stored indices instead of matching again. Changing component values does
+/// not invalidate the cache. Disabled entities are kept in the cache and
+/// skipped on replay, so enabling or disabling one does not invalidate it.
+///
+/// Create one with [`World::cached_query`] and keep it across frames.
+pub struct CachedQuery<Q, WC> {
+    indices: Vec<usize>,
+    /// Query version the indices were collected at, `None` before the first run.
+    version: Option<u64>,
+    _marker: PhantomData<fn() -> (Q, WC)>,
+}
+
+impl<Q, WC: WorldContainer> CachedQuery<Q, WC> {
+    pub const fn new() -> Self {
+        Self {
+            indices: Vec::new(),
+            version: None,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Returns whether the next `for_each` on `world` will match again.
+    pub fn is_dirty<'w>(&self, world: &'w World<WC>) -> bool
+    where
+        Q: Query<'w, WC>,
+    {
+        self.version != Some(Q::version(world))
+    }
+
+    /// Returns the number of cached matches, disabled entities included.
+    pub fn len(&self) -> usize {
+        self.indices.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.indices.is_empty()
+    }
+
+    /// Iterates over all enabled entities that match the query, matching
+    /// again first if the cache is dirty.
+    pub fn for_each<'w, F>(&mut self, world: &'w World<WC>, mut f: F)
+    where
+        Q: Query<'w, WC>,
+        F: FnMut(usize, Q::Item),
+    {
+        self.refresh(world);
+
+        for &entity_index in &self.indices {
+            if world.is_index_disabled(entity_index) {
+                continue;
+            }
+            if let Some(item) = Q::fetch(world, entity_index) {
+                record_visit();
+                f(entity_index, item);
+            }
+        }
+    }
+
+    fn refresh<'w>(&mut self, world: &'w World<WC>)
+    where
+        Q: Query<'w, WC>,
+    {
+        let version = Q::version(world);
+        if self.version == Some(version) {
+            return;
+        }
+
+        self.indices.clear();
+        Q::for_each_include_disabled(world, |entity_index, _| {
+            self.indices.push(entity_index);
+        });
+        self.version = Some(version);
+    }
+}
+
+impl<Q, WC: WorldContainer> Default for CachedQuery<Q, WC> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<WC: WorldContainer> World<WC> {
+    /// Returns a [`CachedQuery`] for `Q`, already filled with the current matches.
+    pub fn cached_query<'w, Q: Query<'w, WC>>(&'w self) -> CachedQuery<Q, WC> {
+        let mut cached = CachedQuery::new();
+        cached.refresh(self);
+        cached
+    }
+}
@@ -1,34 +1,340 @@
 extern crate alloc;
 
-use crate::Entity;
+use alloc::vec::Vec;
+use core::ops::ControlFlow;
+
+use crate::{Entity, Entry};
+
+/// The kind of storage backing a component container.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StorageKind {
+    /// A [`VecComponentContainer`](crate::VecComponentContainer) using the
+    /// `Global` allocator, which queries iterate through the vec fast path.
+    Vec,
+    /// A [`VecComponentContainer`](crate::VecComponentContainer) using another
+    /// allocator, which queries iterate through the generic fallback path, see
+    /// [`VecAllocator`](crate::VecAllocator).
+    VecInAllocator,
+    /// A [`HashComponentContainer`](crate::HashComponentContainer).
+    Hash,
+    /// A [`PackedComponentContainer`](crate::PackedComponentContainer).
+    Packed,
+    /// An [`ArrayComponentContainer`](crate::ArrayComponentContainer).
+    Array,
+    /// Any other container implementation.
+    Other,
+}
+
+/// Reasons a component can fail to be stored by `try_set` or `World::try_add`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SetError {
+    /// The entity is not the current occupant of its index, for instance
+    /// because it was despawned or comes from another world.
+    StaleEntity,
+    /// The container cannot hold a component at this index.
+    IndexOutOfRange(usize),
+    /// Growing the container to reach the index failed.
+    AllocationFailed,
+}
+
+/// Returns whether `entity`'s index holds a component that `get` does not
+/// hand out for `entity`, i.e. one set for another generation.
+///
+/// Writing through such a handle would overwrite the current occupant's
+/// component.
+pub(crate) fn is_stale<C, S: ComponentContainer<C> + ?Sized>(
+    container: &S,
+    entity: Entity,
+) -> bool {
+    container.get_index(entity.index).is_some() && container.get(entity).is_none()
+}
 
 pub trait ComponentContainer<C> {
+    /// Whether `for_each` visits components in an order fixed by the
+    /// container's own rules, ascending index or insertion order, rather than
+    /// one left to a hash function.
+    ///
+    /// Swapping an ordered container for an unordered one keeps the set of
+    /// entities a query visits but may change the order.
+    const ORDERED: bool = false;
+
     fn add_entity(&mut self, entity: Entity);
+    fn remove_entity(&mut self, entity: Entity);
+
+    /// Removes the component of `entity`, returning it if there was one.
+    fn remove(&mut self, entity: Entity) -> Option<C>;
+
+    /// Removes every component and entity slot, keeping allocated capacity.
+    fn clear(&mut self);
+
+    /// Reserves room for at least `additional` more entities, so adding them
+    /// does not reallocate mid-frame.
+    ///
+    /// Does nothing by default, for containers that do not preallocate.
+    fn reserve(&mut self, _additional: usize) {}
+
+    /// Frees memory no live component needs, without moving any component.
+    ///
+    /// Does nothing by default.
+    fn shrink_to_fit(&mut self) {}
+
+    /// Moves every component of `other` into this container, shifting each
+    /// entity index by `offset`.
+    fn merge_from(&mut self, other: Self, offset: usize)
+    where
+        Self: Sized;
+
+    /// Replaces the contents of `other` with a copy of every component in this container.
+    fn clone_into(&self, other: &mut Self)
+    where
+        Self: Sized,
+        C: Clone,
+    {
+        let stale: Vec<usize> = other.iter_indices().collect();
+        for index in stale {
+            other.remove_entity(Entity::new(index));
+        }
+
+        for index in self.iter_indices() {
+            if let Some(component) = self.get_index(index) {
+                let entity = Entity::new(index);
+                other.add_entity(entity);
+                other.set(entity, component.clone());
+            }
+        }
+    }
+
+    /// Stores `component` for `entity`, replacing any previous one.
+    ///
+    /// Growable containers fit `entity` as needed, so `add_entity` need not
+    /// have been called for it.
     fn set(&mut self, entity: Entity, component: C);
     fn get(&self, entity: Entity) -> Option<&C>;
+
+    /// Like `set`, but grows the container as needed and reports failures
+    /// instead of panicking.
+    ///
+    /// Fails with [`SetError::StaleEntity`] if the slot holds a component set
+    /// for another generation, rather than overwriting it. Otherwise the
+    /// default relies on `add_entity`, so it only fails if that panics.
+    fn try_set(&mut self, entity: Entity, component: C) -> Result<(), SetError> {
+        if is_stale(self, entity) {
+            return Err(SetError::StaleEntity);
+        }
+        self.add_entity(entity);
+        self.set(entity, component);
+        Ok(())
+    }
+    /// Returns whether `entity` currently has a component in this container.
+    fn contains(&self, entity: Entity) -> bool {
+        self.get(entity).is_some()
+    }
+
     fn get_index(&self, entity: usize) -> Option<&C>;
     fn get_mut(&mut self, entity: Entity) -> Option<&mut C>;
     fn get_index_mut(&mut self, entity: usize) -> Option<&mut C>;
     fn len(&self) -> usize;
+
+    /// Returns a counter that grows whenever a component is added to an empty
+    /// slot or removed, but not when a stored component is overwritten or
+    /// mutated. Cached queries compare it to know when to rebuild.
+    fn version(&self) -> u64;
+
+    /// Returns the number of components stored.
+    ///
+    /// Unlike `len`, which may count empty slots, this is what queries compare
+    /// to pick the container to iterate.
+    fn count(&self) -> usize {
+        self.iter_indices().count()
+    }
+
+    fn iter_indices(&self) -> impl Iterator<Item = usize> + '_;
     fn is_empty(&self) -> bool {
         self.len() == 0
     }
 
+    fn entry(&mut self, entity: Entity) -> Entry<'_, C, Self>
+    where
+        Self: Sized,
+    {
+        Entry::new(self, entity)
+    }
+
+    /// Visits every component with its entity index, in the container's
+    /// iteration order.
     fn for_each<F>(&self, f: F)
     where
         F: FnMut(usize, &C);
 
+    /// Visits the components at indices `0..max` by index lookup, in
+    /// ascending order, skipping any stored at `max` or above.
+    ///
+    /// When a hash container holds a component for nearly every index below
+    /// `max`, probing each index in order is cheaper than iterating the map;
+    /// queries switch to it on their own past that density.
+    fn for_each_dense<F>(&self, max: usize, mut f: F)
+    where
+        F: FnMut(usize, &C),
+    {
+        for index in 0..max {
+            if let Some(component) = self.get_index(index) {
+                f(index, component);
+            }
+        }
+    }
+
+    /// Mutable variant of `for_each`. Marks nothing as changed.
     fn for_each_mut<F>(&mut self, f: F)
     where
         F: FnMut(usize, &mut C);
 
+    /// Like `for_each`, but stops as soon as `f` returns
+    /// [`ControlFlow::Break`], returning that value.
+    fn try_for_each<B, F>(&self, mut f: F) -> ControlFlow<B>
+    where
+        F: FnMut(usize, &C) -> ControlFlow<B>,
+    {
+        for index in self.iter_indices() {
+            if let Some(component) = self.get_index(index) {
+                f(index, component)?;
+            }
+        }
+
+        ControlFlow::Continue(())
+    }
+
+    /// Returns the generation of the entity the component at `index` was set
+    /// for. Containers that do not track generations report 0.
+    fn generation_at(&self, _index: usize) -> u32 {
+        0
+    }
+
+    /// Like `for_each`, but hands out each component's [`Entity`], generation
+    /// included, so it can be passed straight back to `get` or the world.
+    fn for_each_entity<F>(&self, mut f: F)
+    where
+        F: FnMut(Entity, &C),
+    {
+        self.for_each(|index, component| {
+            f(
+                Entity::with_generation(index, self.generation_at(index)),
+                component,
+            )
+        });
+    }
+
+    /// Removes every component for which `pred` returns true, handing each one
+    /// to `f` along with its index.
+    ///
+    /// The default collects the matching indices before removing anything, so
+    /// removal never disturbs the walk.
+    fn drain_where<P, F>(&mut self, mut pred: P, mut f: F)
+    where
+        P: FnMut(&C) -> bool,
+        F: FnMut(usize, C),
+    {
+        let mut matching = Vec::new();
+        self.for_each(|index, component| {
+            if pred(component) {
+                matching.push(index);
+            }
+        });
+
+        for index in matching {
+            let entity = Entity::with_generation(index, self.generation_at(index));
+            if let Some(component) = self.remove(entity) {
+                f(index, component);
+            }
+        }
+    }
+
+    /// Exchanges the components of `a` and `b`, each keeping its own
+    /// generation.
+    ///
+    /// If only one of them has a component, it moves to the other; if neither
+    /// does, nothing happens. Like `remove`, slots are looked up by index
+    /// alone, so callers should only pass current entities.
+    fn swap(&mut self, a: Entity, b: Entity) {
+        if a.index == b.index {
+            return;
+        }
+
+        let component_a = self.remove(a);
+        let component_b = self.remove(b);
+        if let Some(component) = component_b {
+            self.set(a, component);
+        }
+        if let Some(component) = component_a {
+            self.set(b, component);
+        }
+    }
+
+    /// Moves the component of `from` to `to`, replacing any component `to`
+    /// had. Returns whether `from` had a component to move.
+    ///
+    /// Like `swap`, slots are looked up by index alone.
+    fn move_component(&mut self, from: Entity, to: Entity) -> bool {
+        if from.index == to.index {
+            return self.get_index(from.index).is_some();
+        }
+
+        match self.remove(from) {
+            Some(component) => {
+                self.set(to, component);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Visits the components written since the last `clear_change_ticks`.
+    ///
+    /// Containers that do not track changes visit every component. Note that
+    /// `get_mut` marks a component as changed even if the caller never writes
+    /// through the returned reference, while `for_each_mut` marks nothing.
+    fn for_each_changed<F>(&self, f: F)
+    where
+        F: FnMut(usize, &C),
+    {
+        self.for_each(f);
+    }
+
+    /// Forgets which components changed, typically at the end of a frame.
+    fn clear_change_ticks(&mut self) {}
+
     fn is_vec_container(&self) -> bool {
         false
     }
+
+    fn is_hash_container(&self) -> bool {
+        false
+    }
+
+    /// Reports how the container stores its components.
+    ///
+    /// Unlike `is_vec_container`, this does not depend on the allocator, so a
+    /// vec container that still takes the query fallback reports `Vec`.
+    fn storage_kind(&self) -> StorageKind {
+        StorageKind::Other
+    }
+
+    /// Checks the container's internal bookkeeping, returning `false` if it is corrupted.
+    fn check_invariants(&self) -> bool {
+        true
+    }
 }
 
 pub trait GetComponentContainer<C> {
     type Container: ComponentContainer<C>;
+
+    /// Bit of `C` in entity signatures, see [`World::for_each_matching`].
+    ///
+    /// Assigned by the [`world!`](crate::world!) macro; `None` leaves `C` out
+    /// of signatures.
+    ///
+    /// [`World::for_each_matching`]: crate::World::for_each_matching
+    const COMPONENT_ID: Option<u8> = None;
+
     fn get_components(&self) -> &Self::Container;
     fn get_components_mut(&mut self) -> &mut Self::Container;
 }
@@ -1,10 +1,47 @@
 #[derive(Clone, Copy)]
 pub struct Entity {
     pub(crate) index: usize,
+    pub(crate) generation: u32,
+    /// Id of the world that spawned the entity, 0 for handles built by hand.
+    /// Only tracked with debug assertions, to catch cross-world mix-ups.
+    #[cfg(debug_assertions)]
+    pub(crate) world_id: u16,
 }
 
 impl Entity {
     pub fn new(index: usize) -> Self {
-        Entity { index }
+        Self::with_generation(index, 0)
     }
+
+    /// Creates a handle to the entity at `index` as of the given generation.
+    ///
+    /// Containers only hand out a component when the handle's generation matches
+    /// the one the component was set with.
+    pub fn with_generation(index: usize, generation: u32) -> Self {
+        Entity {
+            index,
+            generation,
+            #[cfg(debug_assertions)]
+            world_id: 0,
+        }
+    }
+
+    /// Returns the entity's index, the one queries pass to their closures.
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    pub fn generation(&self) -> u32 {
+        self.generation
+    }
+}
+
+/// Why a checked lookup could not tell whether an entity has a component.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EntityError {
+    /// The index was never reached by the world or container.
+    OutOfRange(usize),
+    /// The handle's generation is not the current one at its index: the
+    /// entity was despawned, or the handle was built by hand.
+    Stale,
 }
@@ -0,0 +1,67 @@
+use core::marker::PhantomData;
+
+use crate::container::is_stale;
+use crate::{ComponentContainer, Entity};
+
+/// A view into one entity's slot in a component container, obtained through
+/// [`ComponentContainer::entry`].
+///
+/// Insertions go through the container's `set`, so its bookkeeping (such as
+/// `VecComponentContainer`'s active indices) stays consistent.
+pub struct Entry<'a, C, S: ComponentContainer<C>> {
+    container: &'a mut S,
+    entity: Entity,
+    _phantom: PhantomData<C>,
+}
+
+impl<'a, C, S: ComponentContainer<C>> Entry<'a, C, S> {
+    pub(crate) fn new(container: &'a mut S, entity: Entity) -> Self {
+        Self {
+            container,
+            entity,
+            _phantom: PhantomData,
+        }
+    }
+
+    pub fn entity(&self) -> Entity {
+        self.entity
+    }
+
+    /// Inserts `default` if the entity has no component, then returns the component.
+    ///
+    /// See [`Entry::or_insert_with`] for stale handles.
+    pub fn or_insert(self, default: C) -> Option<&'a mut C> {
+        self.or_insert_with(|| default)
+    }
+
+    /// Inserts the result of `default` if the entity has no component, then returns the component.
+    ///
+    /// Returns `None` without calling `default` if the slot holds a component
+    /// set for another generation, so a stale handle never overwrites the
+    /// component of the entity now using its index.
+    pub fn or_insert_with<F>(self, default: F) -> Option<&'a mut C>
+    where
+        F: FnOnce() -> C,
+    {
+        if is_stale(self.container, self.entity) {
+            return None;
+        }
+        if self.container.get(self.entity).is_none() {
+            self.container.set(self.entity, default());
+        }
+
+        self.container.get_mut(self.entity)
+    }
+
+    /// Calls `f` on the component if the entity has one.
+    pub fn and_modify<F>(self, f: F) -> Self
+    where
+        F: FnOnce(&mut C),
+    {
+        if let Some(component) = self.container.get_mut(self.entity) {
+            f(component);
+        }
+
+        self
+    }
+}
@@ -0,0 +1,117 @@
+use alloc::alloc::{Allocator, Global};
+use alloc::vec::{Drain, Vec};
+use core::mem;
+use core::slice::Iter;
+
+use crate::{World, WorldContainer};
+
+/// A double-buffered queue of `E` events.
+///
+/// Events sent during a frame become readable after the next
+/// [`EventChannel::update`], and are dropped by the update after that. Systems
+/// can therefore write events in one pass and read them in the next, whatever
+/// order they run in.
+pub struct EventChannel<E, A: Allocator = Global> {
+    /// Events sent since the last update.
+    current: Vec<E, A>,
+    /// Events sent during the previous frame, the ones `read` and `drain` see.
+    previous: Vec<E, A>,
+}
+
+impl<E> EventChannel<E> {
+    pub const fn new() -> Self {
+        Self {
+            current: Vec::new(),
+            previous: Vec::new(),
+        }
+    }
+}
+
+impl<E> Default for EventChannel<E> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<E, A: Allocator + Clone> EventChannel<E, A> {
+    pub fn new_in(allocator: A) -> Self {
+        Self {
+            current: Vec::new_in(allocator.clone()),
+            previous: Vec::new_in(allocator),
+        }
+    }
+}
+
+impl<E, A: Allocator> EventChannel<E, A> {
+    /// Queues `event`, readable after the next update.
+    pub fn send(&mut self, event: E) {
+        self.current.push(event);
+    }
+
+    /// Returns the events sent during the previous frame.
+    pub fn read(&self) -> Iter<'_, E> {
+        self.previous.iter()
+    }
+
+    /// Takes the events sent during the previous frame out of the channel.
+    pub fn drain(&mut self) -> Drain<'_, E, A> {
+        self.previous.drain(..)
+    }
+
+    /// Drops every event, whether already readable or not.
+    pub fn clear(&mut self) {
+        self.current.clear();
+        self.previous.clear();
+    }
+
+    /// Makes this frame's events readable and drops the previous frame's.
+    ///
+    /// Both buffers keep their capacity, so a steady event rate does not allocate.
+    pub fn update(&mut self) {
+        mem::swap(&mut self.current, &mut self.previous);
+        self.current.clear();
+    }
+}
+
+/// Gives access to the world's channel of `E` events.
+///
+/// Usually generated by the `events { ... }` section of the
+/// [`world!`](crate::world!) macro.
+pub trait GetEventChannel<E> {
+    type Allocator: Allocator;
+    fn get_events(&self) -> &EventChannel<E, Self::Allocator>;
+    fn get_events_mut(&mut self) -> &mut EventChannel<E, Self::Allocator>;
+}
+
+impl<WC: WorldContainer> World<WC> {
+    /// Returns the channel of `E` events.
+    pub fn events<E>(&self) -> &EventChannel<E, <WC as GetEventChannel<E>>::Allocator>
+    where
+        WC: GetEventChannel<E>,
+    {
+        self.containers.get_events()
+    }
+
+    /// Mutable variant of [`World::events`].
+    pub fn events_mut<E>(&mut self) -> &mut EventChannel<E, <WC as GetEventChannel<E>>::Allocator>
+    where
+        WC: GetEventChannel<E>,
+    {
+        self.containers.get_events_mut()
+    }
+
+    /// Queues `event` on the channel of `E` events.
+    pub fn send_event<E>(&mut self, event: E)
+    where
+        WC: GetEventChannel<E>,
+    {
+        self.containers.get_events_mut().send(event);
+    }
+
+    /// Updates every event channel of the world, see [`EventChannel::update`].
+    ///
+    /// Call it once per frame.
+    pub fn update_events(&mut self) {
+        self.containers.update_events();
+    }
+}
@@ -1,15 +1,22 @@
 use agb::hash_map::HashMap;
 use alloc::alloc::Allocator;
 use alloc::alloc::Global;
+use alloc::vec::Vec;
 
 pub struct HashComponentContainer<C, A: Allocator = Global> {
     container: HashMap<usize, C, A>,
+    /// Generation of the entity each component was set for.
+    generations: HashMap<usize, u32, A>,
+    /// Structural version, see `ComponentContainer::version`.
+    version: u64,
 }
 
 impl<C> HashComponentContainer<C> {
     pub fn new() -> Self {
         Self {
             container: HashMap::new(),
+            generations: HashMap::new(),
+            version: 0,
         }
     }
 }
@@ -23,28 +30,84 @@ impl<C> Default for HashComponentContainer<C> {
 impl<C, A: Allocator + Clone> HashComponentContainer<C, A> {
     pub fn new_in(allocator: A) -> Self {
         Self {
-            container: HashMap::new_in(allocator),
+            container: HashMap::new_in(allocator.clone()),
+            generations: HashMap::new_in(allocator),
+            version: 0,
         }
     }
 
-    #[inline]
-    pub fn for_each<F>(&self, mut f: F)
+    /// Creates a container with room for `capacity` components before it grows.
+    ///
+    /// The maps are allocated up front from `allocator`, one entry per
+    /// component and one per generation, so keep `capacity` close to the
+    /// actual number of components.
+    pub fn with_capacity_in(capacity: usize, allocator: A) -> Self {
+        Self {
+            container: HashMap::with_capacity_in(capacity, allocator.clone()),
+            generations: HashMap::with_capacity_in(capacity, allocator),
+            version: 0,
+        }
+    }
+
+    /// Returns an iterator over the components, in no particular order.
+    pub fn iter(&self) -> impl Iterator<Item = (usize, &C)> + '_ {
+        self.container
+            .iter()
+            .map(|(&index, component)| (index, component))
+    }
+
+    /// Mutable variant of [`HashComponentContainer::iter`].
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (usize, &mut C)> + '_ {
+        self.container
+            .iter_mut()
+            .map(|(&index, component)| (index, component))
+    }
+
+    /// Like [`ComponentContainer::for_each`](crate::ComponentContainer::for_each), but visits components in
+    /// ascending index order, the same on every run.
+    ///
+    /// Pays for determinism with a temporary `Vec` of every key (4 bytes per
+    /// component on the GBA) and an O(n log n) sort on each call. Use it for
+    /// replays and lockstep logic, and `for_each` where order does not matter.
+    pub fn for_each_ordered<F>(&self, mut f: F)
     where
         F: FnMut(usize, &C),
     {
-        self.container.iter().for_each(|(index, component)| {
-            f(*index, component);
-        });
+        let mut indices: Vec<usize> = self.container.keys().copied().collect();
+        indices.sort_unstable();
+
+        for index in indices {
+            if let Some(component) = self.container.get(&index) {
+                f(index, component);
+            }
+        }
+    }
+
+    /// Moves every component out as `(index, component)` pairs, in no
+    /// particular order, leaving the container empty.
+    ///
+    /// The maps keep their capacity, so the container can be filled again
+    /// without growing. The returned `Vec` uses the container's allocator.
+    pub fn take_all(&mut self) -> Vec<(usize, C), A> {
+        let indices: Vec<usize> = self.container.keys().copied().collect();
+        let mut taken = Vec::with_capacity_in(indices.len(), self.container.allocator().clone());
+        for index in indices {
+            if let Some(component) = self.container.remove(&index) {
+                taken.push((index, component));
+            }
+        }
+        self.generations.clear();
+
+        if !taken.is_empty() {
+            self.version += 1;
+        }
+        taken
     }
 
+    /// Returns whether the component at `entity`'s index was set for its generation.
     #[inline]
-    pub fn for_each_mut<F>(&mut self, mut f: F)
-    where
-        F: FnMut(usize, &mut C),
-    {
-        self.container.iter_mut().for_each(|(index, component)| {
-            f(*index, component);
-        });
+    fn is_current(&self, entity: crate::Entity) -> bool {
+        self.generations.get(&entity.index) == Some(&entity.generation)
     }
 }
 
@@ -52,17 +115,78 @@ impl<C, A: Allocator + Clone> crate::ComponentContainer<C> for HashComponentCont
     #[inline]
     fn add_entity(&mut self, _entity: crate::Entity) {}
 
+    #[inline]
+    fn remove_entity(&mut self, entity: crate::Entity) {
+        self.remove(entity);
+    }
+
+    #[inline]
+    fn remove(&mut self, entity: crate::Entity) -> Option<C> {
+        self.generations.remove(&entity.index);
+        let component = self.container.remove(&entity.index)?;
+        self.version += 1;
+        Some(component)
+    }
+
+    fn clear(&mut self) {
+        self.container.clear();
+        self.generations.clear();
+        self.version += 1;
+    }
+
+    fn merge_from(&mut self, mut other: Self, offset: usize) {
+        let indices: Vec<usize> = other.container.keys().copied().collect();
+
+        for index in indices {
+            if let Some(component) = other.container.remove(&index) {
+                let generation = other.generations.remove(&index).unwrap_or(0);
+                self.set(
+                    crate::Entity::with_generation(index + offset, generation),
+                    component,
+                );
+            }
+        }
+    }
+
+    fn clone_into(&self, other: &mut Self)
+    where
+        C: Clone,
+    {
+        other.container.clear();
+        other.generations.clear();
+
+        for (&index, component) in self.container.iter() {
+            other.container.insert(index, component.clone());
+        }
+        for (&index, &generation) in self.generations.iter() {
+            other.generations.insert(index, generation);
+        }
+        other.version += 1;
+    }
+
     #[inline]
     fn set(&mut self, entity: crate::Entity, component: C) {
-        self.container.insert(entity.index, component);
+        if self.container.insert(entity.index, component).is_none() {
+            self.version += 1;
+        }
+        self.generations.insert(entity.index, entity.generation);
     }
 
     #[inline]
     fn get(&self, entity: crate::Entity) -> Option<&C> {
+        if !self.is_current(entity) {
+            return None;
+        }
+
         let index = entity.index;
         self.container.get(&index)
     }
 
+    #[inline]
+    fn contains(&self, entity: crate::Entity) -> bool {
+        self.container.contains_key(&entity.index) && self.is_current(entity)
+    }
+
     #[inline]
     fn get_index(&self, entity: usize) -> Option<&C> {
         self.container.get(&entity)
@@ -70,6 +194,10 @@ impl<C, A: Allocator + Clone> crate::ComponentContainer<C> for HashComponentCont
 
     #[inline]
     fn get_mut(&mut self, entity: crate::Entity) -> Option<&mut C> {
+        if !self.is_current(entity) {
+            return None;
+        }
+
         let index = entity.index;
         self.container.get_mut(&index)
     }
@@ -84,6 +212,32 @@ impl<C, A: Allocator + Clone> crate::ComponentContainer<C> for HashComponentCont
         self.container.len()
     }
 
+    #[inline]
+    fn count(&self) -> usize {
+        self.container.len()
+    }
+
+    fn version(&self) -> u64 {
+        self.version
+    }
+
+    fn iter_indices(&self) -> impl Iterator<Item = usize> + '_ {
+        self.container.keys().copied()
+    }
+
+    fn is_hash_container(&self) -> bool {
+        true
+    }
+
+    fn generation_at(&self, index: usize) -> u32 {
+        self.generations.get(&index).copied().unwrap_or(0)
+    }
+
+    fn storage_kind(&self) -> crate::StorageKind {
+        crate::StorageKind::Hash
+    }
+
+    #[inline]
     fn for_each<F>(&self, mut f: F)
     where
         F: FnMut(usize, &C),
@@ -93,6 +247,16 @@ impl<C, A: Allocator + Clone> crate::ComponentContainer<C> for HashComponentCont
         });
     }
 
+    fn try_for_each<B, F>(&self, mut f: F) -> core::ops::ControlFlow<B>
+    where
+        F: FnMut(usize, &C) -> core::ops::ControlFlow<B>,
+    {
+        self.container
+            .iter()
+            .try_for_each(|(index, component)| f(*index, component))
+    }
+
+    #[inline]
     fn for_each_mut<F>(&mut self, mut f: F)
     where
         F: FnMut(usize, &mut C),
@@ -0,0 +1,54 @@
+//! Optional query counters for tuning world layouts.
+//!
+//! With the `instrumentation` feature enabled, queries record how many entities
+//! they visited and how many per-entity lookups the fallback paths performed.
+//! The counters are global and never reset on their own; read them with
+//! [`query_counters`] and clear them with [`reset_query_counters`].
+//!
+//! Without the feature the recording hooks are empty and compile away.
+
+#[cfg(feature = "instrumentation")]
+use portable_atomic::{AtomicUsize, Ordering};
+
+#[cfg(feature = "instrumentation")]
+static VISITED: AtomicUsize = AtomicUsize::new(0);
+#[cfg(feature = "instrumentation")]
+static LOOKUPS: AtomicUsize = AtomicUsize::new(0);
+
+/// Snapshot of the query counters.
+#[cfg(feature = "instrumentation")]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct QueryCounters {
+    /// Number of entities passed to query closures.
+    pub visited: usize,
+    /// Number of per-entity `get_index` lookups done by fallback paths.
+    pub lookups: usize,
+}
+
+/// Returns the counters accumulated since the last reset.
+#[cfg(feature = "instrumentation")]
+pub fn query_counters() -> QueryCounters {
+    QueryCounters {
+        visited: VISITED.load(Ordering::Relaxed),
+        lookups: LOOKUPS.load(Ordering::Relaxed),
+    }
+}
+
+/// Sets every counter back to zero.
+#[cfg(feature = "instrumentation")]
+pub fn reset_query_counters() {
+    VISITED.store(0, Ordering::Relaxed);
+    LOOKUPS.store(0, Ordering::Relaxed);
+}
+
+#[inline(always)]
+pub(crate) fn record_visit() {
+    #[cfg(feature = "instrumentation")]
+    VISITED.fetch_add(1, Ordering::Relaxed);
+}
+
+#[inline(always)]
+pub(crate) fn record_lookup() {
+    #[cfg(feature = "instrumentation")]
+    LOOKUPS.fetch_add(1, Ordering::Relaxed);
+}
@@ -1,21 +1,77 @@
+//! A small entity component system for the Game Boy Advance.
+//!
+//! Components are plain types: any `struct` or `enum` can be stored without
+//! deriving or implementing anything, and no container keeps intrusive link
+//! fields in them. A component costs `size_of::<Option<C>>()` per slot in a
+//! [`VecComponentContainer`] and one map entry in a [`HashComponentContainer`].
+
 #![no_std]
 #![feature(allocator_api)]
 
 extern crate alloc;
 
+mod adaptive_container;
+mod array_container;
+mod builder;
+mod cached_query;
 mod container;
 mod entity;
+mod entry;
+mod events;
 mod hash_container;
+mod instrumentation;
+mod packed_container;
+mod pooled_container;
 mod query;
+mod query_mut;
+mod relation;
+mod resource;
+mod schedule;
+mod snapshot;
+mod union_query;
 mod vec_container;
+mod view;
 mod world;
 mod zip;
 
-pub use container::{ComponentContainer, GetComponentContainer};
-pub use entity::Entity;
+pub use adaptive_container::AdaptiveComponentContainer;
+pub use array_container::ArrayComponentContainer;
+pub use builder::EntityBuilder;
+pub use cached_query::CachedQuery;
+pub use container::{ComponentContainer, GetComponentContainer, SetError, StorageKind};
+pub use entity::{Entity, EntityError};
+pub use entry::Entry;
+pub use events::{EventChannel, GetEventChannel};
 pub use hash_container::HashComponentContainer;
-pub use query::Query;
-pub use vec_container::VecComponentContainer;
+#[cfg(feature = "instrumentation")]
+pub use instrumentation::{query_counters, reset_query_counters, QueryCounters};
+pub use packed_container::PackedComponentContainer;
+pub use pooled_container::{PooledComponentContainer, Reusable};
+pub use query::{Opt, Query, QueryFilter, ValidationLevel, With, Without};
+pub use query_mut::QueryMut;
+pub use relation::{Children, Parent};
+pub use resource::GetResource;
+pub use schedule::{Schedule, Stage, System};
+pub use snapshot::{
+    save_container, ComponentId, Deserialize, RestoreError, Serialize, SnapshotBuffer,
+    WorldSnapshot, SNAPSHOT_VERSION,
+};
+pub use union_query::UnionQuery;
+pub use vec_container::{VecAllocator, VecComponentContainer};
+pub use view::WorldView;
 pub use world::World;
 pub use world::WorldContainer;
-pub use zip::{zip, zip3, ZippedQuery2, ZippedQuery3};
+
+/// `Global`, named through a stable path so `world!` can use it in crates
+/// without `allocator_api`.
+#[doc(hidden)]
+pub type __GlobalAllocator = alloc::alloc::Global;
+#[doc(hidden)]
+pub use snapshot::__id_occurrences;
+#[doc(hidden)]
+pub use world::__position;
+
+pub use zip::{
+    zip, zip3, zip3_mut, zip4, zip_mut, zip_vec_hash, ZippedQuery2, ZippedQuery3, ZippedQuery4,
+    ZippedQueryMut2, ZippedQueryMut3, ZippedVecHash, SPARSE_RATIO,
+};
@@ -0,0 +1,288 @@
+use crate::container::is_stale;
+use crate::ComponentContainer;
+use crate::Entity;
+use crate::SetError;
+use crate::StorageKind;
+use alloc::alloc::Allocator;
+use alloc::alloc::Global;
+use alloc::vec::Vec;
+
+/// Marks an entity index with no component in `sparse`.
+const EMPTY: usize = usize::MAX;
+
+/// A sparse set: components packed in a dense `Vec<C>`, without holes.
+///
+/// Iteration walks the dense array directly, so it never skips empty slots
+/// the way [`VecComponentContainer`](crate::VecComponentContainer) does.
+/// Removal swaps the last component into the freed slot, so iteration order
+/// changes as components are removed.
+pub struct PackedComponentContainer<C, A: Allocator = Global> {
+    /// The components, in no particular order.
+    dense: Vec<C, A>,
+    /// Entity index of each dense component.
+    entities: Vec<usize, A>,
+    /// Generation of the entity each dense component was set for.
+    generations: Vec<u32, A>,
+    /// Dense index of each entity's component, or `EMPTY`.
+    sparse: Vec<usize, A>,
+    /// Structural version, see `ComponentContainer::version`.
+    version: u64,
+}
+
+impl<C> PackedComponentContainer<C> {
+    pub const fn new() -> Self {
+        Self {
+            dense: Vec::new(),
+            entities: Vec::new(),
+            generations: Vec::new(),
+            sparse: Vec::new(),
+            version: 0,
+        }
+    }
+}
+
+impl<C> Default for PackedComponentContainer<C> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<C, A: Allocator + Clone> PackedComponentContainer<C, A> {
+    pub fn new_in(allocator: A) -> Self {
+        Self {
+            dense: Vec::new_in(allocator.clone()),
+            entities: Vec::new_in(allocator.clone()),
+            generations: Vec::new_in(allocator.clone()),
+            sparse: Vec::new_in(allocator),
+            version: 0,
+        }
+    }
+
+    /// Creates a container with room for `capacity` components and entities.
+    pub fn with_capacity_in(capacity: usize, allocator: A) -> Self {
+        Self {
+            dense: Vec::with_capacity_in(capacity, allocator.clone()),
+            entities: Vec::with_capacity_in(capacity, allocator.clone()),
+            generations: Vec::with_capacity_in(capacity, allocator.clone()),
+            sparse: Vec::with_capacity_in(capacity, allocator),
+            version: 0,
+        }
+    }
+
+    /// Returns the entity index of each component and the dense components.
+    ///
+    /// Both slices have the same length and are in the same order.
+    pub fn raw_parts(&self) -> (&[usize], &[C]) {
+        (&self.entities, &self.dense)
+    }
+
+    #[inline]
+    fn dense_index(&self, index: usize) -> Option<usize> {
+        match self.sparse.get(index) {
+            Some(&dense_index) if dense_index != EMPTY => Some(dense_index),
+            _ => None,
+        }
+    }
+
+    #[inline]
+    fn current_dense_index(&self, entity: Entity) -> Option<usize> {
+        self.dense_index(entity.index)
+            .filter(|&dense_index| self.generations[dense_index] == entity.generation)
+    }
+}
+
+impl<C, A: Allocator + Clone> ComponentContainer<C> for PackedComponentContainer<C, A> {
+    /// Dense array order: insertion order until a removal moves the last
+    /// component into the hole.
+    const ORDERED: bool = true;
+
+    fn add_entity(&mut self, entity: Entity) {
+        if self.sparse.len() <= entity.index {
+            self.sparse.resize(entity.index + 1, EMPTY);
+        }
+    }
+
+    fn remove_entity(&mut self, entity: Entity) {
+        self.remove(entity);
+    }
+
+    fn remove(&mut self, entity: Entity) -> Option<C> {
+        let dense_index = self.dense_index(entity.index)?;
+        self.sparse[entity.index] = EMPTY;
+
+        let component = self.dense.swap_remove(dense_index);
+        self.entities.swap_remove(dense_index);
+        self.generations.swap_remove(dense_index);
+
+        if let Some(&moved) = self.entities.get(dense_index) {
+            self.sparse[moved] = dense_index;
+        }
+        self.version += 1;
+
+        Some(component)
+    }
+
+    fn clear(&mut self) {
+        self.dense.clear();
+        self.entities.clear();
+        self.generations.clear();
+        self.sparse.clear();
+        self.version += 1;
+    }
+
+    fn reserve(&mut self, additional: usize) {
+        self.dense.reserve(additional);
+        self.entities.reserve(additional);
+        self.generations.reserve(additional);
+        self.sparse.reserve(additional);
+    }
+
+    /// Drops the unused entries past the highest live entity index, then
+    /// frees the spare capacity.
+    fn shrink_to_fit(&mut self) {
+        let len = self.entities.iter().max().map_or(0, |&index| index + 1);
+        self.sparse.truncate(len);
+
+        self.dense.shrink_to_fit();
+        self.entities.shrink_to_fit();
+        self.generations.shrink_to_fit();
+        self.sparse.shrink_to_fit();
+    }
+
+    fn merge_from(&mut self, other: Self, offset: usize) {
+        let components = other.dense.into_iter();
+        let entities = other.entities.into_iter().zip(other.generations);
+
+        for (component, (index, generation)) in components.zip(entities) {
+            let entity = Entity::with_generation(index + offset, generation);
+            self.add_entity(entity);
+            self.set(entity, component);
+        }
+    }
+
+    fn clone_into(&self, other: &mut Self)
+    where
+        C: Clone,
+    {
+        other.dense.clone_from(&self.dense);
+        other.entities.clone_from(&self.entities);
+        other.generations.clone_from(&self.generations);
+        other.sparse.clone_from(&self.sparse);
+        other.version += 1;
+    }
+
+    fn set(&mut self, entity: Entity, component: C) {
+        if let Some(dense_index) = self.dense_index(entity.index) {
+            self.dense[dense_index] = component;
+            self.generations[dense_index] = entity.generation;
+            return;
+        }
+
+        self.add_entity(entity);
+        self.sparse[entity.index] = self.dense.len();
+        self.dense.push(component);
+        self.entities.push(entity.index);
+        self.generations.push(entity.generation);
+        self.version += 1;
+    }
+
+    fn try_set(&mut self, entity: Entity, component: C) -> Result<(), SetError> {
+        if is_stale(self, entity) {
+            return Err(SetError::StaleEntity);
+        }
+        let index = entity.index;
+        let len = index
+            .checked_add(1)
+            .ok_or(SetError::IndexOutOfRange(index))?;
+
+        let additional = len.saturating_sub(self.sparse.len());
+        self.sparse
+            .try_reserve(additional)
+            .and(self.dense.try_reserve(1))
+            .and(self.entities.try_reserve(1))
+            .and(self.generations.try_reserve(1))
+            .map_err(|_| SetError::AllocationFailed)?;
+
+        self.set(entity, component);
+        Ok(())
+    }
+
+    fn get(&self, entity: Entity) -> Option<&C> {
+        let dense_index = self.current_dense_index(entity)?;
+        Some(&self.dense[dense_index])
+    }
+
+    fn get_index(&self, entity: usize) -> Option<&C> {
+        let dense_index = self.dense_index(entity)?;
+        Some(&self.dense[dense_index])
+    }
+
+    fn get_mut(&mut self, entity: Entity) -> Option<&mut C> {
+        let dense_index = self.current_dense_index(entity)?;
+        Some(&mut self.dense[dense_index])
+    }
+
+    fn get_index_mut(&mut self, entity: usize) -> Option<&mut C> {
+        let dense_index = self.dense_index(entity)?;
+        Some(&mut self.dense[dense_index])
+    }
+
+    fn len(&self) -> usize {
+        self.dense.len()
+    }
+
+    fn count(&self) -> usize {
+        self.dense.len()
+    }
+
+    fn version(&self) -> u64 {
+        self.version
+    }
+
+    fn iter_indices(&self) -> impl Iterator<Item = usize> + '_ {
+        self.entities.iter().copied()
+    }
+
+    fn for_each<F>(&self, mut f: F)
+    where
+        F: FnMut(usize, &C),
+    {
+        for (&index, component) in self.entities.iter().zip(&self.dense) {
+            f(index, component);
+        }
+    }
+
+    fn for_each_mut<F>(&mut self, mut f: F)
+    where
+        F: FnMut(usize, &mut C),
+    {
+        for (&index, component) in self.entities.iter().zip(&mut self.dense) {
+            f(index, component);
+        }
+    }
+
+    fn generation_at(&self, index: usize) -> u32 {
+        self.dense_index(index)
+            .map_or(0, |dense_index| self.generations[dense_index])
+    }
+
+    fn storage_kind(&self) -> StorageKind {
+        StorageKind::Packed
+    }
+
+    fn check_invariants(&self) -> bool {
+        if self.entities.len() != self.dense.len() || self.generations.len() != self.dense.len() {
+            return false;
+        }
+
+        let mapped = self.sparse.iter().filter(|&&d| d != EMPTY).count();
+        if mapped != self.dense.len() {
+            return false;
+        }
+
+        self.entities
+            .iter()
+            .enumerate()
+            .all(|(dense_index, &index)| self.sparse.get(index) == Some(&dense_index))
+    }
+}
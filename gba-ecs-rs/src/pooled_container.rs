@@ -0,0 +1,271 @@
+use core::ops::ControlFlow;
+
+use alloc::alloc::{Allocator, Global};
+use alloc::vec::Vec;
+
+use crate::{
+    ComponentContainer, Entity, GetComponentContainer, SetError, VecAllocator,
+    VecComponentContainer, World, WorldContainer,
+};
+
+/// A component that can be cleared and handed out again instead of dropped,
+/// for components owning heap memory such as a `Vec` of path points.
+pub trait Reusable {
+    /// Puts the component back in a blank state, keeping its allocations,
+    /// e.g. with `Vec::clear`.
+    fn reset(&mut self);
+}
+
+/// A [`VecComponentContainer`] that keeps the components it would drop in a
+/// pool, so their heap allocations survive despawn/spawn cycles.
+///
+/// Components dropped by the container itself, on despawn or `clear`, are
+/// reset and pooled. Those handed back to the caller, by `remove` or
+/// `drain_where`, are not, and neither is a component overwritten by `set`.
+/// Pooled components are reused by [`PooledComponentContainer::insert_with`]
+/// and [`World::add_pooled`]; plain `set` stores the component it is given.
+///
+/// Queries always take the generic path for this container, and it reports
+/// [`StorageKind::Other`](crate::StorageKind::Other). Components
+/// without heap data gain nothing from it and should use a plain
+/// `VecComponentContainer`.
+pub struct PooledComponentContainer<C: Reusable, A: Allocator + Clone = Global> {
+    inner: VecComponentContainer<C, A>,
+    pool: Vec<C, A>,
+}
+
+impl<C: Reusable> PooledComponentContainer<C> {
+    pub fn new() -> Self {
+        Self::new_in(Global)
+    }
+}
+
+impl<C: Reusable> Default for PooledComponentContainer<C> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<C: Reusable, A: VecAllocator> PooledComponentContainer<C, A> {
+    pub fn new_in(allocator: A) -> Self {
+        Self {
+            inner: VecComponentContainer::new_in(allocator.clone()),
+            pool: Vec::new_in(allocator),
+        }
+    }
+
+    /// Stores a component for `entity` taken from the pool, or
+    /// `C::default()` if the pool is empty, after `init` has filled it in.
+    pub fn insert_with<F>(&mut self, entity: Entity, init: F)
+    where
+        C: Default,
+        F: FnOnce(&mut C),
+    {
+        let mut component = self.pool.pop().unwrap_or_default();
+        init(&mut component);
+        self.inner.set(entity, component);
+    }
+
+    /// Resets `component` and puts it in the pool, e.g. after taking it out
+    /// with `remove`.
+    pub fn recycle(&mut self, mut component: C) {
+        component.reset();
+        self.pool.push(component);
+    }
+
+    /// Returns the number of components waiting in the pool.
+    pub fn pooled(&self) -> usize {
+        self.pool.len()
+    }
+
+    /// Drops every pooled component, freeing their allocations.
+    pub fn clear_pool(&mut self) {
+        self.pool.clear();
+    }
+}
+
+impl<C: Reusable, A: VecAllocator> ComponentContainer<C> for PooledComponentContainer<C, A> {
+    const ORDERED: bool = true;
+
+    fn add_entity(&mut self, entity: Entity) {
+        self.inner.add_entity(entity);
+    }
+
+    /// Pools the component of `entity` instead of dropping it.
+    fn remove_entity(&mut self, entity: Entity) {
+        if let Some(component) = self.inner.remove(entity) {
+            self.recycle(component);
+        }
+    }
+
+    fn remove(&mut self, entity: Entity) -> Option<C> {
+        self.inner.remove(entity)
+    }
+
+    /// Pools every component, then clears the slots.
+    fn clear(&mut self) {
+        let pool = &mut self.pool;
+        self.inner.drain_where(
+            |_| true,
+            |_, mut component| {
+                component.reset();
+                pool.push(component);
+            },
+        );
+        self.inner.clear();
+    }
+
+    fn reserve(&mut self, additional: usize) {
+        self.inner.reserve(additional);
+    }
+
+    /// Drops the pool along with the memory the slots no longer need.
+    fn shrink_to_fit(&mut self) {
+        self.inner.shrink_to_fit();
+        self.pool.clear();
+        self.pool.shrink_to_fit();
+    }
+
+    fn merge_from(&mut self, other: Self, offset: usize) {
+        self.inner.merge_from(other.inner, offset);
+        self.pool.extend(other.pool);
+    }
+
+    /// Copies the components only; `other` keeps its own pool.
+    fn clone_into(&self, other: &mut Self)
+    where
+        C: Clone,
+    {
+        self.inner.clone_into(&mut other.inner);
+    }
+
+    fn set(&mut self, entity: Entity, component: C) {
+        self.inner.set(entity, component);
+    }
+
+    fn try_set(&mut self, entity: Entity, component: C) -> Result<(), SetError> {
+        self.inner.try_set(entity, component)
+    }
+
+    fn get(&self, entity: Entity) -> Option<&C> {
+        self.inner.get(entity)
+    }
+
+    fn contains(&self, entity: Entity) -> bool {
+        self.inner.contains(entity)
+    }
+
+    fn get_index(&self, entity: usize) -> Option<&C> {
+        self.inner.get_index(entity)
+    }
+
+    fn get_mut(&mut self, entity: Entity) -> Option<&mut C> {
+        self.inner.get_mut(entity)
+    }
+
+    fn get_index_mut(&mut self, entity: usize) -> Option<&mut C> {
+        self.inner.get_index_mut(entity)
+    }
+
+    fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    fn version(&self) -> u64 {
+        self.inner.version()
+    }
+
+    fn count(&self) -> usize {
+        self.inner.count()
+    }
+
+    fn iter_indices(&self) -> impl Iterator<Item = usize> + '_ {
+        self.inner.iter_indices()
+    }
+
+    fn for_each<F>(&self, f: F)
+    where
+        F: FnMut(usize, &C),
+    {
+        self.inner.for_each(f);
+    }
+
+    fn for_each_mut<F>(&mut self, f: F)
+    where
+        F: FnMut(usize, &mut C),
+    {
+        self.inner.for_each_mut(f);
+    }
+
+    fn try_for_each<B, F>(&self, f: F) -> ControlFlow<B>
+    where
+        F: FnMut(usize, &C) -> ControlFlow<B>,
+    {
+        self.inner.try_for_each(f)
+    }
+
+    fn generation_at(&self, index: usize) -> u32 {
+        self.inner.generation_at(index)
+    }
+
+    fn drain_where<P, F>(&mut self, pred: P, f: F)
+    where
+        P: FnMut(&C) -> bool,
+        F: FnMut(usize, C),
+    {
+        self.inner.drain_where(pred, f);
+    }
+
+    fn swap(&mut self, a: Entity, b: Entity) {
+        self.inner.swap(a, b);
+    }
+
+    /// Pools the component `to` had, if any.
+    fn move_component(&mut self, from: Entity, to: Entity) -> bool {
+        if from.index != to.index && self.inner.get_index(from.index).is_some() {
+            if let Some(component) = self.inner.remove(Entity::new(to.index)) {
+                self.recycle(component);
+            }
+        }
+        self.inner.move_component(from, to)
+    }
+
+    fn for_each_changed<F>(&self, f: F)
+    where
+        F: FnMut(usize, &C),
+    {
+        self.inner.for_each_changed(f);
+    }
+
+    fn clear_change_ticks(&mut self) {
+        self.inner.clear_change_ticks();
+    }
+
+    fn check_invariants(&self) -> bool {
+        self.inner.check_invariants()
+    }
+}
+
+impl<WC: WorldContainer> World<WC> {
+    /// Like [`World::add`], but builds the component from the pool, see
+    /// [`PooledComponentContainer::insert_with`].
+    ///
+    /// Does nothing if `entity` has been despawned.
+    pub fn add_pooled<C, A, F>(&mut self, entity: Entity, init: F)
+    where
+        C: Reusable + Default,
+        A: VecAllocator,
+        WC: GetComponentContainer<C, Container = PooledComponentContainer<C, A>>,
+        F: FnOnce(&mut C),
+    {
+        if !self.is_current(entity) {
+            return;
+        }
+        self.materialize(entity);
+
+        self.get_mut::<C>().insert_with(entity, init);
+        if let Some(id) = <WC as GetComponentContainer<C>>::COMPONENT_ID {
+            self.set_signature_bit(entity.index, id, true);
+        }
+    }
+}
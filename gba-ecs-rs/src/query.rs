@@ -2,7 +2,7 @@
 //!
 //! This module provides a trait-based query system that allows for efficient
 //! iteration over entities that have specific component combinations. The system
-//! supports single component queries and tuple queries for multiple components (up to 3).
+//! supports single component queries and tuple queries for multiple components (up to 4).
 //!
 //! # Safety
 //!
@@ -36,22 +36,26 @@
 //! });
 //! ```
 
+use crate::instrumentation::{record_lookup, record_visit};
 use crate::{
-    zip, zip3, ComponentContainer, Entity, GetComponentContainer, VecComponentContainer, World,
-    WorldContainer,
+    zip, zip3, zip4, ComponentContainer, Entity, GetComponentContainer, StorageKind,
+    VecComponentContainer, World, WorldContainer, ZippedVecHash,
 };
 use alloc::alloc::Allocator;
 use alloc::alloc::Global;
+use alloc::vec::Vec;
+use core::marker::PhantomData;
 use core::mem::transmute;
+use core::ops::ControlFlow;
 
 /// Casts a generic ComponentContainer to a VecComponentContainer with runtime validation.
 ///
 /// # Safety
 ///
 /// This function includes runtime validation but is still unsafe because:
-/// 1. We cannot verify the allocator type A at runtime
-/// 2. Type casting still bypasses Rust's type system
-/// 3. The caller must ensure the allocator type matches
+/// 1. Type casting still bypasses Rust's type system
+/// 2. The caller must ensure the allocator type matches; `is_vec_container()` only
+///    returns true for `Global` vec containers (see `VEC_FAST_PATH_SAFE`)
 ///
 /// This function will panic in debug mode if is_vec_container() returns false,
 /// helping catch misuse during development.
@@ -67,7 +71,11 @@ use core::mem::transmute;
 /// # Panics
 ///
 /// Panics in debug mode if the container is not actually a VecComponentContainer
-unsafe fn cast_to_vec_container<C, A: Allocator + Clone, Container: ComponentContainer<C>>(
+pub(crate) unsafe fn cast_to_vec_container<
+    C,
+    A: Allocator + Clone,
+    Container: ComponentContainer<C>,
+>(
     container: &Container,
 ) -> &VecComponentContainer<C, A> {
     // Runtime validation in debug mode
@@ -151,6 +159,10 @@ unsafe fn query_single_generic_container<'a, A, WC, F>(
 
 /// Helper function for tuple queries using VecComponentContainers.
 ///
+/// Visits in index order, unless the data is sparse (see
+/// [`ZippedQuery2::is_sparse`](crate::ZippedQuery2::is_sparse)), in which
+/// case it follows the shorter container's insertion order.
+///
 /// # Safety
 ///
 /// This function assumes both containers are actually VecComponentContainers with Global allocator.
@@ -170,19 +182,208 @@ unsafe fn query_tuple_vec_containers<'a, A, B, WC, F>(
     let vec_container_a = cast_to_vec_container::<A, Global, _>(container_a);
     let vec_container_b = cast_to_vec_container::<B, Global, _>(container_b);
 
-    zip(vec_container_a, vec_container_b).for_each(|entity_index, component_a, component_b| {
+    let zipped = zip(vec_container_a, vec_container_b);
+    let mut visit = |entity_index, component_a, component_b| {
         let component_a_extended = extend_component_lifetime(component_a);
         let component_b_extended = extend_component_lifetime(component_b);
         f(entity_index, (component_a_extended, component_b_extended));
+    };
+
+    // Mostly empty slots: walking the shorter active list touches fewer of them.
+    if zipped.is_sparse() {
+        zipped.for_each_sparse(&mut visit);
+    } else {
+        zipped.for_each(&mut visit);
+    }
+}
+
+/// Helper function for tuple queries pairing a VecComponentContainer with a
+/// HashComponentContainer, see [`ZippedVecHash::for_each`].
+///
+/// # Safety
+///
+/// `container_x` must be a VecComponentContainer with Global allocator.
+unsafe fn drive_vec_hash<'a, X, Y, CX, CY, F>(container_x: &CX, container_y: &CY, mut f: F)
+where
+    X: 'a,
+    Y: 'a,
+    CX: ComponentContainer<X>,
+    CY: ComponentContainer<Y>,
+    F: FnMut(usize, &'a X, &'a Y),
+{
+    let vec_container = cast_to_vec_container::<X, Global, _>(container_x);
+    ZippedVecHash::new(vec_container, container_y).for_each(|entity_index, x, y| {
+        f(
+            entity_index,
+            extend_component_lifetime(x),
+            extend_component_lifetime(y),
+        )
+    });
+}
+
+/// Returns whether a hash container holding `count` components is better walked
+/// by index lookup over `0..bound`, with `bound` the world's entity count:
+/// once three indices in four hold a component, probing each index beats
+/// iterating the map.
+fn is_dense(count: usize, bound: usize) -> bool {
+    count > 0 && count.saturating_mul(4) >= bound.saturating_mul(3)
+}
+
+/// Visits every component of `container` through
+/// [`ComponentContainer::for_each_dense`] over `0..bound`, then any stored at
+/// `bound` or above, which only containers filled outside the world can hold.
+///
+/// # Safety
+///
+/// The components are handed out with the world's lifetime `'a`; the container
+/// must be owned by the world.
+unsafe fn for_each_dense_hash<'a, X, CX, F>(container: &CX, bound: usize, mut f: F)
+where
+    X: 'a,
+    CX: ComponentContainer<X>,
+    F: FnMut(usize, &'a X),
+{
+    let mut visited = 0;
+    container.for_each_dense(bound, |entity_index, component| {
+        visited += 1;
+        f(entity_index, extend_component_lifetime(component));
     });
+
+    if visited < container.count() {
+        container.for_each(|entity_index, component| {
+            if entity_index >= bound {
+                f(entity_index, extend_component_lifetime(component));
+            }
+        });
+    }
+}
+
+/// Dense variant of [`drive_pair`], walking `container_x` with
+/// [`for_each_dense_hash`].
+unsafe fn drive_pair_dense<'a, X, Y, CX, CY, F>(
+    container_x: &CX,
+    container_y: &CY,
+    bound: usize,
+    mut f: F,
+) where
+    X: 'a,
+    Y: 'a,
+    CX: ComponentContainer<X>,
+    CY: ComponentContainer<Y>,
+    F: FnMut(usize, &'a X, &'a Y),
+{
+    for_each_dense_hash(container_x, bound, |entity_index, component_x| {
+        record_lookup();
+        if let Some(component_y) = container_y.get_index(entity_index) {
+            f(
+                entity_index,
+                component_x,
+                extend_component_lifetime(component_y),
+            );
+        }
+    });
+}
+
+/// Returns the position of the smallest count, preferring earlier positions on ties.
+fn smallest(counts: &[usize]) -> usize {
+    let mut best = 0;
+    for (position, &count) in counts.iter().enumerate() {
+        if count < counts[best] {
+            best = position;
+        }
+    }
+    best
+}
+
+/// Visits every index present in both containers, driven by `container_x` and
+/// looking `container_y` up per entity.
+unsafe fn drive_pair<'a, X, Y, CX, CY, F>(container_x: &CX, container_y: &CY, mut f: F)
+where
+    X: 'a,
+    Y: 'a,
+    CX: ComponentContainer<X>,
+    CY: ComponentContainer<Y>,
+    F: FnMut(usize, &'a X, &'a Y),
+{
+    container_x.for_each(|entity_index, component_x| {
+        record_lookup();
+        if let Some(component_y) = container_y.get_index(entity_index) {
+            f(
+                entity_index,
+                extend_component_lifetime(component_x),
+                extend_component_lifetime(component_y),
+            );
+        }
+    });
+}
+
+/// Three-container variant of [`drive_pair`].
+unsafe fn drive_triple<'a, X, Y, Z, CX, CY, CZ, F>(
+    container_x: &CX,
+    container_y: &CY,
+    container_z: &CZ,
+    mut f: F,
+) where
+    X: 'a,
+    Y: 'a,
+    Z: 'a,
+    CX: ComponentContainer<X>,
+    CY: ComponentContainer<Y>,
+    CZ: ComponentContainer<Z>,
+    F: FnMut(usize, &'a X, &'a Y, &'a Z),
+{
+    drive_pair(
+        container_x,
+        container_y,
+        |entity_index, x: &'a X, y: &'a Y| {
+            record_lookup();
+            if let Some(z) = container_z.get_index(entity_index) {
+                f(entity_index, x, y, extend_component_lifetime(z));
+            }
+        },
+    );
+}
+
+/// Four-container variant of [`drive_pair`].
+unsafe fn drive_quad<'a, W, X, Y, Z, CW, CX, CY, CZ, F>(
+    container_w: &CW,
+    container_x: &CX,
+    container_y: &CY,
+    container_z: &CZ,
+    mut f: F,
+) where
+    W: 'a,
+    X: 'a,
+    Y: 'a,
+    Z: 'a,
+    CW: ComponentContainer<W>,
+    CX: ComponentContainer<X>,
+    CY: ComponentContainer<Y>,
+    CZ: ComponentContainer<Z>,
+    F: FnMut(usize, &'a W, &'a X, &'a Y, &'a Z),
+{
+    drive_triple(
+        container_w,
+        container_x,
+        container_y,
+        |entity_index, w: &'a W, x: &'a X, y: &'a Y| {
+            record_lookup();
+            if let Some(z) = container_z.get_index(entity_index) {
+                f(entity_index, w, x, y, extend_component_lifetime(z));
+            }
+        },
+    );
 }
 
 /// Helper function for tuple queries using mixed container types.
 ///
+/// Iteration is driven by the container with the fewest components, so a rare
+/// component paired with a common one only visits the rare entities.
+///
 /// # Safety
 ///
-/// This function creates raw pointers to containers and dereferences them.
-/// The pointers remain valid because the containers are owned by the world.
+/// The components are handed out with the world's lifetime `'a`; the containers
+/// must be owned by the world.
 unsafe fn query_tuple_generic_containers<'a, A, B, WC, F>(
     container_a: &<WC as GetComponentContainer<A>>::Container,
     container_b: &<WC as GetComponentContainer<B>>::Container,
@@ -195,21 +396,14 @@ unsafe fn query_tuple_generic_containers<'a, A, B, WC, F>(
     <WC as GetComponentContainer<B>>::Container: ComponentContainer<B>,
     F: FnMut(usize, (&'a A, &'a B)),
 {
-    let container_a_ptr = container_a as *const <WC as GetComponentContainer<A>>::Container;
-    let container_b_ptr = container_b as *const <WC as GetComponentContainer<B>>::Container;
-
-    // Iterate over container A and lookup in container B
-    (*container_a_ptr).for_each(|entity_index, component_a| {
-        if let Some(entity) = safe_entity_new(entity_index) {
-            if let Some(component_b) = (*container_b_ptr).get(entity) {
-                let component_a_extended = extend_component_lifetime(component_a);
-                let component_b_extended = extend_component_lifetime(component_b);
-                f(entity_index, (component_a_extended, component_b_extended));
-            }
-        }
-        // If entity creation fails, we skip this entity silently
-        // This prevents crashes from invalid entity indices
-    });
+    match smallest(&[container_a.count(), container_b.count()]) {
+        0 => drive_pair(container_a, container_b, |i, a: &'a A, b: &'a B| {
+            f(i, (a, b))
+        }),
+        _ => drive_pair(container_b, container_a, |i, b: &'a B, a: &'a A| {
+            f(i, (a, b))
+        }),
+    }
 }
 
 /// Helper function for triple queries using VecComponentContainers.
@@ -240,29 +434,157 @@ unsafe fn query_triple_vec_containers<'a, A, B, C, WC, F>(
     let vec_container_b = cast_to_vec_container::<B, Global, _>(container_b);
     let vec_container_c = cast_to_vec_container::<C, Global, _>(container_c);
 
-    zip3(vec_container_a, vec_container_b, vec_container_c).for_each(
-        |entity_index, component_a, component_b, component_c| {
-            let component_a_extended = extend_component_lifetime(component_a);
-            let component_b_extended = extend_component_lifetime(component_b);
-            let component_c_extended = extend_component_lifetime(component_c);
-            f(
-                entity_index,
-                (
-                    component_a_extended,
-                    component_b_extended,
-                    component_c_extended,
-                ),
-            );
-        },
-    );
+    let zipped = zip3(vec_container_a, vec_container_b, vec_container_c);
+    let mut visit = |entity_index, component_a, component_b, component_c| {
+        let component_a_extended = extend_component_lifetime(component_a);
+        let component_b_extended = extend_component_lifetime(component_b);
+        let component_c_extended = extend_component_lifetime(component_c);
+        f(
+            entity_index,
+            (
+                component_a_extended,
+                component_b_extended,
+                component_c_extended,
+            ),
+        );
+    };
+
+    // Same trade-off as the pair query: a rare component in any position
+    // makes walking its active list cheaper than scanning every slot.
+    if zipped.is_sparse() {
+        zipped.for_each_sparse(&mut visit);
+    } else {
+        zipped.for_each(&mut visit);
+    }
+}
+
+/// Visits every index present in both vec containers, driven by the one with
+/// fewer live components.
+///
+/// # Safety
+///
+/// Both containers must be VecComponentContainers with Global allocator.
+unsafe fn drive_vec_pair<'a, X, Y, F>(
+    container_x: &'a VecComponentContainer<X, Global>,
+    container_y: &'a VecComponentContainer<Y, Global>,
+    mut f: F,
+) where
+    F: FnMut(usize, &'a X, &'a Y),
+{
+    if container_x.active_indices.len() <= container_y.active_indices.len() {
+        container_x.for_each(|entity_index, component_x| {
+            record_lookup();
+            if let Some(component_y) = container_y.get_index(entity_index) {
+                f(
+                    entity_index,
+                    extend_component_lifetime(component_x),
+                    component_y,
+                );
+            }
+        });
+    } else {
+        container_y.for_each(|entity_index, component_y| {
+            record_lookup();
+            if let Some(component_x) = container_x.get_index(entity_index) {
+                f(
+                    entity_index,
+                    component_x,
+                    extend_component_lifetime(component_y),
+                );
+            }
+        });
+    }
+}
+
+/// Helper function for triple queries where exactly two containers are VecComponentContainers.
+///
+/// Iteration is driven by one of the vec containers, so those two are read by
+/// index and only the remaining container is looked up per entity. If the
+/// remaining container holds the fewest components, it drives instead through
+/// [`query_triple_generic_containers`].
+///
+/// # Safety
+///
+/// Exactly one of the three containers may fail `is_vec_container()`; the other
+/// two are cast to VecComponentContainers with Global allocator.
+unsafe fn query_triple_two_vec_containers<'a, A, B, C, WC, F>(
+    container_a: &'a <WC as GetComponentContainer<A>>::Container,
+    container_b: &'a <WC as GetComponentContainer<B>>::Container,
+    container_c: &'a <WC as GetComponentContainer<C>>::Container,
+    mut f: F,
+) where
+    A: 'a,
+    B: 'a,
+    C: 'a,
+    WC: WorldContainer
+        + GetComponentContainer<A>
+        + GetComponentContainer<B>
+        + GetComponentContainer<C>,
+    <WC as GetComponentContainer<A>>::Container: ComponentContainer<A>,
+    <WC as GetComponentContainer<B>>::Container: ComponentContainer<B>,
+    <WC as GetComponentContainer<C>>::Container: ComponentContainer<C>,
+    F: FnMut(usize, (&'a A, &'a B, &'a C)),
+{
+    let counts = [
+        container_a.count(),
+        container_b.count(),
+        container_c.count(),
+    ];
+    let other = [
+        container_a.is_vec_container(),
+        container_b.is_vec_container(),
+        container_c.is_vec_container(),
+    ]
+    .iter()
+    .position(|&is_vec| !is_vec)
+    .unwrap_or(0);
+    let other_is_rarest = counts
+        .iter()
+        .enumerate()
+        .all(|(position, &count)| position == other || counts[other] < count);
+    if other_is_rarest {
+        query_triple_generic_containers::<A, B, C, WC, F>(container_a, container_b, container_c, f);
+        return;
+    }
+
+    if !container_a.is_vec_container() {
+        let vec_container_b = cast_to_vec_container::<B, Global, _>(container_b);
+        let vec_container_c = cast_to_vec_container::<C, Global, _>(container_c);
+        drive_vec_pair(vec_container_b, vec_container_c, |entity_index, b, c| {
+            record_lookup();
+            if let Some(a) = container_a.get_index(entity_index) {
+                f(entity_index, (a, b, c));
+            }
+        });
+    } else if !container_b.is_vec_container() {
+        let vec_container_a = cast_to_vec_container::<A, Global, _>(container_a);
+        let vec_container_c = cast_to_vec_container::<C, Global, _>(container_c);
+        drive_vec_pair(vec_container_a, vec_container_c, |entity_index, a, c| {
+            record_lookup();
+            if let Some(b) = container_b.get_index(entity_index) {
+                f(entity_index, (a, b, c));
+            }
+        });
+    } else {
+        let vec_container_a = cast_to_vec_container::<A, Global, _>(container_a);
+        let vec_container_b = cast_to_vec_container::<B, Global, _>(container_b);
+        drive_vec_pair(vec_container_a, vec_container_b, |entity_index, a, b| {
+            record_lookup();
+            if let Some(c) = container_c.get_index(entity_index) {
+                f(entity_index, (a, b, c));
+            }
+        });
+    }
 }
 
 /// Helper function for triple queries using mixed container types.
 ///
+/// Iteration is driven by the container with the fewest components.
+///
 /// # Safety
 ///
-/// This function creates raw pointers to containers and dereferences them.
-/// The pointers remain valid because the containers are owned by the world.
+/// The components are handed out with the world's lifetime `'a`; the containers
+/// must be owned by the world.
 unsafe fn query_triple_generic_containers<'a, A, B, C, WC, F>(
     container_a: &<WC as GetComponentContainer<A>>::Container,
     container_b: &<WC as GetComponentContainer<B>>::Container,
@@ -281,84 +603,198 @@ unsafe fn query_triple_generic_containers<'a, A, B, C, WC, F>(
     <WC as GetComponentContainer<C>>::Container: ComponentContainer<C>,
     F: FnMut(usize, (&'a A, &'a B, &'a C)),
 {
-    let container_a_ptr = container_a as *const <WC as GetComponentContainer<A>>::Container;
-    let container_b_ptr = container_b as *const <WC as GetComponentContainer<B>>::Container;
-    let container_c_ptr = container_c as *const <WC as GetComponentContainer<C>>::Container;
-
-    // Iterate over container A and lookup in containers B and C
-    (*container_a_ptr).for_each(|entity_index, component_a| {
-        if let Some(entity) = safe_entity_new(entity_index) {
-            if let Some(component_b) = (*container_b_ptr).get(entity) {
-                if let Some(component_c) = (*container_c_ptr).get(entity) {
-                    let component_a_extended = extend_component_lifetime(component_a);
-                    let component_b_extended = extend_component_lifetime(component_b);
-                    let component_c_extended = extend_component_lifetime(component_c);
-                    f(
-                        entity_index,
-                        (
-                            component_a_extended,
-                            component_b_extended,
-                            component_c_extended,
-                        ),
-                    );
-                }
-            }
-        }
-        // If entity creation fails, we skip this entity silently
-        // This prevents crashes from invalid entity indices
-    });
+    let counts = [
+        container_a.count(),
+        container_b.count(),
+        container_c.count(),
+    ];
+
+    match smallest(&counts) {
+        0 => drive_triple(
+            container_a,
+            container_b,
+            container_c,
+            |i, a: &'a A, b: &'a B, c: &'a C| f(i, (a, b, c)),
+        ),
+        1 => drive_triple(
+            container_b,
+            container_a,
+            container_c,
+            |i, b: &'a B, a: &'a A, c: &'a C| f(i, (a, b, c)),
+        ),
+        _ => drive_triple(
+            container_c,
+            container_a,
+            container_b,
+            |i, c: &'a C, a: &'a A, b: &'a B| f(i, (a, b, c)),
+        ),
+    }
 }
 
-/// Common validation logic for container queries.
+/// Helper function for quad queries using VecComponentContainers.
 ///
-/// This function performs early validation checks that are common across all query types.
-/// It's designed to catch common issues in debug mode without impacting release performance.
-fn validate_container<C, Container: ComponentContainer<C>>(container: &Container, name: &str) {
-    debug_assert!(
-        !container.is_empty() || true,
-        "Container {} validation passed",
-        name
-    );
+/// # Safety
+///
+/// This function assumes all four containers are actually VecComponentContainers with Global allocator.
+/// Validation is performed inside cast_to_vec_container.
+unsafe fn query_quad_vec_containers<'a, A, B, C, D, WC, F>(
+    container_a: &<WC as GetComponentContainer<A>>::Container,
+    container_b: &<WC as GetComponentContainer<B>>::Container,
+    container_c: &<WC as GetComponentContainer<C>>::Container,
+    container_d: &<WC as GetComponentContainer<D>>::Container,
+    mut f: F,
+) where
+    A: 'a,
+    B: 'a,
+    C: 'a,
+    D: 'a,
+    WC: WorldContainer
+        + GetComponentContainer<A>
+        + GetComponentContainer<B>
+        + GetComponentContainer<C>
+        + GetComponentContainer<D>,
+    <WC as GetComponentContainer<A>>::Container: ComponentContainer<A>,
+    <WC as GetComponentContainer<B>>::Container: ComponentContainer<B>,
+    <WC as GetComponentContainer<C>>::Container: ComponentContainer<C>,
+    <WC as GetComponentContainer<D>>::Container: ComponentContainer<D>,
+    F: FnMut(usize, (&'a A, &'a B, &'a C, &'a D)),
+{
+    let vec_container_a = cast_to_vec_container::<A, Global, _>(container_a);
+    let vec_container_b = cast_to_vec_container::<B, Global, _>(container_b);
+    let vec_container_c = cast_to_vec_container::<C, Global, _>(container_c);
+    let vec_container_d = cast_to_vec_container::<D, Global, _>(container_d);
 
-    // Additional validation for potential overflow conditions
-    debug_assert!(
-        container.len() < usize::MAX / 2,
-        "Container {} has suspiciously large size: {}",
-        name,
-        container.len()
+    zip4(
+        vec_container_a,
+        vec_container_b,
+        vec_container_c,
+        vec_container_d,
+    )
+    .for_each(
+        |entity_index, component_a, component_b, component_c, component_d| {
+            f(
+                entity_index,
+                (
+                    extend_component_lifetime(component_a),
+                    extend_component_lifetime(component_b),
+                    extend_component_lifetime(component_c),
+                    extend_component_lifetime(component_d),
+                ),
+            );
+        },
     );
 }
 
-/// Validates entity index bounds to prevent out-of-bounds access.
-///
-/// # Arguments
+/// Helper function for quad queries using mixed container types.
 ///
-/// * `entity_index` - The entity index to validate
-/// * `max_entities` - The maximum number of entities (container length)
+/// Iteration is driven by the container with the fewest components.
 ///
-/// # Returns
+/// # Safety
 ///
-/// `true` if the entity index is valid, `false` otherwise
-fn is_valid_entity_index(entity_index: usize, max_entities: usize) -> bool {
-    entity_index < max_entities
+/// The components are handed out with the world's lifetime `'a`; the containers
+/// must be owned by the world.
+unsafe fn query_quad_generic_containers<'a, A, B, C, D, WC, F>(
+    container_a: &<WC as GetComponentContainer<A>>::Container,
+    container_b: &<WC as GetComponentContainer<B>>::Container,
+    container_c: &<WC as GetComponentContainer<C>>::Container,
+    container_d: &<WC as GetComponentContainer<D>>::Container,
+    mut f: F,
+) where
+    A: 'a,
+    B: 'a,
+    C: 'a,
+    D: 'a,
+    WC: WorldContainer
+        + GetComponentContainer<A>
+        + GetComponentContainer<B>
+        + GetComponentContainer<C>
+        + GetComponentContainer<D>,
+    <WC as GetComponentContainer<A>>::Container: ComponentContainer<A>,
+    <WC as GetComponentContainer<B>>::Container: ComponentContainer<B>,
+    <WC as GetComponentContainer<C>>::Container: ComponentContainer<C>,
+    <WC as GetComponentContainer<D>>::Container: ComponentContainer<D>,
+    F: FnMut(usize, (&'a A, &'a B, &'a C, &'a D)),
+{
+    let counts = [
+        container_a.count(),
+        container_b.count(),
+        container_c.count(),
+        container_d.count(),
+    ];
+
+    match smallest(&counts) {
+        0 => drive_quad(
+            container_a,
+            container_b,
+            container_c,
+            container_d,
+            |i, a: &'a A, b: &'a B, c: &'a C, d: &'a D| f(i, (a, b, c, d)),
+        ),
+        1 => drive_quad(
+            container_b,
+            container_a,
+            container_c,
+            container_d,
+            |i, b: &'a B, a: &'a A, c: &'a C, d: &'a D| f(i, (a, b, c, d)),
+        ),
+        2 => drive_quad(
+            container_c,
+            container_a,
+            container_b,
+            container_d,
+            |i, c: &'a C, a: &'a A, b: &'a B, d: &'a D| f(i, (a, b, c, d)),
+        ),
+        _ => drive_quad(
+            container_d,
+            container_a,
+            container_b,
+            container_c,
+            |i, d: &'a D, a: &'a A, b: &'a B, c: &'a C| f(i, (a, b, c, d)),
+        ),
+    }
 }
 
-/// Safe wrapper around Entity::new that validates the entity index.
-///
-/// # Arguments
+/// How much checking queries perform on their containers before iterating.
 ///
-/// * `entity_index` - The entity index to create an Entity from
-///
-/// # Returns
-///
-/// `Some(Entity)` if the index is valid, `None` otherwise
-fn safe_entity_new(entity_index: usize) -> Option<Entity> {
-    // Entity::new might have internal validation, but we add an extra check
-    // to ensure we don't create entities with obviously invalid indices
-    if entity_index == usize::MAX {
-        None
-    } else {
-        Some(Entity::new(entity_index))
+/// Only selectable with the `validation` feature. Without it every world uses
+/// `Debug`, whose checks are compiled out of release builds, so queries pay
+/// nothing for validation.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ValidationLevel {
+    /// No checks at all.
+    Off,
+    /// Lightweight size checks, compiled out of release builds.
+    #[default]
+    Debug,
+    /// Runs `check_invariants` on every container before each query, and
+    /// panics if one of them is corrupted.
+    Paranoid,
+}
+
+/// Common validation logic for container queries.
+#[inline]
+fn validate_container<C, Container: ComponentContainer<C>>(
+    container: &Container,
+    name: &str,
+    level: ValidationLevel,
+) {
+    match level {
+        ValidationLevel::Off => {}
+        ValidationLevel::Debug => {
+            debug_assert!(
+                container.len() < usize::MAX / 2,
+                "Container {} has suspiciously large size: {}",
+                name,
+                container.len()
+            );
+        }
+        ValidationLevel::Paranoid => {
+            assert!(
+                container.check_invariants(),
+                "Container {} failed its invariant checks",
+                name
+            );
+        }
     }
 }
 
@@ -366,36 +802,321 @@ fn safe_entity_new(entity_index: usize) -> Option<Entity> {
 ///
 /// This trait allows for efficient iteration over entities that have specific
 /// component combinations. Implementations provide optimized paths for different
-/// container types. Currently supports queries for 1, 2, or 3 components.
+/// container types. Currently supports queries for 1, 2, 3, or 4 components.
+///
+/// # Ordering
+///
+/// Every path visits the same set of entities, but not always in the same
+/// order. The vec fast path walks slots by ascending index, or the shortest
+/// `active_indices` when sparse. A hash container holding a component for
+/// most entities is walked by ascending index too. Every other path follows
+/// the `for_each` order of its driving container, the one holding the fewest
+/// components. Results
+/// only depend on the operations performed when every container involved is
+/// [`ORDERED`](ComponentContainer::ORDERED); otherwise use
+/// [`World::for_each_sorted`].
 pub trait Query<'a, WC: WorldContainer> {
     /// The type of item yielded by this query (e.g., &A, (&A, &B), or (&A, &B, &C))
     type Item;
 
-    /// Iterates over all entities that match this query.
+    /// Iterates over all entities that match this query, including disabled ones.
     ///
     /// # Arguments
     ///
     /// * `world` - The world to query from
-    /// * `f` - A closure that will be called for each matching entity
-    ///         The closure receives (entity_index, components)
-    fn for_each<F>(world: &'a World<WC>, f: F)
+    /// * `f` - A closure that will be called for each matching entity.
+    ///   The closure receives (entity_index, components)
+    fn for_each_include_disabled<F>(world: &'a World<WC>, f: F)
     where
         F: FnMut(usize, Self::Item);
 
-    /// Iterates over all entities that match this query using sparse traversal.
+    /// Sparse traversal variant of [`Query::for_each_include_disabled`].
     ///
     /// This method always uses the generic container approach, which iterates through
-    /// one container's active indices and looks up components in other containers.
+    /// the container holding the fewest components and looks up the others.
     /// This can be more efficient when dealing with sparse data.
     ///
     /// # Arguments
     ///
     /// * `world` - The world to query from
-    /// * `f` - A closure that will be called for each matching entity
-    ///         The closure receives (entity_index, components)
-    fn for_each_sparse<F>(world: &'a World<WC>, f: F)
+    /// * `f` - A closure that will be called for each matching entity.
+    ///   The closure receives (entity_index, components)
+    fn for_each_sparse_include_disabled<F>(world: &'a World<WC>, f: F)
     where
         F: FnMut(usize, Self::Item);
+
+    /// Iterates over all enabled entities that match this query.
+    ///
+    /// Entities disabled with [`World::disable`] are skipped.
+    ///
+    /// # Arguments
+    ///
+    /// * `world` - The world to query from
+    /// * `f` - A closure that will be called for each matching entity.
+    ///   The closure receives (entity_index, components)
+    fn for_each<F>(world: &'a World<WC>, mut f: F)
+    where
+        F: FnMut(usize, Self::Item),
+    {
+        if world.has_disabled() {
+            Self::for_each_include_disabled(world, |entity_index, item| {
+                if !world.is_index_disabled(entity_index) {
+                    record_visit();
+                    f(entity_index, item);
+                }
+            });
+        } else {
+            Self::for_each_include_disabled(world, |entity_index, item| {
+                record_visit();
+                f(entity_index, item);
+            });
+        }
+    }
+
+    /// Like [`Query::for_each`], but passes single `Copy` components by value,
+    /// which lets the closure keep them in registers instead of reloading
+    /// them through the reference.
+    fn for_each_copied<T, F>(world: &'a World<WC>, mut f: F)
+    where
+        Self: Query<'a, WC, Item = &'a T>,
+        T: Copy + 'a,
+        F: FnMut(usize, T),
+    {
+        Self::for_each(world, |entity_index, component| f(entity_index, *component));
+    }
+
+    /// Like [`Query::for_each`], but hands out each match's [`Entity`] with
+    /// its current generation instead of the bare index.
+    ///
+    /// The handle can be stored and used on later frames: once the entity is
+    /// despawned, lookups through it return `None`. Building it costs one
+    /// bounds-checked read of the world's generation table per match, on top
+    /// of the query itself. That is a load from a different array than the
+    /// components, so in tight loops that never keep the entity, prefer the
+    /// plain index of [`Query::for_each`].
+    fn for_each_entity<F>(world: &'a World<WC>, mut f: F)
+    where
+        F: FnMut(Entity, Self::Item),
+    {
+        Self::for_each(world, |entity_index, item| {
+            f(world.entity_at(entity_index), item);
+        });
+    }
+
+    /// Iterates over all enabled entities that match this query using sparse traversal.
+    ///
+    /// Entities disabled with [`World::disable`] are skipped. They are visited
+    /// in the order their components were added to the driving container, not
+    /// by index; see [`Query::for_each_sparse_ordered`].
+    ///
+    /// # Arguments
+    ///
+    /// * `world` - The world to query from
+    /// * `f` - A closure that will be called for each matching entity.
+    ///   The closure receives (entity_index, components)
+    fn for_each_sparse<F>(world: &'a World<WC>, mut f: F)
+    where
+        F: FnMut(usize, Self::Item),
+    {
+        if world.has_disabled() {
+            Self::for_each_sparse_include_disabled(world, |entity_index, item| {
+                if !world.is_index_disabled(entity_index) {
+                    record_visit();
+                    f(entity_index, item);
+                }
+            });
+        } else {
+            Self::for_each_sparse_include_disabled(world, |entity_index, item| {
+                record_visit();
+                f(entity_index, item);
+            });
+        }
+    }
+
+    /// Like [`Query::for_each_sparse`], but visits entities in ascending index
+    /// order, so the result does not depend on the order components were added.
+    ///
+    /// The matches are collected and sorted in `scratch`, which is overwritten;
+    /// passing the same `Vec` every frame avoids allocating once it has grown.
+    /// Each match is then fetched a second time, so this costs a sort and one
+    /// lookup per component per entity on top of the sparse traversal.
+    fn for_each_sparse_ordered<F>(world: &'a World<WC>, scratch: &mut Vec<usize>, mut f: F)
+    where
+        F: FnMut(usize, Self::Item),
+    {
+        scratch.clear();
+        Self::for_each_sparse_include_disabled(world, |entity_index, _| {
+            if !world.is_index_disabled(entity_index) {
+                scratch.push(entity_index);
+            }
+        });
+        scratch.sort_unstable();
+
+        for &entity_index in scratch.iter() {
+            if let Some(item) = Self::fetch(world, entity_index) {
+                record_visit();
+                f(entity_index, item);
+            }
+        }
+    }
+
+    /// Returns the item of the entity at `entity_index`, or `None` if it does
+    /// not match the query. Disabled entities are not filtered out.
+    fn fetch(world: &'a World<WC>, entity_index: usize) -> Option<Self::Item>;
+
+    /// Returns the indices [`Query::find`] tries, a superset of the matches.
+    fn candidates(world: &'a World<WC>) -> impl Iterator<Item = usize> + 'a;
+
+    /// Returns a value that changes whenever the set of matches may have,
+    /// ignoring disabled entities. Built from the
+    /// [`ComponentContainer::version`] of every container the query reads.
+    fn version(world: &'a World<WC>) -> u64;
+
+    /// Returns the first enabled match for which `pred` returns true.
+    ///
+    /// Stops iterating as soon as it is found, unlike [`Query::for_each`].
+    /// Matches are tried in the iteration order of the query's first component.
+    fn find<P>(world: &'a World<WC>, mut pred: P) -> Option<(usize, Self::Item)>
+    where
+        P: FnMut(usize, &Self::Item) -> bool,
+    {
+        let found = Self::try_for_each(world, |entity_index, item| {
+            if pred(entity_index, &item) {
+                ControlFlow::Break((entity_index, item))
+            } else {
+                ControlFlow::Continue(())
+            }
+        });
+
+        match found {
+            ControlFlow::Break(found) => Some(found),
+            ControlFlow::Continue(()) => None,
+        }
+    }
+
+    /// Like [`Query::for_each`], but stops as soon as `f` returns
+    /// [`ControlFlow::Break`], returning that value.
+    ///
+    /// Visits matches in the same order as [`Query::find`].
+    ///
+    /// ```ignore
+    /// <&Enemy as Query<_>>::try_for_each(&world, |index, enemy| {
+    ///     if enemy.in_range(player) {
+    ///         return ControlFlow::Break(index);
+    ///     }
+    ///     ControlFlow::Continue(())
+    /// });
+    /// ```
+    fn try_for_each<B, F>(world: &'a World<WC>, mut f: F) -> ControlFlow<B>
+    where
+        F: FnMut(usize, Self::Item) -> ControlFlow<B>,
+    {
+        for entity_index in Self::candidates(world) {
+            if world.is_index_disabled(entity_index) {
+                continue;
+            }
+
+            if let Some(item) = Self::fetch(world, entity_index) {
+                record_visit();
+                f(entity_index, item)?;
+            }
+        }
+
+        ControlFlow::Continue(())
+    }
+
+    /// Returns the first enabled match, see [`Query::find`].
+    ///
+    /// ```ignore
+    /// let (player_index, player) = <&Player as Query<_>>::first(&world)?;
+    /// ```
+    fn first(world: &'a World<WC>) -> Option<(usize, Self::Item)> {
+        Self::find(world, |_entity_index, _item| true)
+    }
+
+    /// Returns how many enabled entities match this query.
+    ///
+    /// Equivalent to counting the calls [`Query::for_each`] would make, without
+    /// a closure at the call site.
+    fn count(world: &'a World<WC>) -> usize {
+        let mut count = 0;
+        Self::for_each(world, |_entity_index, _item| count += 1);
+        count
+    }
+
+    /// Iterates over all entities that match this query and are marked alive.
+    ///
+    /// Liveness is read from a caller-maintained bitset: entity `i` is alive when
+    /// bit `i % 32` of `alive[i / 32]` is set. Indices past the end of the bitset
+    /// are treated as dead, regardless of the components they hold.
+    ///
+    /// # Arguments
+    ///
+    /// * `world` - The world to query from
+    /// * `alive` - The liveness bitset, one bit per entity index
+    /// * `f` - A closure that will be called for each matching live entity
+    fn for_each_alive<F>(world: &'a World<WC>, alive: &[u32], mut f: F)
+    where
+        F: FnMut(usize, Self::Item),
+    {
+        Self::for_each(world, |entity_index, item| {
+            if is_alive(alive, entity_index) {
+                f(entity_index, item);
+            }
+        });
+    }
+
+    /// Counts matching entities into `N` bins chosen by `key`.
+    ///
+    /// Each match increments `out[key(entity_index, item)]`, saturating at
+    /// `u16::MAX`. Keys past the last bin are clamped into it, so every match is
+    /// counted. Nothing is allocated.
+    fn histogram<const N: usize, KF>(world: &'a World<WC>, mut key: KF) -> [u16; N]
+    where
+        KF: FnMut(usize, Self::Item) -> usize,
+    {
+        let mut out = [0u16; N];
+        if N == 0 {
+            return out;
+        }
+
+        Self::for_each(world, |entity_index, item| {
+            let bin = key(entity_index, item).min(N - 1);
+            out[bin] = out[bin].saturating_add(1);
+        });
+        out
+    }
+
+    /// Writes one OAM entry per matching entity into `oam`, indexed by match ordinal.
+    ///
+    /// `f` maps a matched entity's components to its attribute entry. Matches past
+    /// the end of `oam` are not written.
+    ///
+    /// # Returns
+    ///
+    /// The number of entries written, i.e. the first unused ordinal in `oam`
+    #[cfg(feature = "agb-render")]
+    fn write_oam<T, F>(world: &'a World<WC>, oam: &mut [T], mut f: F) -> usize
+    where
+        F: FnMut(usize, Self::Item) -> T,
+    {
+        let mut written = 0;
+        Self::for_each(world, |entity_index, item| {
+            if let Some(entry) = oam.get_mut(written) {
+                *entry = f(entity_index, item);
+                written += 1;
+            }
+        });
+        written
+    }
+}
+
+/// Reads the bit for `entity_index` in a liveness bitset.
+#[inline]
+fn is_alive(alive: &[u32], entity_index: usize) -> bool {
+    alive
+        .get(entity_index / 32)
+        .is_some_and(|word| word & (1 << (entity_index % 32)) != 0)
 }
 
 /// Implementation of Query for single component queries (&A).
@@ -409,38 +1130,68 @@ where
 {
     type Item = &'a A;
 
-    fn for_each<F>(world: &'a World<WC>, f: F)
+    fn fetch(world: &'a World<WC>, entity_index: usize) -> Option<Self::Item> {
+        world.get::<A>().get_index(entity_index)
+    }
+
+    fn candidates(world: &'a World<WC>) -> impl Iterator<Item = usize> + 'a {
+        world.get::<A>().iter_indices()
+    }
+
+    fn version(world: &'a World<WC>) -> u64 {
+        world.get::<A>().version()
+    }
+
+    fn for_each_include_disabled<F>(world: &'a World<WC>, f: F)
     where
         F: FnMut(usize, Self::Item),
     {
         let container = world.get::<A>();
 
         // Early validation
-        validate_container(container, "A");
+        validate_container(container, "A", world.validation_level());
 
         if container.is_vec_container() {
             // SAFETY: Container type verified by is_vec_container()
             // Helper function handles the unsafe casting and lifetime extension
             unsafe { query_single_vec_container::<A, WC, F>(container, f) };
+        } else if container.storage_kind() == StorageKind::Hash
+            && is_dense(container.count(), world.last_entity)
+        {
+            // Dense path: probing every index beats walking the map
+            // SAFETY: the container is owned by `world`
+            unsafe { for_each_dense_hash(container, world.last_entity, f) };
         } else {
             // SAFETY: Helper function handles the unsafe lifetime extension
             unsafe { query_single_generic_container::<A, WC, F>(container, f) };
         }
     }
 
-    fn for_each_sparse<F>(world: &'a World<WC>, f: F)
+    fn for_each_sparse_include_disabled<F>(world: &'a World<WC>, f: F)
     where
         F: FnMut(usize, Self::Item),
     {
         let container = world.get::<A>();
 
         // Early validation
-        validate_container(container, "A");
+        validate_container(container, "A", world.validation_level());
 
         // Always use generic container approach for sparse traversal
         // SAFETY: Helper function handles the unsafe lifetime extension
         unsafe { query_single_generic_container::<A, WC, F>(container, f) };
     }
+
+    fn count(world: &'a World<WC>) -> usize {
+        // Without disabled entities every component matches, so the container
+        // already knows the answer.
+        if world.has_disabled() {
+            let mut count = 0;
+            Self::for_each(world, |_entity_index, _item| count += 1);
+            count
+        } else {
+            world.get::<A>().count()
+        }
+    }
 }
 
 /// Implementation of Query for two-component queries (&A, &B).
@@ -456,7 +1207,25 @@ where
 {
     type Item = (&'a A, &'a B);
 
-    fn for_each<F>(world: &'a World<WC>, f: F)
+    fn fetch(world: &'a World<WC>, entity_index: usize) -> Option<Self::Item> {
+        Some((
+            world.get::<A>().get_index(entity_index)?,
+            world.get::<B>().get_index(entity_index)?,
+        ))
+    }
+
+    fn candidates(world: &'a World<WC>) -> impl Iterator<Item = usize> + 'a {
+        world.get::<A>().iter_indices()
+    }
+
+    fn version(world: &'a World<WC>) -> u64 {
+        world
+            .get::<A>()
+            .version()
+            .wrapping_add(world.get::<B>().version())
+    }
+
+    fn for_each_include_disabled<F>(world: &'a World<WC>, mut f: F)
     where
         F: FnMut(usize, Self::Item),
     {
@@ -464,22 +1233,62 @@ where
         let container_b = world.get::<B>();
 
         // Early validation for both containers
-        validate_container(container_a, "A");
-        validate_container(container_b, "B");
+        let level = world.validation_level();
+        validate_container(container_a, "A", level);
+        validate_container(container_b, "B", level);
 
         if container_a.is_vec_container() && container_b.is_vec_container() {
             // Fast path: Both containers are VecComponentContainers
             // SAFETY: Container types verified by is_vec_container()
             // Helper function handles the unsafe casting and lifetime extension
             unsafe { query_tuple_vec_containers::<A, B, WC, F>(container_a, container_b, f) };
+        } else if container_a.is_vec_container() && container_b.storage_kind() == StorageKind::Hash
+        {
+            // Mixed path: the vec side is read by slot, the hash side by lookup
+            // SAFETY: container_a verified by is_vec_container()
+            unsafe {
+                drive_vec_hash(container_a, container_b, |i, a: &'a A, b: &'a B| {
+                    f(i, (a, b))
+                })
+            };
+        } else if container_b.is_vec_container() && container_a.storage_kind() == StorageKind::Hash
+        {
+            // SAFETY: container_b verified by is_vec_container()
+            unsafe {
+                drive_vec_hash(container_b, container_a, |i, b: &'a B, a: &'a A| {
+                    f(i, (a, b))
+                })
+            };
         } else {
-            // Fallback path: At least one container is not a VecComponentContainer
-            // SAFETY: Helper function handles the unsafe raw pointer operations and lifetime extension
-            unsafe { query_tuple_generic_containers::<A, B, WC, F>(container_a, container_b, f) };
+            // Fallback path: At least one container is not a VecComponentContainer.
+            // A dense hash driver is walked by index, see `is_dense`.
+            let bound = world.last_entity;
+            let (count_a, count_b) = (container_a.count(), container_b.count());
+            let dense_a = count_a <= count_b
+                && container_a.storage_kind() == StorageKind::Hash
+                && is_dense(count_a, bound);
+            let dense_b = count_b < count_a
+                && container_b.storage_kind() == StorageKind::Hash
+                && is_dense(count_b, bound);
+
+            // SAFETY: Helper functions handle the unsafe raw pointer operations and lifetime extension
+            unsafe {
+                if dense_a {
+                    drive_pair_dense(container_a, container_b, bound, |i, a: &'a A, b: &'a B| {
+                        f(i, (a, b))
+                    });
+                } else if dense_b {
+                    drive_pair_dense(container_b, container_a, bound, |i, b: &'a B, a: &'a A| {
+                        f(i, (a, b))
+                    });
+                } else {
+                    query_tuple_generic_containers::<A, B, WC, F>(container_a, container_b, f);
+                }
+            }
         }
     }
 
-    fn for_each_sparse<F>(world: &'a World<WC>, f: F)
+    fn for_each_sparse_include_disabled<F>(world: &'a World<WC>, f: F)
     where
         F: FnMut(usize, Self::Item),
     {
@@ -487,8 +1296,9 @@ where
         let container_b = world.get::<B>();
 
         // Early validation for both containers
-        validate_container(container_a, "A");
-        validate_container(container_b, "B");
+        let level = world.validation_level();
+        validate_container(container_a, "A", level);
+        validate_container(container_b, "B", level);
 
         // Always use generic container approach for sparse traversal
         // SAFETY: Helper function handles the unsafe raw pointer operations and lifetime extension
@@ -498,9 +1308,10 @@ where
 
 /// Implementation of Query for three-component queries (&A, &B, &C).
 ///
-/// This implementation provides two execution paths:
+/// This implementation provides three execution paths:
 /// 1. Fast path: When all three containers are VecComponentContainers, uses zip3 for efficient iteration
-/// 2. Fallback path: When containers are different types, iterates over one and looks up the others
+/// 2. Mixed path: When two of them are, one of those drives and only the third is looked up
+/// 3. Fallback path: Otherwise, iterates over one and looks up the others
 impl<'a, A: 'a, B: 'a, C: 'a, WC> Query<'a, WC> for (&A, &B, &C)
 where
     WC: WorldContainer
@@ -513,7 +1324,27 @@ where
 {
     type Item = (&'a A, &'a B, &'a C);
 
-    fn for_each<F>(world: &'a World<WC>, f: F)
+    fn fetch(world: &'a World<WC>, entity_index: usize) -> Option<Self::Item> {
+        Some((
+            world.get::<A>().get_index(entity_index)?,
+            world.get::<B>().get_index(entity_index)?,
+            world.get::<C>().get_index(entity_index)?,
+        ))
+    }
+
+    fn candidates(world: &'a World<WC>) -> impl Iterator<Item = usize> + 'a {
+        world.get::<A>().iter_indices()
+    }
+
+    fn version(world: &'a World<WC>) -> u64 {
+        world
+            .get::<A>()
+            .version()
+            .wrapping_add(world.get::<B>().version())
+            .wrapping_add(world.get::<C>().version())
+    }
+
+    fn for_each_include_disabled<F>(world: &'a World<WC>, f: F)
     where
         F: FnMut(usize, Self::Item),
     {
@@ -522,9 +1353,10 @@ where
         let container_c = world.get::<C>();
 
         // Early validation for all three containers
-        validate_container(container_a, "A");
-        validate_container(container_b, "B");
-        validate_container(container_c, "C");
+        let level = world.validation_level();
+        validate_container(container_a, "A", level);
+        validate_container(container_b, "B", level);
+        validate_container(container_c, "C", level);
 
         if container_a.is_vec_container()
             && container_b.is_vec_container()
@@ -541,6 +1373,26 @@ where
                     f,
                 )
             };
+        } else if [
+            container_a.is_vec_container(),
+            container_b.is_vec_container(),
+            container_c.is_vec_container(),
+        ]
+        .iter()
+        .filter(|&&is_vec| is_vec)
+        .count()
+            == 2
+        {
+            // Mixed path: two VecComponentContainers drive, the third is looked up
+            // SAFETY: Exactly one container failed is_vec_container(), checked above
+            unsafe {
+                query_triple_two_vec_containers::<A, B, C, WC, F>(
+                    container_a,
+                    container_b,
+                    container_c,
+                    f,
+                )
+            };
         } else {
             // Fallback path: At least one container is not a VecComponentContainer
             // SAFETY: Helper function handles the unsafe raw pointer operations and lifetime extension
@@ -555,7 +1407,7 @@ where
         }
     }
 
-    fn for_each_sparse<F>(world: &'a World<WC>, f: F)
+    fn for_each_sparse_include_disabled<F>(world: &'a World<WC>, f: F)
     where
         F: FnMut(usize, Self::Item),
     {
@@ -564,9 +1416,10 @@ where
         let container_c = world.get::<C>();
 
         // Early validation for all three containers
-        validate_container(container_a, "A");
-        validate_container(container_b, "B");
-        validate_container(container_c, "C");
+        let level = world.validation_level();
+        validate_container(container_a, "A", level);
+        validate_container(container_b, "B", level);
+        validate_container(container_c, "C", level);
 
         // Always use generic container approach for sparse traversal
         // SAFETY: Helper function handles the unsafe raw pointer operations and lifetime extension
@@ -580,3 +1433,311 @@ where
         };
     }
 }
+
+/// Implementation of Query for four-component queries (&A, &B, &C, &D).
+///
+/// This implementation provides two execution paths:
+/// 1. Fast path: When all four containers are VecComponentContainers, uses zip4 for efficient iteration
+/// 2. Fallback path: When containers are different types, iterates over one and looks up the others
+impl<'a, A: 'a, B: 'a, C: 'a, D: 'a, WC> Query<'a, WC> for (&A, &B, &C, &D)
+where
+    WC: WorldContainer
+        + GetComponentContainer<A>
+        + GetComponentContainer<B>
+        + GetComponentContainer<C>
+        + GetComponentContainer<D>,
+    <WC as GetComponentContainer<A>>::Container: ComponentContainer<A>,
+    <WC as GetComponentContainer<B>>::Container: ComponentContainer<B>,
+    <WC as GetComponentContainer<C>>::Container: ComponentContainer<C>,
+    <WC as GetComponentContainer<D>>::Container: ComponentContainer<D>,
+{
+    type Item = (&'a A, &'a B, &'a C, &'a D);
+
+    fn fetch(world: &'a World<WC>, entity_index: usize) -> Option<Self::Item> {
+        Some((
+            world.get::<A>().get_index(entity_index)?,
+            world.get::<B>().get_index(entity_index)?,
+            world.get::<C>().get_index(entity_index)?,
+            world.get::<D>().get_index(entity_index)?,
+        ))
+    }
+
+    fn candidates(world: &'a World<WC>) -> impl Iterator<Item = usize> + 'a {
+        world.get::<A>().iter_indices()
+    }
+
+    fn version(world: &'a World<WC>) -> u64 {
+        world
+            .get::<A>()
+            .version()
+            .wrapping_add(world.get::<B>().version())
+            .wrapping_add(world.get::<C>().version())
+            .wrapping_add(world.get::<D>().version())
+    }
+
+    fn for_each_include_disabled<F>(world: &'a World<WC>, f: F)
+    where
+        F: FnMut(usize, Self::Item),
+    {
+        let container_a = world.get::<A>();
+        let container_b = world.get::<B>();
+        let container_c = world.get::<C>();
+        let container_d = world.get::<D>();
+
+        // Early validation for all four containers
+        let level = world.validation_level();
+        validate_container(container_a, "A", level);
+        validate_container(container_b, "B", level);
+        validate_container(container_c, "C", level);
+        validate_container(container_d, "D", level);
+
+        if container_a.is_vec_container()
+            && container_b.is_vec_container()
+            && container_c.is_vec_container()
+            && container_d.is_vec_container()
+        {
+            // Fast path: All four containers are VecComponentContainers
+            // SAFETY: Container types verified by is_vec_container()
+            // Helper function handles the unsafe casting and lifetime extension
+            unsafe {
+                query_quad_vec_containers::<A, B, C, D, WC, F>(
+                    container_a,
+                    container_b,
+                    container_c,
+                    container_d,
+                    f,
+                )
+            };
+        } else {
+            // Fallback path: At least one container is not a VecComponentContainer
+            // SAFETY: Helper function handles the unsafe raw pointer operations and lifetime extension
+            unsafe {
+                query_quad_generic_containers::<A, B, C, D, WC, F>(
+                    container_a,
+                    container_b,
+                    container_c,
+                    container_d,
+                    f,
+                )
+            };
+        }
+    }
+
+    fn for_each_sparse_include_disabled<F>(world: &'a World<WC>, f: F)
+    where
+        F: FnMut(usize, Self::Item),
+    {
+        let container_a = world.get::<A>();
+        let container_b = world.get::<B>();
+        let container_c = world.get::<C>();
+        let container_d = world.get::<D>();
+
+        // Early validation for all four containers
+        let level = world.validation_level();
+        validate_container(container_a, "A", level);
+        validate_container(container_b, "B", level);
+        validate_container(container_c, "C", level);
+        validate_container(container_d, "D", level);
+
+        // Always use generic container approach for sparse traversal
+        // SAFETY: Helper function handles the unsafe raw pointer operations and lifetime extension
+        unsafe {
+            query_quad_generic_containers::<A, B, C, D, WC, F>(
+                container_a,
+                container_b,
+                container_c,
+                container_d,
+                f,
+            )
+        };
+    }
+}
+
+/// Query filter matching entities that have a `T` component.
+///
+/// Only gates iteration; `T` is not part of the yielded item.
+///
+/// ```ignore
+/// world.for_each::<(&Position, With<Player>), _>(|entity_index, position| {});
+/// ```
+pub struct With<T>(PhantomData<T>);
+
+/// Query filter matching entities that do not have a `T` component.
+///
+/// Only gates iteration; `T` is not part of the yielded item.
+///
+/// ```ignore
+/// world.for_each::<(&Position, &Velocity, Without<Frozen>), _>(
+///     |entity_index, (position, velocity)| {},
+/// );
+/// ```
+pub struct Without<T>(PhantomData<T>);
+
+/// Query slot yielding `Option<&T>`: entities without a `T` still match.
+///
+/// Goes after the required components, and before a filter if there is one.
+/// Iteration is driven by the required components; `T` is looked up for
+/// each match.
+///
+/// ```ignore
+/// world.for_each::<(&Position, Opt<Velocity>), _>(|entity_index, (position, velocity)| {
+///     if let Some(velocity) = velocity {}
+/// });
+/// ```
+pub struct Opt<T>(PhantomData<T>);
+
+/// A filter placed in the last slot of a query tuple.
+pub trait QueryFilter<WC: WorldContainer> {
+    /// Returns whether the entity at `entity_index` passes the filter.
+    fn matches(world: &World<WC>, entity_index: usize) -> bool;
+
+    /// Returns the version of the container the filter reads, see
+    /// [`Query::version`].
+    fn version(world: &World<WC>) -> u64;
+}
+
+impl<T, WC> QueryFilter<WC> for With<T>
+where
+    WC: WorldContainer + GetComponentContainer<T>,
+{
+    #[inline]
+    fn matches(world: &World<WC>, entity_index: usize) -> bool {
+        world.get::<T>().contains(world.entity_at(entity_index))
+    }
+
+    fn version(world: &World<WC>) -> u64 {
+        world.get::<T>().version()
+    }
+}
+
+impl<T, WC> QueryFilter<WC> for Without<T>
+where
+    WC: WorldContainer + GetComponentContainer<T>,
+{
+    #[inline]
+    fn matches(world: &World<WC>, entity_index: usize) -> bool {
+        !world.get::<T>().contains(world.entity_at(entity_index))
+    }
+
+    fn version(world: &World<WC>) -> u64 {
+        world.get::<T>().version()
+    }
+}
+
+/// Implements `Query` for a component query followed by a filter, yielding the
+/// same items as the unfiltered query.
+macro_rules! impl_filtered_query {
+    ($filter:ident, $base:ty, ($($slot:ty),+), $($component:ident),+) => {
+        impl<'a, $($component: 'a,)+ X, WC> Query<'a, WC> for ($($slot,)+ $filter<X>)
+        where
+            WC: WorldContainer,
+            $base: Query<'a, WC>,
+            $filter<X>: QueryFilter<WC>,
+        {
+            type Item = <$base as Query<'a, WC>>::Item;
+
+            fn fetch(world: &'a World<WC>, entity_index: usize) -> Option<Self::Item> {
+                if <$filter<X>>::matches(world, entity_index) {
+                    <$base>::fetch(world, entity_index)
+                } else {
+                    None
+                }
+            }
+
+            fn candidates(world: &'a World<WC>) -> impl Iterator<Item = usize> + 'a {
+                <$base>::candidates(world)
+            }
+
+            fn version(world: &'a World<WC>) -> u64 {
+                <$base>::version(world).wrapping_add(<$filter<X>>::version(world))
+            }
+
+            fn for_each_include_disabled<F>(world: &'a World<WC>, mut f: F)
+            where
+                F: FnMut(usize, Self::Item),
+            {
+                <$base>::for_each_include_disabled(world, |entity_index, item| {
+                    if <$filter<X>>::matches(world, entity_index) {
+                        f(entity_index, item);
+                    }
+                });
+            }
+
+            fn for_each_sparse_include_disabled<F>(world: &'a World<WC>, mut f: F)
+            where
+                F: FnMut(usize, Self::Item),
+            {
+                <$base>::for_each_sparse_include_disabled(world, |entity_index, item| {
+                    if <$filter<X>>::matches(world, entity_index) {
+                        f(entity_index, item);
+                    }
+                });
+            }
+        }
+    };
+}
+
+impl_filtered_query!(With, &'a A, (&A), A);
+impl_filtered_query!(Without, &'a A, (&A), A);
+impl_filtered_query!(With, (&'a A, &'a B), (&A, &B), A, B);
+impl_filtered_query!(Without, (&'a A, &'a B), (&A, &B), A, B);
+impl_filtered_query!(With, (&'a A, &'a B, &'a C), (&A, &B, &C), A, B, C);
+impl_filtered_query!(Without, (&'a A, &'a B, &'a C), (&A, &B, &C), A, B, C);
+impl_filtered_query!(With, (&'a A, Opt<O>), (&A, Opt<O>), A, O);
+impl_filtered_query!(Without, (&'a A, Opt<O>), (&A, Opt<O>), A, O);
+impl_filtered_query!(With, (&'a A, &'a B, Opt<O>), (&A, &B, Opt<O>), A, B, O);
+impl_filtered_query!(Without, (&'a A, &'a B, Opt<O>), (&A, &B, Opt<O>), A, B, O);
+
+/// Implements `Query` for required components followed by an [`Opt`],
+/// yielding the required components' item extended with `Option<&O>`.
+macro_rules! impl_optional_query {
+    ($base:ty, $pattern:pat, ($($binding:ident),+), $($component:ident),+) => {
+        impl<'a, $($component: 'a,)+ O: 'a, WC> Query<'a, WC> for ($(&$component,)+ Opt<O>)
+        where
+            WC: WorldContainer + GetComponentContainer<O>,
+            $base: Query<'a, WC, Item = $base>,
+        {
+            type Item = ($(&'a $component,)+ Option<&'a O>);
+
+            fn fetch(world: &'a World<WC>, entity_index: usize) -> Option<Self::Item> {
+                let $pattern = <$base>::fetch(world, entity_index)?;
+                Some(($($binding,)+ world.get::<O>().get_index(entity_index)))
+            }
+
+            fn candidates(world: &'a World<WC>) -> impl Iterator<Item = usize> + 'a {
+                <$base>::candidates(world)
+            }
+
+            /// Only the required components decide which entities match.
+            fn version(world: &'a World<WC>) -> u64 {
+                <$base>::version(world)
+            }
+
+            fn for_each_include_disabled<F>(world: &'a World<WC>, mut f: F)
+            where
+                F: FnMut(usize, Self::Item),
+            {
+                let optional = world.get::<O>();
+                <$base>::for_each_include_disabled(world, |entity_index, $pattern| {
+                    record_lookup();
+                    f(entity_index, ($($binding,)+ optional.get_index(entity_index)));
+                });
+            }
+
+            fn for_each_sparse_include_disabled<F>(world: &'a World<WC>, mut f: F)
+            where
+                F: FnMut(usize, Self::Item),
+            {
+                let optional = world.get::<O>();
+                <$base>::for_each_sparse_include_disabled(world, |entity_index, $pattern| {
+                    record_lookup();
+                    f(entity_index, ($($binding,)+ optional.get_index(entity_index)));
+                });
+            }
+        }
+    };
+}
+
+impl_optional_query!(&'a A, a, (a), A);
+impl_optional_query!((&'a A, &'a B), (a, b), (a, b), A, B);
+impl_optional_query!((&'a A, &'a B, &'a C), (a, b, c), (a, b, c), A, B, C);
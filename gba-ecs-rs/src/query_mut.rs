@@ -0,0 +1,326 @@
+//! Mutable queries.
+//!
+//! A [`QueryMut`] is run with [`World::for_each_mut`], typically once per
+//! frame. Each run looks its containers up again, as raw pointers taken from
+//! the world it is given, so all the unsafe pointer handling for mutable
+//! queries lives in this module.
+//!
+//! # Examples
+//!
+//! ```ignore
+//! // Every frame:
+//! world.for_each_mut::<(&mut Position, &Velocity), _>(|entity_index, (position, velocity)| {
+//!     position.x += velocity.dx;
+//! });
+//! ```
+
+use alloc::alloc::Global;
+
+use crate::instrumentation::record_lookup;
+use crate::query::cast_to_vec_container;
+use crate::vec_container::IterationGuard;
+use crate::world::is_disabled_in;
+use crate::{
+    zip, zip_mut, ComponentContainer, GetComponentContainer, VecComponentContainer, World,
+    WorldContainer,
+};
+
+/// A query borrowing some of its components mutably.
+///
+/// Implemented for `&mut A`, `(&mut A, &B)` and `(&mut A, &mut B)`. Tuple queries
+/// panic if both components resolve to the same container, so the mutable
+/// references they hand out never alias.
+pub trait QueryMut<WC: WorldContainer> {
+    /// Raw pointers to the containers the query touches.
+    type Pointers: Copy;
+
+    /// The type of item yielded by this query (e.g., `(&mut A, &B)`).
+    type Item<'w>
+    where
+        Self: 'w;
+
+    /// Looks up the query's containers in `world`.
+    ///
+    /// Panics if two components of the query resolve to the same container.
+    fn pointers(world: &mut World<WC>) -> Self::Pointers;
+
+    /// Calls `f` for every entity matching the query whose bit is clear in
+    /// `disabled`, using pointers obtained earlier.
+    ///
+    /// # Safety
+    ///
+    /// `pointers` must come from [`QueryMut::pointers`] on a world that stays
+    /// borrowed mutably for `'w`, and `disabled` must be that world's disabled
+    /// set. Nothing else of the world may be used meanwhile: any other access,
+    /// even one that does not move it, invalidates the pointers.
+    unsafe fn for_each_with_pointers<'w, F>(pointers: Self::Pointers, disabled: &'w [u32], f: F)
+    where
+        Self: 'w,
+        F: FnMut(usize, Self::Item<'w>);
+
+    /// Calls `f` for every enabled entity matching the query.
+    fn for_each_mut<'w, F>(world: &'w mut World<WC>, f: F)
+    where
+        Self: 'w,
+        F: FnMut(usize, Self::Item<'w>),
+    {
+        let pointers = Self::pointers(world);
+
+        // SAFETY: the pointers were just taken from `world`, which is borrowed
+        // mutably for the whole call. `disabled` is a separate field, so
+        // borrowing it leaves the containers untouched.
+        unsafe { Self::for_each_with_pointers(pointers, &world.disabled, f) };
+    }
+}
+
+impl<A, WC> QueryMut<WC> for &mut A
+where
+    WC: WorldContainer + GetComponentContainer<A>,
+{
+    type Pointers = *mut <WC as GetComponentContainer<A>>::Container;
+    type Item<'w>
+        = &'w mut A
+    where
+        Self: 'w;
+
+    fn pointers(world: &mut World<WC>) -> Self::Pointers {
+        world.get_mut::<A>() as *mut _
+    }
+
+    unsafe fn for_each_with_pointers<'w, F>(pointers: Self::Pointers, disabled: &'w [u32], mut f: F)
+    where
+        Self: 'w,
+        F: FnMut(usize, Self::Item<'w>),
+    {
+        let container_a = &mut *pointers;
+
+        container_a.for_each_mut(|entity_index, component_a| {
+            if !is_disabled_in(disabled, entity_index) {
+                // SAFETY: the component lives in a container owned by the
+                // world, which stays borrowed for `'w`.
+                f(entity_index, &mut *(component_a as *mut A));
+            }
+        });
+    }
+}
+
+impl<A, B, WC> QueryMut<WC> for (&mut A, &B)
+where
+    WC: WorldContainer + GetComponentContainer<A> + GetComponentContainer<B>,
+{
+    type Pointers = (
+        *mut <WC as GetComponentContainer<A>>::Container,
+        *const <WC as GetComponentContainer<B>>::Container,
+    );
+    type Item<'w>
+        = (&'w mut A, &'w B)
+    where
+        Self: 'w;
+
+    fn pointers(world: &mut World<WC>) -> Self::Pointers {
+        let (container_a, container_b) = world
+            .container_ptrs::<A, B>()
+            .expect("QueryMut components A and B share a container");
+
+        (container_a, container_b)
+    }
+
+    unsafe fn for_each_with_pointers<'w, F>(pointers: Self::Pointers, disabled: &'w [u32], mut f: F)
+    where
+        Self: 'w,
+        F: FnMut(usize, Self::Item<'w>),
+    {
+        let (container_a, container_b) = (&mut *pointers.0, &*pointers.1);
+
+        if container_a.is_vec_container() && container_b.is_vec_container() {
+            // Fast path: both containers are VecComponentContainers with Global
+            // allocator, as checked by is_vec_container().
+            let vec_container_a = cast_to_vec_container::<A, Global, _>(container_a);
+            let vec_container_b = cast_to_vec_container::<B, Global, _>(container_b);
+
+            zip(vec_container_a, vec_container_b).for_each_mut(
+                |entity_index, component_a, component_b| {
+                    if !is_disabled_in(disabled, entity_index) {
+                        f(
+                            entity_index,
+                            (&mut *(component_a as *mut A), &*(component_b as *const B)),
+                        );
+                    }
+                },
+            );
+            return;
+        }
+
+        container_a.for_each_mut(|entity_index, component_a| {
+            if is_disabled_in(disabled, entity_index) {
+                return;
+            }
+
+            record_lookup();
+            if let Some(component_b) = container_b.get_index(entity_index) {
+                // SAFETY: both containers are owned by the world, which stays
+                // borrowed for `'w`, and they are distinct, so the references
+                // do not alias.
+                f(
+                    entity_index,
+                    (&mut *(component_a as *mut A), &*(component_b as *const B)),
+                );
+            }
+        });
+    }
+}
+
+impl<A, B, WC> QueryMut<WC> for (&mut A, &mut B)
+where
+    WC: WorldContainer + GetComponentContainer<A> + GetComponentContainer<B>,
+{
+    type Pointers = (
+        *mut <WC as GetComponentContainer<A>>::Container,
+        *mut <WC as GetComponentContainer<B>>::Container,
+    );
+    type Item<'w>
+        = (&'w mut A, &'w mut B)
+    where
+        Self: 'w;
+
+    fn pointers(world: &mut World<WC>) -> Self::Pointers {
+        world
+            .container_ptrs::<A, B>()
+            .expect("QueryMut components A and B share a container")
+    }
+
+    unsafe fn for_each_with_pointers<'w, F>(pointers: Self::Pointers, disabled: &'w [u32], mut f: F)
+    where
+        Self: 'w,
+        F: FnMut(usize, Self::Item<'w>),
+    {
+        let (container_a, container_b) = (&mut *pointers.0, &mut *pointers.1);
+
+        if container_a.is_vec_container() && container_b.is_vec_container() {
+            // Fast path: both containers are VecComponentContainers with Global
+            // allocator, as checked by is_vec_container(), and distinct, as
+            // checked by `pointers`.
+            let vec_container_a =
+                &mut *(container_a as *mut _ as *mut VecComponentContainer<A, Global>);
+            let vec_container_b =
+                &mut *(container_b as *mut _ as *mut VecComponentContainer<B, Global>);
+
+            zip_mut(vec_container_a, vec_container_b).for_each_mut2(
+                |entity_index, component_a, component_b| {
+                    if !is_disabled_in(disabled, entity_index) {
+                        f(
+                            entity_index,
+                            (&mut *(component_a as *mut A), &mut *(component_b as *mut B)),
+                        );
+                    }
+                },
+            );
+            return;
+        }
+
+        container_a.for_each_mut(|entity_index, component_a| {
+            if is_disabled_in(disabled, entity_index) {
+                return;
+            }
+
+            record_lookup();
+            if let Some(component_b) = container_b.get_index_mut(entity_index) {
+                // SAFETY: both containers are owned by the world, which stays
+                // borrowed for `'w`, and they are distinct, so the references
+                // do not alias.
+                f(
+                    entity_index,
+                    (&mut *(component_a as *mut A), &mut *(component_b as *mut B)),
+                );
+            }
+        });
+    }
+}
+
+impl<WC: WorldContainer> World<WC> {
+    /// Runs a system over every enabled `A` and another over every enabled
+    /// `B`, as if by two [`World::for_each_mut`] calls.
+    ///
+    /// When both are stored in [`VecComponentContainer`]s, a single walk over
+    /// the slots serves both systems, so the loop and disabled checks are paid
+    /// once; an entity holding both components gets `fa` then `fb`. This only
+    /// pays off when most entities hold both: the walk visits every slot up to
+    /// the longer container's length, empty or not. Other containers are
+    /// simply iterated one after the other.
+    ///
+    /// Panics if `A` and `B` resolve to the same container.
+    pub fn run_fused<A, B, FA, FB>(&mut self, mut fa: FA, mut fb: FB)
+    where
+        WC: GetComponentContainer<A> + GetComponentContainer<B>,
+        FA: FnMut(usize, &mut A),
+        FB: FnMut(usize, &mut B),
+    {
+        let (container_a, container_b) = self
+            .container_ptrs::<A, B>()
+            .expect("run_fused components A and B share a container");
+
+        let disabled = &self.disabled;
+        // SAFETY: both containers are owned by `self`, which is borrowed for
+        // the whole call, and they are distinct, so the references do not
+        // alias. `disabled` is a separate field, so borrowing it leaves them
+        // untouched.
+        let (container_a, container_b) = unsafe { (&mut *container_a, &mut *container_b) };
+
+        if container_a.is_vec_container() && container_b.is_vec_container() {
+            // SAFETY: is_vec_container() only holds for `Global` vec containers.
+            let (vec_container_a, vec_container_b) = unsafe {
+                (
+                    &mut *(container_a as *mut _ as *mut VecComponentContainer<A, Global>),
+                    &mut *(container_b as *mut _ as *mut VecComponentContainer<B, Global>),
+                )
+            };
+            let _guards = [
+                IterationGuard::new(&vec_container_a.iterating),
+                IterationGuard::new(&vec_container_b.iterating),
+            ];
+
+            let slots_a = &mut vec_container_a.container;
+            let slots_b = &mut vec_container_b.container;
+            for entity_index in 0..slots_a.len().max(slots_b.len()) {
+                if is_disabled_in(disabled, entity_index) {
+                    continue;
+                }
+
+                if let Some(Some(component_a)) = slots_a.get_mut(entity_index) {
+                    fa(entity_index, component_a);
+                }
+                if let Some(Some(component_b)) = slots_b.get_mut(entity_index) {
+                    fb(entity_index, component_b);
+                }
+            }
+            return;
+        }
+
+        container_a.for_each_mut(|entity_index, component_a| {
+            if !is_disabled_in(disabled, entity_index) {
+                fa(entity_index, component_a);
+            }
+        });
+        container_b.for_each_mut(|entity_index, component_b| {
+            if !is_disabled_in(disabled, entity_index) {
+                fb(entity_index, component_b);
+            }
+        });
+    }
+
+    /// Calls `f` for every enabled entity matching the mutable query `Q`.
+    ///
+    /// The world stays borrowed mutably until `f` returns for the last entity,
+    /// so nothing else can touch it meanwhile.
+    ///
+    /// ```ignore
+    /// world.for_each_mut::<&mut Position, _>(|_, position| position.x += 1);
+    /// ```
+    pub fn for_each_mut<'w, Q, F>(&'w mut self, f: F)
+    where
+        Q: QueryMut<WC> + 'w,
+        F: FnMut(usize, Q::Item<'w>),
+    {
+        Q::for_each_mut(self, f);
+    }
+}
@@ -0,0 +1,144 @@
+//! Parent/child hierarchies kept consistent in both directions.
+//!
+//! A child holds a [`Parent`] component and its parent a [`Children`] list.
+//! Both must be listed in the world, and links must be made through
+//! [`World::set_parent`] and [`World::remove_parent`] rather than by adding
+//! either component directly, which would leave the other side out of date:
+//!
+//! ```ignore
+//! world!(MyWorld { Position, Parent, Children });
+//!
+//! let ship = world.spawn();
+//! let turret = world.spawn();
+//! world.set_parent(turret, ship);
+//!
+//! // Despawns the turret, then the ship.
+//! world.despawn_recursive(ship);
+//! ```
+
+use alloc::vec;
+use alloc::vec::Vec;
+
+use crate::{ComponentContainer, Entity, GetComponentContainer, World, WorldContainer};
+
+/// The entity this entity is attached to.
+#[derive(Clone, Copy)]
+pub struct Parent(pub Entity);
+
+/// The entities attached to this entity, in the order they were attached.
+#[derive(Clone, Default)]
+pub struct Children(Vec<Entity>);
+
+impl Children {
+    pub fn as_slice(&self) -> &[Entity] {
+        &self.0
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    fn remove(&mut self, child: Entity) {
+        self.0.retain(|&entity| !same(entity, child));
+    }
+}
+
+/// Compares handles by index and generation, ignoring the debug world id.
+fn same(a: Entity, b: Entity) -> bool {
+    a.index == b.index && a.generation == b.generation
+}
+
+impl<WC> World<WC>
+where
+    WC: WorldContainer + GetComponentContainer<Parent> + GetComponentContainer<Children>,
+{
+    /// Attaches `child` to `parent`, detaching it from its previous parent.
+    ///
+    /// Does nothing if either entity has been despawned, or if `parent` is
+    /// `child` or one of its descendants; the latter would make a cycle and
+    /// fails a debug assertion.
+    pub fn set_parent(&mut self, child: Entity, parent: Entity) {
+        if !self.is_current(child) || !self.is_current(parent) {
+            return;
+        }
+
+        let cycle = self.is_descendant_or_self(parent, child);
+        debug_assert!(
+            !cycle,
+            "attaching entity {} to {} would make a cycle",
+            child.index, parent.index
+        );
+        if cycle {
+            return;
+        }
+
+        self.remove_parent(child);
+        self.add(child, Parent(parent));
+        let children = self.get_mut::<Children>();
+        match children.get_mut(parent) {
+            Some(children) => children.0.push(child),
+            None => self.add(parent, Children(vec![child])),
+        }
+    }
+
+    /// Detaches `child` from its parent, returning the parent if it had one.
+    ///
+    /// The parent's [`Children`] component is kept, even once empty.
+    pub fn remove_parent(&mut self, child: Entity) -> Option<Entity> {
+        let Parent(parent) = self.remove::<Parent>(child)?;
+        if let Some(children) = self.get_mut::<Children>().get_mut(parent) {
+            children.remove(child);
+        }
+        Some(parent)
+    }
+
+    /// Despawns `entity` and all of its descendants.
+    ///
+    /// Deeper levels go first: every descendant is despawned before its
+    /// parent, and `entity` last, after being detached from its own parent.
+    /// Children already despawned on their own are skipped.
+    pub fn despawn_recursive(&mut self, entity: Entity) {
+        if !self.is_current(entity) {
+            return;
+        }
+        self.remove_parent(entity);
+
+        let mut order = vec![entity];
+        let mut next = 0;
+        while let Some(&current) = order.get(next) {
+            if let Some(children) = self.get::<Children>().get(current) {
+                order.extend(
+                    children
+                        .as_slice()
+                        .iter()
+                        .copied()
+                        .filter(|&child| self.is_current(child)),
+                );
+            }
+            next += 1;
+        }
+
+        for &entity in order.iter().rev() {
+            self.despawn(entity);
+        }
+    }
+
+    /// Returns whether `entity` is `ancestor` or one of its descendants.
+    fn is_descendant_or_self(&self, entity: Entity, ancestor: Entity) -> bool {
+        let parents = self.get::<Parent>();
+        let mut current = entity;
+        loop {
+            if same(current, ancestor) {
+                return true;
+            }
+            match parents.get(current) {
+                Some(&Parent(parent)) => current = parent,
+                None => return false,
+            }
+        }
+    }
+}
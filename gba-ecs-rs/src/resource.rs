@@ -0,0 +1,45 @@
+use crate::{World, WorldContainer};
+
+/// Gives access to the slot holding the world's single `R` resource.
+///
+/// Usually generated by the `resources { ... }` section of the
+/// [`world!`](crate::world!) macro, which stores each resource in its own
+/// `Option<R>` field rather than in a type-erased map.
+pub trait GetResource<R> {
+    fn get_resource(&self) -> &Option<R>;
+    fn get_resource_mut(&mut self) -> &mut Option<R>;
+}
+
+impl<WC: WorldContainer> World<WC> {
+    /// Stores `resource`, returning the previous value if there was one.
+    pub fn insert_resource<R>(&mut self, resource: R) -> Option<R>
+    where
+        WC: GetResource<R>,
+    {
+        self.containers.get_resource_mut().replace(resource)
+    }
+
+    /// Takes the `R` resource out of the world.
+    pub fn remove_resource<R>(&mut self) -> Option<R>
+    where
+        WC: GetResource<R>,
+    {
+        self.containers.get_resource_mut().take()
+    }
+
+    /// Returns the `R` resource, or `None` if it was never inserted.
+    pub fn resource<R>(&self) -> Option<&R>
+    where
+        WC: GetResource<R>,
+    {
+        self.containers.get_resource().as_ref()
+    }
+
+    /// Mutable variant of [`World::resource`].
+    pub fn resource_mut<R>(&mut self) -> Option<&mut R>
+    where
+        WC: GetResource<R>,
+    {
+        self.containers.get_resource_mut().as_mut()
+    }
+}
@@ -0,0 +1,114 @@
+//! Running systems in a fixed order every frame.
+//!
+//! A system is anything implementing [`System`], including every
+//! `FnMut(&mut World<WC>)`, so plain functions work:
+//!
+//! ```ignore
+//! fn movement(world: &mut World<MyWorld>) {
+//!     world.for_each_mut::<(&mut Position, &Velocity), _>(|_, (position, velocity)| {
+//!         position.x += velocity.dx;
+//!     });
+//! }
+//!
+//! let mut schedule = Schedule::new();
+//! schedule.add_system(movement);
+//! schedule.add_system_to_stage(Stage::PostUpdate, |world: &mut World<MyWorld>| {
+//!     world.update_events();
+//! });
+//!
+//! loop {
+//!     schedule.run_all(&mut world);
+//! }
+//! ```
+
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+
+use crate::{World, WorldContainer};
+
+/// Logic run against a world, typically once per frame.
+pub trait System<WC: WorldContainer> {
+    fn run(&mut self, world: &mut World<WC>);
+}
+
+impl<WC: WorldContainer, F: FnMut(&mut World<WC>)> System<WC> for F {
+    fn run(&mut self, world: &mut World<WC>) {
+        self(world);
+    }
+}
+
+/// Coarse ordering of systems within a frame, run in declaration order.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Stage {
+    /// Input and anything else later stages depend on.
+    PreUpdate,
+    /// Game logic. Systems go here unless told otherwise.
+    Update,
+    /// Rendering, event updates and other end of frame work.
+    PostUpdate,
+}
+
+impl Stage {
+    const COUNT: usize = 3;
+}
+
+/// An ordered list of systems, grouped by [`Stage`].
+///
+/// Within a stage, systems run in the order they were added.
+pub struct Schedule<WC: WorldContainer> {
+    stages: [Vec<Box<dyn System<WC>>>; Stage::COUNT],
+}
+
+impl<WC: WorldContainer> Schedule<WC> {
+    pub const fn new() -> Self {
+        Self {
+            stages: [Vec::new(), Vec::new(), Vec::new()],
+        }
+    }
+
+    /// Adds `system` at the end of the [`Stage::Update`] stage.
+    pub fn add_system<S: System<WC> + 'static>(&mut self, system: S) -> &mut Self {
+        self.add_system_to_stage(Stage::Update, system)
+    }
+
+    /// Adds `system` at the end of `stage`.
+    pub fn add_system_to_stage<S: System<WC> + 'static>(
+        &mut self,
+        stage: Stage,
+        system: S,
+    ) -> &mut Self {
+        self.stages[stage as usize].push(Box::new(system));
+        self
+    }
+
+    /// Returns the number of systems in every stage.
+    pub fn len(&self) -> usize {
+        self.stages.iter().map(Vec::len).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Runs every system once, stage by stage.
+    pub fn run_all(&mut self, world: &mut World<WC>) {
+        for stage in &mut self.stages {
+            for system in stage {
+                system.run(world);
+            }
+        }
+    }
+}
+
+impl<WC: WorldContainer> Default for Schedule<WC> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<WC: WorldContainer> World<WC> {
+    /// Runs `system` once against this world.
+    pub fn run<S: System<WC>>(&mut self, mut system: S) {
+        system.run(self);
+    }
+}
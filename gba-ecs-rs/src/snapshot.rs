@@ -0,0 +1,255 @@
+//! Saving and restoring a world's components to a flat byte buffer.
+//!
+//! Snapshots are meant for save data, which lives in SRAM on real hardware and
+//! can come back corrupted. Restoring therefore never panics on malformed input:
+//! the whole buffer is validated before the world is touched, and any problem is
+//! reported as a [`RestoreError`].
+//!
+//! # Format
+//!
+//! All integers are little-endian.
+//!
+//! ```text
+//! u16 version
+//! u32 entity count
+//! sections until the end of the buffer:
+//!     u16 component id, its ComponentId::ID
+//!     u32 entry count
+//!     per entry: u32 entity index, then the component's bytes
+//! ```
+//!
+//! Entries are written in the container's iteration order, so restoring them
+//! through `set` reproduces the same order. Sections are matched to
+//! containers by id, so reordering a world's components keeps old saves
+//! loadable as long as each component keeps its [`ComponentId`].
+
+use alloc::alloc::Allocator;
+use alloc::vec::Vec;
+
+use crate::{ComponentContainer, Entity, World, WorldContainer};
+
+/// Version written at the start of every snapshot.
+pub const SNAPSHOT_VERSION: u16 = 2;
+
+/// Reasons a snapshot can fail to restore.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RestoreError {
+    /// The snapshot was written by an incompatible format version.
+    VersionMismatch { expected: u16, found: u16 },
+    /// The buffer ended before the data it announces.
+    Truncated,
+    /// A section refers to a component id the world does not know.
+    UnknownComponent(u16),
+    /// An entry refers to an entity index past the snapshot's entity count.
+    EntityOutOfRange(usize),
+}
+
+/// The stable id a component's section is saved under.
+///
+/// Pick ids by hand and never reuse or change one once saves exist: unlike
+/// positions in a [`world!`](crate::world!) list, they survive refactors.
+///
+/// ```ignore
+/// impl ComponentId for Position {
+///     const ID: u16 = 7;
+/// }
+/// ```
+pub trait ComponentId {
+    const ID: u16;
+}
+
+/// Counts how many of `ids` equal `id`, for `world!`'s duplicate check.
+#[doc(hidden)]
+pub const fn __id_occurrences(id: u16, ids: &[u16]) -> usize {
+    let mut count = 0;
+    let mut i = 0;
+    while i < ids.len() {
+        if ids[i] == id {
+            count += 1;
+        }
+        i += 1;
+    }
+    count
+}
+
+/// A component with a fixed-size byte representation.
+pub trait Serialize {
+    /// Number of bytes written by `to_bytes`.
+    const SIZE: usize;
+
+    /// Writes the component into `out`, which is exactly `SIZE` bytes long.
+    fn to_bytes(&self, out: &mut [u8]);
+}
+
+/// A component that can be rebuilt from the bytes written by [`Serialize`].
+pub trait Deserialize: Serialize + Sized {
+    /// Reads a component from `bytes`, which is exactly `SIZE` bytes long.
+    fn from_bytes(bytes: &[u8]) -> Self;
+}
+
+/// A growable byte buffer snapshots are written to.
+///
+/// Implemented for `Vec<u8, A>` with any allocator, so a snapshot can be
+/// built directly in the memory region it is headed for.
+pub trait SnapshotBuffer {
+    /// Appends `len` zeroed bytes and returns them for writing.
+    fn append(&mut self, len: usize) -> &mut [u8];
+}
+
+impl<A: Allocator> SnapshotBuffer for Vec<u8, A> {
+    fn append(&mut self, len: usize) -> &mut [u8] {
+        let start = self.len();
+        self.resize(start + len, 0);
+        &mut self[start..]
+    }
+}
+
+/// Implemented by world containers whose components can be snapshotted.
+///
+/// The `snapshot { ... }` section of the [`world!`](crate::world!) macro
+/// generates it.
+pub trait WorldSnapshot: WorldContainer {
+    /// Writes one section per component container, using [`save_container`].
+    fn save_components(&self, out: &mut dyn SnapshotBuffer);
+
+    /// Returns the serialized size of the component with the given id, or `None`
+    /// if the id is unknown.
+    fn component_size(id: u16) -> Option<usize>;
+
+    /// Sets the component with the given id on `entity`, reading it from `bytes`.
+    fn restore_component(&mut self, id: u16, entity: Entity, bytes: &[u8]);
+}
+
+/// Writes a section holding every component of `container` under `id`.
+pub fn save_container<C, S, B>(id: u16, container: &S, out: &mut B)
+where
+    C: Serialize,
+    S: ComponentContainer<C>,
+    B: SnapshotBuffer + ?Sized,
+{
+    let count = container
+        .iter_indices()
+        .filter(|&index| container.get_index(index).is_some())
+        .count() as u32;
+    out.append(2).copy_from_slice(&id.to_le_bytes());
+    out.append(4).copy_from_slice(&count.to_le_bytes());
+
+    for index in container.iter_indices() {
+        if let Some(component) = container.get_index(index) {
+            out.append(4).copy_from_slice(&(index as u32).to_le_bytes());
+            component.to_bytes(out.append(C::SIZE));
+        }
+    }
+}
+
+/// Bounds-checked cursor over a snapshot buffer.
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], RestoreError> {
+        if self.bytes.len() < len {
+            return Err(RestoreError::Truncated);
+        }
+
+        let (taken, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        Ok(taken)
+    }
+
+    fn read_u16(&mut self) -> Result<u16, RestoreError> {
+        let bytes = self.take(2)?;
+        Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn read_u32(&mut self) -> Result<u32, RestoreError> {
+        let bytes = self.take(4)?;
+        Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+}
+
+/// Walks every entry in `bytes`, calling `f(id, index, component_bytes)`.
+///
+/// Returns the snapshot's entity count.
+fn walk<WC, F>(bytes: &[u8], mut f: F) -> Result<usize, RestoreError>
+where
+    WC: WorldSnapshot,
+    F: FnMut(u16, usize, &[u8]),
+{
+    let mut reader = Reader { bytes };
+
+    let version = reader.read_u16()?;
+    if version != SNAPSHOT_VERSION {
+        return Err(RestoreError::VersionMismatch {
+            expected: SNAPSHOT_VERSION,
+            found: version,
+        });
+    }
+
+    let entity_count = reader.read_u32()? as usize;
+
+    while !reader.bytes.is_empty() {
+        let id = reader.read_u16()?;
+        let size = WC::component_size(id).ok_or(RestoreError::UnknownComponent(id))?;
+        let entry_count = reader.read_u32()?;
+
+        for _ in 0..entry_count {
+            let index = reader.read_u32()? as usize;
+            if index >= entity_count {
+                return Err(RestoreError::EntityOutOfRange(index));
+            }
+            f(id, index, reader.take(size)?);
+        }
+    }
+
+    Ok(entity_count)
+}
+
+impl<WC: WorldSnapshot> World<WC> {
+    /// Appends a snapshot of every entity and component to `out`.
+    pub fn save<A: Allocator>(&self, out: &mut Vec<u8, A>) {
+        out.extend_from_slice(&SNAPSHOT_VERSION.to_le_bytes());
+        out.extend_from_slice(&(self.last_entity as u32).to_le_bytes());
+        self.containers.save_components(out);
+    }
+
+    /// Builds a new world from a snapshot written by [`World::save`].
+    pub fn load(bytes: &[u8]) -> Result<Self, RestoreError> {
+        let mut world = Self::new();
+        world.restore(bytes)?;
+        Ok(world)
+    }
+
+    /// Replaces the world's contents with a snapshot written by [`World::save`].
+    ///
+    /// The buffer is fully validated first; on error the world is left untouched.
+    pub fn restore(&mut self, bytes: &[u8]) -> Result<(), RestoreError> {
+        let entity_count = walk::<WC, _>(bytes, |_, _, _| {})?;
+
+        for index in 0..self.last_entity {
+            self.containers.remove_entity(Entity::new(index));
+        }
+        for index in 0..entity_count {
+            self.containers.add_entity(Entity::new(index));
+        }
+
+        let containers = &mut self.containers;
+        walk::<WC, _>(bytes, |id, index, component| {
+            containers.restore_component(id, Entity::new(index), component);
+        })?;
+        self.last_entity = entity_count;
+        self.clear_all_disabled();
+        self.reserved.clear();
+        self.generations.clear();
+        self.generations.resize(entity_count, 0);
+        self.free_indices.clear();
+        self.live.clear();
+        self.live.resize(entity_count, true);
+        self.signatures = (0..entity_count)
+            .map(|index| self.containers.signature(index))
+            .collect();
+
+        Ok(())
+    }
+}
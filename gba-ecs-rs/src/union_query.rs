@@ -0,0 +1,105 @@
+//! Visiting entities that have any of a set of components.
+//!
+//! Where a [`Query`](crate::Query) is an intersection, a union query visits
+//! every entity holding at least one of the listed components, once each:
+//!
+//! ```ignore
+//! world.for_each_any::<(Sprite, Text, Particle), _>(|entity_index, (sprite, text, particle)| {
+//!     // At least one of the three is `Some`.
+//! });
+//! ```
+
+use alloc::vec;
+use alloc::vec::Vec;
+
+use crate::instrumentation::record_visit;
+use crate::{ComponentContainer, GetComponentContainer, World, WorldContainer};
+
+/// A tuple of component types whose union can be visited with
+/// [`World::for_each_any`].
+///
+/// Implemented for tuples of two to four component types.
+pub trait UnionQuery<'a, WC: WorldContainer> {
+    /// One `Option<&C>` per component type, in the tuple's order.
+    type Item;
+
+    /// Calls `f` once for each enabled entity with any of the components.
+    ///
+    /// Entities are visited in the iteration order of the first container,
+    /// then those not seen yet from the second, and so on.
+    fn for_each<F>(world: &'a World<WC>, f: F)
+    where
+        F: FnMut(usize, Self::Item);
+}
+
+/// Entity indices already visited, one bit each.
+struct Visited {
+    words: Vec<u32>,
+}
+
+impl Visited {
+    fn with_slots(slots: usize) -> Self {
+        Self {
+            words: vec![0; slots.div_ceil(32)],
+        }
+    }
+
+    /// Marks `index` as visited, returning whether it was not already.
+    fn insert(&mut self, index: usize) -> bool {
+        let (word, bit) = (index / 32, 1 << (index % 32));
+        if self.words.len() <= word {
+            self.words.resize(word + 1, 0);
+        }
+
+        let fresh = self.words[word] & bit == 0;
+        self.words[word] |= bit;
+        fresh
+    }
+}
+
+macro_rules! impl_union_query {
+    ($($component:ident),+) => {
+        impl<'a, $($component: 'a,)+ WC> UnionQuery<'a, WC> for ($($component,)+)
+        where
+            WC: WorldContainer $(+ GetComponentContainer<$component>)+,
+        {
+            type Item = ($(Option<&'a $component>,)+);
+
+            fn for_each<F>(world: &'a World<WC>, mut f: F)
+            where
+                F: FnMut(usize, Self::Item),
+            {
+                let fetch = |entity_index| {
+                    ($(world.get::<$component>().get_index(entity_index),)+)
+                };
+                let mut visited = Visited::with_slots(world.last_entity);
+                $(
+                    for entity_index in world.get::<$component>().iter_indices() {
+                        if !visited.insert(entity_index) || world.is_index_disabled(entity_index) {
+                            continue;
+                        }
+
+                        record_visit();
+                        f(entity_index, fetch(entity_index));
+                    }
+                )+
+            }
+        }
+    };
+}
+
+impl_union_query!(A, B);
+impl_union_query!(A, B, C);
+impl_union_query!(A, B, C, D);
+
+impl<WC: WorldContainer> World<WC> {
+    /// Visits each enabled entity holding any of the components in `Q`, a
+    /// tuple of component types, once. See [`UnionQuery`].
+    pub fn for_each_any<Q, F>(&self, f: F)
+    where
+        Q: for<'a> UnionQuery<'a, WC>,
+        F: FnMut(usize, <Q as UnionQuery<'_, WC>>::Item),
+    {
+        Q::for_each(self, f);
+    }
+}
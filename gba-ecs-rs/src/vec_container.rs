@@ -1,18 +1,75 @@
+use crate::container::is_stale;
 use crate::ComponentContainer;
 use crate::Entity;
+use crate::EntityError;
+use crate::SetError;
+use crate::StorageKind;
 use alloc::alloc::Allocator;
 use alloc::vec::Vec;
+#[cfg(feature = "debug")]
+use core::any::type_name;
+use core::cell::Cell;
+use core::ops::ControlFlow;
+use core::ops::Range;
 
 pub struct VecComponentContainer<C, A: Allocator = alloc::alloc::Global> {
     pub(crate) container: Vec<Option<C>, A>,
+    /// Occupied slots in the order their components were added: `set` pushes
+    /// new ones to the tail and `remove` keeps the rest in order.
     pub(crate) active_indices: Vec<usize, A>,
+    /// Generation of the entity each slot's component was set for.
+    pub(crate) generations: Vec<u32, A>,
+    /// Set for each slot written through `set`, `get_mut` or `get_index_mut`
+    /// since the last `clear_change_ticks`.
+    pub(crate) changed: Vec<bool, A>,
+    pub(crate) iterating: Cell<usize>,
+    /// Structural version, see `ComponentContainer::version`.
+    version: u64,
+    /// Called when a component is stored in an empty slot, see `set_on_add`.
+    on_add: Option<fn(usize, &C)>,
+    /// Called when a component leaves the container, see `set_on_remove`.
+    on_remove: Option<fn(usize, &mut C)>,
+}
+
+/// Marks a container as being iterated for as long as it is alive.
+///
+/// Iteration caches raw pointers into the backing vecs, so anything that could
+/// reallocate them (`add_entity`, `set`, `remove_entity`) must not run meanwhile.
+pub(crate) struct IterationGuard<'a> {
+    depth: &'a Cell<usize>,
+}
+
+impl<'a> IterationGuard<'a> {
+    #[inline]
+    pub(crate) fn new(depth: &'a Cell<usize>) -> Self {
+        depth.set(depth.get() + 1);
+        Self { depth }
+    }
+}
+
+impl Drop for IterationGuard<'_> {
+    #[inline]
+    fn drop(&mut self) {
+        self.depth.set(self.depth.get() - 1);
+    }
 }
 
 impl<C> VecComponentContainer<C> {
     pub fn new() -> Self {
+        Self::new_empty()
+    }
+
+    /// Const variant of [`VecComponentContainer::new`], usable to initialize a `static`.
+    pub const fn new_empty() -> Self {
         Self {
             container: Vec::new(),
             active_indices: Vec::new(),
+            generations: Vec::new(),
+            changed: Vec::new(),
+            iterating: Cell::new(0),
+            version: 0,
+            on_add: None,
+            on_remove: None,
         }
     }
 }
@@ -23,19 +80,386 @@ impl<C> Default for VecComponentContainer<C> {
     }
 }
 
-impl<C, A: Allocator + Clone> VecComponentContainer<C, A> {
+/// Compares two strings in a const context.
+pub(crate) const fn str_eq(a: &str, b: &str) -> bool {
+    let (a, b) = (a.as_bytes(), b.as_bytes());
+    if a.len() != b.len() {
+        return false;
+    }
+
+    let mut i = 0;
+    while i < a.len() {
+        if a[i] != b[i] {
+            return false;
+        }
+        i += 1;
+    }
+
+    true
+}
+
+/// Allocators a [`VecComponentContainer`] can take the query fast path with.
+///
+/// The fast path casts containers to `VecComponentContainer<_, Global>`, so it
+/// is only taken when [`VecAllocator::IS_GLOBAL`] says `A` is `Global`. Other
+/// allocators keep the default `false` and use the fallback; implement the
+/// trait with an empty body to store components with a custom allocator.
+///
+/// # Safety
+///
+/// `IS_GLOBAL` must only be `true` for [`Global`](alloc::alloc::Global).
+pub unsafe trait VecAllocator: Allocator + Clone {
+    const IS_GLOBAL: bool = false;
+}
+
+// SAFETY: this is `Global`.
+unsafe impl VecAllocator for alloc::alloc::Global {
+    const IS_GLOBAL: bool = true;
+}
+
+// SAFETY: `IS_GLOBAL` is left `false`.
+unsafe impl VecAllocator for agb::ExternalAllocator {}
+
+// SAFETY: `IS_GLOBAL` is left `false`.
+unsafe impl VecAllocator for agb::InternalAllocator {}
+
+impl<C, A: VecAllocator> VecComponentContainer<C, A> {
+    /// Whether queries may take the vec fast path for this container, see
+    /// [`VecAllocator`].
+    pub const VEC_FAST_PATH_SAFE: bool = A::IS_GLOBAL;
+
     pub fn new_in(allocator: A) -> Self {
         Self {
             container: Vec::new_in(allocator.clone()),
-            active_indices: Vec::new_in(allocator),
+            active_indices: Vec::new_in(allocator.clone()),
+            generations: Vec::new_in(allocator.clone()),
+            changed: Vec::new_in(allocator),
+            iterating: Cell::new(0),
+            version: 0,
+            on_add: None,
+            on_remove: None,
+        }
+    }
+
+    /// Creates a container with room for `capacity` entities, so it does not
+    /// reallocate until that many have been added.
+    ///
+    /// Each slot costs `size_of::<Option<C>>()` bytes plus 9 bytes of
+    /// bookkeeping on the GBA (generation, change flag and active index), all
+    /// allocated up front from `allocator`: size the ceiling with IWRAM/EWRAM
+    /// in mind.
+    pub fn with_capacity_in(capacity: usize, allocator: A) -> Self {
+        Self {
+            container: Vec::with_capacity_in(capacity, allocator.clone()),
+            active_indices: Vec::with_capacity_in(capacity, allocator.clone()),
+            generations: Vec::with_capacity_in(capacity, allocator.clone()),
+            changed: Vec::with_capacity_in(capacity, allocator),
+            iterating: Cell::new(0),
+            version: 0,
+            on_add: None,
+            on_remove: None,
+        }
+    }
+
+    /// Sets the function called with each component stored in an empty slot,
+    /// including those copied in by `clone_into`, e.g. to keep a spatial hash
+    /// in sync. Overwriting a component does not call it.
+    ///
+    /// It runs once the component is in place and its index is listed in
+    /// `active_indices`. Being a plain `fn`, it cannot capture state: keep
+    /// derived data in a `static` or a resource updated afterwards.
+    pub fn set_on_add(&mut self, hook: Option<fn(usize, &C)>) {
+        self.on_add = hook;
+    }
+
+    /// Sets the function called with each component leaving the container,
+    /// through `remove`, `remove_entity`, `drain_where`, `truncate`, `clear`,
+    /// or `clone_into` replacing this container's contents.
+    ///
+    /// It runs after the slot is emptied and its index dropped from
+    /// `active_indices`, just before the component is returned or dropped.
+    pub fn set_on_remove(&mut self, hook: Option<fn(usize, &mut C)>) {
+        self.on_remove = hook;
+    }
+
+    /// Drops every slot at index `new_len` and above, along with its component.
+    ///
+    /// Live components in the dropped slots are dropped as well. Entities past
+    /// `new_len` must be re-added with `add_entity` before they can hold a
+    /// component again.
+    pub fn truncate(&mut self, new_len: usize) {
+        self.assert_not_iterating();
+
+        if let Some(on_remove) = self.on_remove {
+            self.active_indices.retain(|&index| index < new_len);
+            for (index, slot) in self.container.iter_mut().enumerate().skip(new_len) {
+                if let Some(mut component) = slot.take() {
+                    on_remove(index, &mut component);
+                }
+            }
+        }
+
+        self.version += 1;
+        self.container.truncate(new_len);
+        self.generations.truncate(new_len);
+        self.changed.truncate(new_len);
+        self.active_indices.retain(|&index| index < new_len);
+
+        debug_assert!(self.validate());
+    }
+
+    /// Moves every component out as `(index, component)` pairs, in insertion
+    /// order, and calls `on_remove` on each.
+    ///
+    /// The container is left empty with its slots still allocated, so it can
+    /// be filled again without growing. The returned `Vec` uses the
+    /// container's allocator.
+    pub fn take_all(&mut self) -> Vec<(usize, C), A> {
+        self.assert_not_iterating();
+
+        let mut taken = Vec::with_capacity_in(
+            self.active_indices.len(),
+            self.container.allocator().clone(),
+        );
+        for index in self.active_indices.drain(..) {
+            if let Some(mut component) = self.container[index].take() {
+                self.changed[index] = false;
+                if let Some(on_remove) = self.on_remove {
+                    on_remove(index, &mut component);
+                }
+                taken.push((index, component));
+            }
+        }
+
+        if !taken.is_empty() {
+            self.version += 1;
+        }
+        taken
+    }
+
+    /// Like `get`, but tells a bad handle apart from a missing component.
+    ///
+    /// Returns `Err(OutOfRange)` if the index is past `len()`, and
+    /// `Err(Stale)` if the slot holds a component set for another generation.
+    /// An empty slot keeps no generation, so it always gives `Ok(None)`.
+    pub fn get_checked(&self, entity: Entity) -> Result<Option<&C>, EntityError> {
+        let slot = self
+            .container
+            .get(entity.index)
+            .ok_or(EntityError::OutOfRange(entity.index))?;
+
+        match slot {
+            Some(_) if self.generations[entity.index] != entity.generation => {
+                Err(EntityError::Stale)
+            }
+            slot => Ok(slot.as_ref()),
+        }
+    }
+
+    /// Drops the empty slots past the highest live component, then frees the
+    /// spare capacity, e.g. after a wave of enemies has been despawned.
+    ///
+    /// No component moves, so entity indices and handles stay valid; `set`
+    /// grows the container again for an entity past the new end.
+    pub fn compact(&mut self) {
+        let len = self
+            .active_indices
+            .iter()
+            .max()
+            .map_or(0, |&index| index + 1);
+        if len < self.container.len() {
+            self.truncate(len);
+        }
+
+        self.container.shrink_to_fit();
+        self.active_indices.shrink_to_fit();
+        self.generations.shrink_to_fit();
+        self.changed.shrink_to_fit();
+    }
+
+    /// Returns the live indices and the backing slots, read-only.
+    ///
+    /// The first slice lists every index holding a component, in insertion order;
+    /// the second is indexed by entity index, with `None` for empty slots.
+    pub fn raw_parts(&self) -> (&[usize], &[Option<C>]) {
+        (&self.active_indices, &self.container)
+    }
+
+    /// Returns the backing slots, indexed by entity index, with `None` for
+    /// empty slots.
+    pub fn as_slice(&self) -> &[Option<C>] {
+        &self.container
+    }
+
+    /// Returns the backing slots for hand-written loops over the components.
+    ///
+    /// Only write through the `Some` slots. Taking or filling a slot here
+    /// leaves `active_indices` out of date, so sparse iteration and `count`
+    /// go wrong until `validate` would pass again; use `set` and `remove` to
+    /// add or drop components. Like `for_each_mut`, nothing is marked as
+    /// changed.
+    pub fn as_mut_slice(&mut self) -> &mut [Option<C>] {
+        self.assert_not_iterating();
+        &mut self.container
+    }
+
+    /// Returns every index holding a component, in insertion order.
+    pub fn active_indices(&self) -> &[usize] {
+        &self.active_indices
+    }
+
+    /// Returns `entity`'s component, first setting it to `f()` if the slot is
+    /// empty.
+    ///
+    /// Returns `None` without calling `f` if the slot holds a component set
+    /// for another generation, so a stale handle never overwrites the
+    /// component of the entity now using its index.
+    ///
+    /// Slots are never handed out as `&mut Option<C>`: filling one behind the
+    /// container's back would leave it out of `active_indices`, and sparse
+    /// iteration would skip it. This is the way to fill a slot in place.
+    pub fn get_or_insert_with<F>(&mut self, entity: Entity, f: F) -> Option<&mut C>
+    where
+        F: FnOnce() -> C,
+    {
+        match self.container.get(entity.index) {
+            Some(Some(_)) if self.generations[entity.index] != entity.generation => return None,
+            Some(Some(_)) => {}
+            _ => {
+                self.set(entity, f());
+                debug_assert!(self.validate());
+            }
+        }
+
+        self.changed[entity.index] = true;
+        self.container[entity.index].as_mut()
+    }
+
+    /// Returns whether `active_indices` lists exactly the occupied slots, each
+    /// once. Meant for tests and debug assertions: it walks every slot.
+    pub fn validate(&self) -> bool {
+        self.check_invariants()
+    }
+
+    /// Prints `len`, `count` and `active_indices` with `agb::println!`, then
+    /// every inconsistency between `active_indices` and the slots: listed
+    /// indices whose slot is empty, out of range or listed twice, and filled
+    /// slots missing from the list.
+    ///
+    /// Fails a debug assertion if any was found. Only built with the `debug`
+    /// feature, so it never ends up in a shipping ROM.
+    #[cfg(feature = "debug")]
+    pub fn debug_dump(&self) {
+        agb::println!(
+            "[{}] len={} count={} active_indices={:?}",
+            type_name::<C>(),
+            self.container.len(),
+            self.count(),
+            &self.active_indices[..]
+        );
+
+        let mut consistent = true;
+        let mut listed = Vec::new();
+        listed.resize(self.container.len(), false);
+        for &index in &self.active_indices {
+            match self.container.get(index) {
+                None => agb::println!("  active index {} is out of range", index),
+                Some(None) => agb::println!("  active index {} has an empty slot", index),
+                Some(Some(_)) if listed[index] => {
+                    agb::println!("  active index {} is listed twice", index)
+                }
+                Some(Some(_)) => {
+                    listed[index] = true;
+                    continue;
+                }
+            }
+            consistent = false;
+        }
+        for (index, slot) in self.container.iter().enumerate() {
+            if slot.is_some() && !listed[index] {
+                agb::println!("  slot {} is filled but not in active_indices", index);
+                consistent = false;
+            }
+        }
+
+        debug_assert!(
+            consistent,
+            "{} container is out of sync with its active indices",
+            type_name::<C>()
+        );
+    }
+
+    /// Returns whether an iteration over this container is in progress.
+    pub fn is_iterating(&self) -> bool {
+        self.iterating.get() > 0
+    }
+
+    /// Returns an iterator over the live components, in ascending index order.
+    pub fn iter(&self) -> impl Iterator<Item = (usize, &C)> + '_ {
+        self.container
+            .iter()
+            .enumerate()
+            .filter_map(|(index, slot)| slot.as_ref().map(|component| (index, component)))
+    }
+
+    /// Mutable variant of [`VecComponentContainer::iter`].
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (usize, &mut C)> + '_ {
+        self.container
+            .iter_mut()
+            .enumerate()
+            .filter_map(|(index, slot)| slot.as_mut().map(|component| (index, component)))
+    }
+
+    /// Returns an iterator over the live components in insertion order, only
+    /// touching occupied slots.
+    pub fn sparse_iter(&self) -> impl Iterator<Item = (usize, &C)> + '_ {
+        self.active_indices.iter().filter_map(|&index| {
+            self.container[index]
+                .as_ref()
+                .map(|component| (index, component))
+        })
+    }
+
+    /// Visits the live components ordered by `key`, e.g. sprites back to
+    /// front by their `z`. Ties are visited in ascending index order.
+    ///
+    /// The order is computed in `scratch`, which is overwritten; passing the
+    /// same `Vec` every frame avoids allocating once it has grown. Storage
+    /// itself is not reordered.
+    pub fn for_each_sorted_by<K, KF, F>(&self, scratch: &mut Vec<usize>, key: KF, mut f: F)
+    where
+        K: Ord,
+        KF: Fn(&C) -> K,
+        F: FnMut(usize, &C),
+    {
+        let _guard = IterationGuard::new(&self.iterating);
+        let component = |index: usize| self.container[index].as_ref().unwrap();
+
+        scratch.clear();
+        scratch.extend_from_slice(&self.active_indices);
+        scratch
+            .sort_unstable_by(|&a, &b| key(component(a)).cmp(&key(component(b))).then(a.cmp(&b)));
+
+        for &index in scratch.iter() {
+            f(index, component(index));
         }
     }
 
+    #[inline]
+    fn assert_not_iterating(&self) {
+        debug_assert!(
+            !self.is_iterating(),
+            "VecComponentContainer modified while it is being iterated; \
+             this can reallocate storage the iteration still points into"
+        );
+    }
+
     #[inline]
     pub fn for_each_fast<F>(&self, mut f: F)
     where
         F: FnMut(usize, &C),
     {
+        let _guard = IterationGuard::new(&self.iterating);
         let len = self.container.len();
         let ptr = self.container.as_ptr();
 
@@ -54,6 +478,7 @@ impl<C, A: Allocator + Clone> VecComponentContainer<C, A> {
     where
         F: FnMut(usize, &mut C),
     {
+        let _guard = IterationGuard::new(&self.iterating);
         let len = self.container.len();
         let ptr = self.container.as_mut_ptr();
 
@@ -66,16 +491,265 @@ impl<C, A: Allocator + Clone> VecComponentContainer<C, A> {
             }
         }
     }
+    /// Visits the components of entities in `range` only, in ascending index order.
+    ///
+    /// Useful to spread heavy per-entity work across frames by advancing the range
+    /// each frame. The range is clamped to the container's length.
+    #[inline]
+    pub fn for_each_range<F>(&self, range: Range<usize>, mut f: F)
+    where
+        F: FnMut(usize, &C),
+    {
+        let _guard = IterationGuard::new(&self.iterating);
+        let end = range.end.min(self.container.len());
+        let start = range.start.min(end);
+
+        for (offset, slot) in self.container[start..end].iter().enumerate() {
+            if let Some(component) = slot {
+                f(start + offset, component);
+            }
+        }
+    }
+
+    /// Mutable variant of [`VecComponentContainer::for_each_range`].
+    #[inline]
+    pub fn for_each_range_mut<F>(&mut self, range: Range<usize>, mut f: F)
+    where
+        F: FnMut(usize, &mut C),
+    {
+        let _guard = IterationGuard::new(&self.iterating);
+        let end = range.end.min(self.container.len());
+        let start = range.start.min(end);
+
+        for (offset, slot) in self.container[start..end].iter_mut().enumerate() {
+            if let Some(component) = slot {
+                f(start + offset, component);
+            }
+        }
+    }
 }
 
-impl<C, A: Allocator + Clone> ComponentContainer<C> for VecComponentContainer<C, A> {
+impl<C, A: VecAllocator> ComponentContainer<C> for VecComponentContainer<C, A> {
+    /// Insertion order, see `active_indices`.
+    const ORDERED: bool = true;
+
     fn add_entity(&mut self, entity: Entity) {
-        while self.container.len() <= entity.index {
-            self.container.push(None);
+        self.assert_not_iterating();
+
+        if self.container.len() <= entity.index {
+            let len = entity.index + 1;
+            self.container.resize_with(len, || None);
+            self.generations.resize(len, 0);
+            self.changed.resize(len, false);
+        }
+    }
+
+    fn remove_entity(&mut self, entity: Entity) {
+        self.remove(entity);
+    }
+
+    fn remove(&mut self, entity: Entity) -> Option<C> {
+        self.assert_not_iterating();
+
+        let index = entity.index;
+        let mut component = self.container.get_mut(index)?.take()?;
+        self.changed[index] = false;
+        self.version += 1;
+
+        if let Some(position) = self.active_indices.iter().position(|&i| i == index) {
+            self.active_indices.remove(position);
+        }
+
+        if let Some(on_remove) = self.on_remove {
+            on_remove(index, &mut component);
+        }
+
+        Some(component)
+    }
+
+    fn reserve(&mut self, additional: usize) {
+        self.assert_not_iterating();
+
+        self.container.reserve(additional);
+        self.active_indices.reserve(additional);
+        self.generations.reserve(additional);
+        self.changed.reserve(additional);
+    }
+
+    fn shrink_to_fit(&mut self) {
+        self.compact();
+    }
+
+    /// Truncates the backing vecs to zero length.
+    ///
+    /// Slots are not overwritten: for components without drop glue this costs
+    /// no memory writes at all, so there is nothing for a DMA fill to speed up.
+    /// An `on_remove` hook is the exception, as each component is taken out
+    /// for it.
+    fn clear(&mut self) {
+        self.assert_not_iterating();
+
+        if let Some(on_remove) = self.on_remove {
+            for index in self.active_indices.drain(..) {
+                if let Some(mut component) = self.container[index].take() {
+                    on_remove(index, &mut component);
+                }
+            }
+        }
+
+        self.container.clear();
+        self.active_indices.clear();
+        self.generations.clear();
+        self.changed.clear();
+        self.version += 1;
+    }
+
+    /// Takes matching components out in a single pass over `active_indices`,
+    /// which is compacted as it goes rather than shifted once per removal.
+    fn drain_where<P, F>(&mut self, mut pred: P, mut f: F)
+    where
+        P: FnMut(&C) -> bool,
+        F: FnMut(usize, C),
+    {
+        self.assert_not_iterating();
+
+        let container = &mut self.container;
+        let changed = &mut self.changed;
+        let on_remove = self.on_remove;
+        let version = &mut self.version;
+        self.active_indices.retain(|&index| {
+            let slot = &mut container[index];
+            if !slot.as_ref().is_some_and(&mut pred) {
+                return true;
+            }
+
+            changed[index] = false;
+            *version += 1;
+            if let Some(mut component) = slot.take() {
+                if let Some(on_remove) = on_remove {
+                    on_remove(index, &mut component);
+                }
+                f(index, component);
+            }
+            false
+        });
+    }
+
+    /// Swaps the slots in place. Components are moved rather than removed and
+    /// set again, so the add and remove hooks are not called; the moved
+    /// components are marked as changed.
+    fn swap(&mut self, a: Entity, b: Entity) {
+        self.assert_not_iterating();
+
+        let has_a = self.get_index(a.index).is_some();
+        let has_b = self.get_index(b.index).is_some();
+        if a.index == b.index || !(has_a || has_b) {
+            return;
+        }
+
+        self.add_entity(Entity::new(a.index.max(b.index)));
+        self.container.swap(a.index, b.index);
+
+        if has_a != has_b {
+            let (from, to) = if has_a {
+                (a.index, b.index)
+            } else {
+                (b.index, a.index)
+            };
+            if let Some(index) = self.active_indices.iter_mut().find(|index| **index == from) {
+                *index = to;
+            }
+            self.version += 1;
+        }
+
+        for entity in [a, b] {
+            if self.container[entity.index].is_some() {
+                self.generations[entity.index] = entity.generation;
+                self.changed[entity.index] = true;
+            }
+        }
+
+        debug_assert!(self.validate());
+    }
+
+    /// Takes the component out of `from`'s slot and puts it in `to`'s without
+    /// calling the hooks for it, like `swap`. A component `to` had is removed
+    /// as by `remove`, remove hook included, then dropped.
+    fn move_component(&mut self, from: Entity, to: Entity) -> bool {
+        self.assert_not_iterating();
+
+        if self.get_index(from.index).is_none() {
+            return false;
+        }
+        if from.index == to.index {
+            return true;
+        }
+
+        self.add_entity(to);
+        self.remove(Entity::new(to.index));
+        let component = self.container[from.index].take();
+        self.changed[from.index] = false;
+
+        if let Some(index) = self
+            .active_indices
+            .iter_mut()
+            .find(|index| **index == from.index)
+        {
+            *index = to.index;
+        }
+
+        self.container[to.index] = component;
+        self.generations[to.index] = to.generation;
+        self.changed[to.index] = true;
+        self.version += 1;
+
+        debug_assert!(self.validate());
+        true
+    }
+
+    fn merge_from(&mut self, mut other: Self, offset: usize) {
+        for &index in &other.active_indices {
+            if let Some(component) = other.container[index].take() {
+                let entity = Entity::with_generation(index + offset, other.generations[index]);
+                self.set(entity, component);
+            }
+        }
+    }
+
+    fn clone_into(&self, other: &mut Self)
+    where
+        C: Clone,
+    {
+        other.assert_not_iterating();
+
+        if other.on_remove.is_some() {
+            other.clear();
+        }
+
+        other.container.clear();
+        other.container.extend_from_slice(&self.container);
+        other.generations.clear();
+        other.generations.extend_from_slice(&self.generations);
+        other.changed.clear();
+        other.changed.extend_from_slice(&self.changed);
+        other.active_indices.clear();
+        other.active_indices.extend_from_slice(&self.active_indices);
+        other.version += 1;
+
+        if let Some(on_add) = other.on_add {
+            for &index in &other.active_indices {
+                if let Some(component) = &other.container[index] {
+                    on_add(index, component);
+                }
+            }
         }
     }
 
     fn get(&self, entity: Entity) -> Option<&C> {
+        if self.generations.get(entity.index) != Some(&entity.generation) {
+            return None;
+        }
+
         if let Some(Some(component)) = self.container.get(entity.index) {
             return Some(component);
         }
@@ -92,7 +766,12 @@ impl<C, A: Allocator + Clone> ComponentContainer<C> for VecComponentContainer<C,
     }
 
     fn get_mut(&mut self, entity: Entity) -> Option<&mut C> {
+        if self.generations.get(entity.index) != Some(&entity.generation) {
+            return None;
+        }
+
         if let Some(Some(component)) = self.container.get_mut(entity.index) {
+            self.changed[entity.index] = true;
             return Some(component);
         }
 
@@ -101,36 +780,105 @@ impl<C, A: Allocator + Clone> ComponentContainer<C> for VecComponentContainer<C,
 
     fn get_index_mut(&mut self, entity: usize) -> Option<&mut C> {
         if let Some(Some(component)) = self.container.get_mut(entity) {
+            self.changed[entity] = true;
             return Some(component);
         }
 
         None
     }
 
+    /// Grows the container to fit `entity` first if `add_entity` was never
+    /// called for it, e.g. for a handle built with `Entity::new`.
     fn set(&mut self, entity: Entity, component: C) {
+        self.assert_not_iterating();
+
         let index = entity.index;
+        if self.container.len() <= index {
+            self.add_entity(entity);
+        }
 
-        let is_new_component = self
-            .container
-            .get(index)
-            .map(|opt| opt.is_none())
-            .unwrap_or(true);
+        let is_new_component = self.container[index].is_none();
 
         self.container[index] = Some(component);
+        self.generations[index] = entity.generation;
+        self.changed[index] = true;
 
         if is_new_component {
             self.active_indices.push(index);
+            self.version += 1;
+
+            if let (Some(on_add), Some(component)) = (self.on_add, &self.container[index]) {
+                on_add(index, component);
+            }
         }
     }
 
+    fn try_set(&mut self, entity: Entity, component: C) -> Result<(), SetError> {
+        if is_stale(self, entity) {
+            return Err(SetError::StaleEntity);
+        }
+        let index = entity.index;
+        let len = index
+            .checked_add(1)
+            .ok_or(SetError::IndexOutOfRange(index))?;
+
+        if let Some(additional) = len.checked_sub(self.container.len()) {
+            self.container
+                .try_reserve(additional)
+                .and(self.generations.try_reserve(additional))
+                .and(self.changed.try_reserve(additional))
+                .map_err(|_| SetError::AllocationFailed)?;
+            self.add_entity(entity);
+        }
+        self.active_indices
+            .try_reserve(1)
+            .map_err(|_| SetError::AllocationFailed)?;
+
+        self.set(entity, component);
+        Ok(())
+    }
+
     fn len(&self) -> usize {
         self.container.len()
     }
 
+    fn version(&self) -> u64 {
+        self.version
+    }
+
+    /// `set` only records an index the first time its slot is filled and
+    /// `remove` drops it again, so `active_indices` holds each live index once.
+    fn count(&self) -> usize {
+        self.active_indices.len()
+    }
+
+    fn for_each_changed<F>(&self, mut f: F)
+    where
+        F: FnMut(usize, &C),
+    {
+        let _guard = IterationGuard::new(&self.iterating);
+        for &index in &self.active_indices {
+            if self.changed[index] {
+                if let Some(Some(component)) = self.container.get(index) {
+                    f(index, component);
+                }
+            }
+        }
+    }
+
+    fn clear_change_ticks(&mut self) {
+        self.changed.fill(false);
+    }
+
+    fn iter_indices(&self) -> impl Iterator<Item = usize> + '_ {
+        self.active_indices.iter().copied()
+    }
+
     fn for_each<F>(&self, mut f: F)
     where
         F: FnMut(usize, &C),
     {
+        let _guard = IterationGuard::new(&self.iterating);
         for &index in &self.active_indices {
             if let Some(Some(component)) = self.container.get(index) {
                 f(index, component);
@@ -138,10 +886,25 @@ impl<C, A: Allocator + Clone> ComponentContainer<C> for VecComponentContainer<C,
         }
     }
 
+    fn try_for_each<B, F>(&self, mut f: F) -> ControlFlow<B>
+    where
+        F: FnMut(usize, &C) -> ControlFlow<B>,
+    {
+        let _guard = IterationGuard::new(&self.iterating);
+        for &index in &self.active_indices {
+            if let Some(Some(component)) = self.container.get(index) {
+                f(index, component)?;
+            }
+        }
+
+        ControlFlow::Continue(())
+    }
+
     fn for_each_mut<F>(&mut self, mut f: F)
     where
         F: FnMut(usize, &mut C),
     {
+        let _guard = IterationGuard::new(&self.iterating);
         for &index in &self.active_indices {
             if let Some(Some(component)) = self.container.get_mut(index) {
                 f(index, component);
@@ -150,6 +913,36 @@ impl<C, A: Allocator + Clone> ComponentContainer<C> for VecComponentContainer<C,
     }
 
     fn is_vec_container(&self) -> bool {
+        Self::VEC_FAST_PATH_SAFE
+    }
+
+    fn generation_at(&self, index: usize) -> u32 {
+        self.generations.get(index).copied().unwrap_or(0)
+    }
+
+    fn storage_kind(&self) -> StorageKind {
+        if Self::VEC_FAST_PATH_SAFE {
+            StorageKind::Vec
+        } else {
+            StorageKind::VecInAllocator
+        }
+    }
+
+    fn check_invariants(&self) -> bool {
+        let live = self.container.iter().filter(|slot| slot.is_some()).count();
+        if live != self.active_indices.len() {
+            return false;
+        }
+
+        let mut seen = Vec::new();
+        seen.resize(self.container.len(), false);
+        for &index in &self.active_indices {
+            match self.container.get(index) {
+                Some(Some(_)) if !seen[index] => seen[index] = true,
+                _ => return false,
+            }
+        }
+
         true
     }
 }
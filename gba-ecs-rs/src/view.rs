@@ -0,0 +1,119 @@
+use crate::{
+    ComponentContainer, Entity, EntityError, GetComponentContainer, GetResource, Query, World,
+    WorldContainer,
+};
+
+/// Read-only access to a [`World`], for systems that must not change it.
+///
+/// Only the reading methods are forwarded, so a system taking a `WorldView`
+/// cannot spawn, despawn, or write components, e.g. a render system that
+/// should never touch simulation state:
+///
+/// ```ignore
+/// fn draw(view: WorldView<'_, MyWorld>) {
+///     view.for_each::<(&Position, &Sprite), _>(|_, (position, sprite)| {
+///         // Draw the sprite at the position.
+///     });
+/// }
+///
+/// draw(world.as_view());
+/// ```
+pub struct WorldView<'a, WC: WorldContainer> {
+    world: &'a World<WC>,
+}
+
+impl<WC: WorldContainer> Clone for WorldView<'_, WC> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<WC: WorldContainer> Copy for WorldView<'_, WC> {}
+
+impl<'a, WC: WorldContainer> WorldView<'a, WC> {
+    /// Returns the `C` container. Only its `&self` methods are reachable.
+    pub fn get<C>(&self) -> &'a <WC as GetComponentContainer<C>>::Container
+    where
+        WC: GetComponentContainer<C>,
+    {
+        self.world.get::<C>()
+    }
+
+    /// See [`World::get_checked`].
+    pub fn get_checked<C>(&self, entity: Entity) -> Result<Option<&'a C>, EntityError>
+    where
+        WC: GetComponentContainer<C>,
+    {
+        self.world.get_checked::<C>(entity)
+    }
+
+    /// See [`World::has`].
+    pub fn has<C>(&self, entity: Entity) -> bool
+    where
+        WC: GetComponentContainer<C>,
+    {
+        self.world.has::<C>(entity)
+    }
+
+    /// See [`World::count`].
+    pub fn count<C>(&self) -> usize
+    where
+        WC: GetComponentContainer<C>,
+    {
+        self.get::<C>().count()
+    }
+
+    /// See [`World::resource`].
+    pub fn resource<R>(&self) -> Option<&'a R>
+    where
+        WC: GetResource<R>,
+    {
+        self.world.resource::<R>()
+    }
+
+    pub fn is_alive(&self, entity: Entity) -> bool {
+        self.world.is_alive(entity)
+    }
+
+    pub fn is_disabled(&self, entity: Entity) -> bool {
+        self.world.is_disabled(entity)
+    }
+
+    pub fn live_count(&self) -> usize {
+        self.world.live_count()
+    }
+
+    /// See [`World::for_each`].
+    pub fn for_each<Q, F>(&self, f: F)
+    where
+        Q: Query<'a, WC>,
+        F: FnMut(usize, Q::Item),
+    {
+        Q::for_each(self.world, f);
+    }
+
+    /// See [`World::for_each_entity`].
+    pub fn for_each_entity<Q, F>(&self, f: F)
+    where
+        Q: Query<'a, WC>,
+        F: FnMut(Entity, Q::Item),
+    {
+        Q::for_each_entity(self.world, f);
+    }
+
+    /// See [`World::for_each_sparse`].
+    pub fn for_each_sparse<Q, F>(&self, f: F)
+    where
+        Q: Query<'a, WC>,
+        F: FnMut(usize, Q::Item),
+    {
+        Q::for_each_sparse(self.world, f);
+    }
+}
+
+impl<WC: WorldContainer> World<WC> {
+    /// Returns a [`WorldView`] that can only read this world.
+    pub fn as_view(&self) -> WorldView<'_, WC> {
+        WorldView { world: self }
+    }
+}
@@ -1,30 +1,727 @@
-use crate::{ComponentContainer, Entity, GetComponentContainer};
+use alloc::vec::Vec;
+use core::ops::ControlFlow;
+use core::ptr;
+#[cfg(debug_assertions)]
+use portable_atomic::{AtomicU16, Ordering};
+
+use crate::vec_container::str_eq;
+use crate::{
+    ComponentContainer, Entity, EntityError, GetComponentContainer, SetError, StorageKind,
+    ValidationLevel,
+};
 
 pub struct World<WC: WorldContainer> {
-    last_entity: usize,
-    containers: WC,
+    pub(crate) last_entity: usize,
+    pub(crate) containers: WC,
+    #[cfg(feature = "validation")]
+    validation: ValidationLevel,
+    /// One bit per entity index, set while the entity is disabled.
+    pub(crate) disabled: Vec<u32>,
+    disabled_count: usize,
+    /// One bit per entity index, set while the entity is reserved but not
+    /// materialized, see `reserve_entity`.
+    pub(crate) reserved: Vec<u32>,
+    /// Current generation of each entity index.
+    pub(crate) generations: Vec<u32>,
+    /// Despawned indices waiting to be reused by `spawn`.
+    pub(crate) free_indices: Vec<usize>,
+    /// Whether each entity index below `last_entity` is alive, see `entities`.
+    pub(crate) live: Vec<bool>,
+    /// Component bitmask of each entity index, see `signature`. Indices past
+    /// the end have no components.
+    pub(crate) signatures: Vec<u64>,
+    /// Called after each spawn and despawn, see `set_spawn_observer`.
+    on_spawn: Option<fn(Entity, usize)>,
+    on_despawn: Option<fn(Entity, usize)>,
+    /// Id stamped into the entities this world spawns, picked on first spawn.
+    #[cfg(debug_assertions)]
+    id: u16,
 }
 
+/// Next world id to hand out; 0 is reserved for unstamped handles.
+#[cfg(debug_assertions)]
+static NEXT_WORLD_ID: AtomicU16 = AtomicU16::new(1);
+
 impl<WC: WorldContainer> World<WC> {
+    /// Creates an empty world, building its containers with
+    /// [`WorldContainer::new`].
+    ///
+    /// This cannot be a `const fn`: it calls a trait method. To initialize a
+    /// `static` world, build the containers in a `const` and pass them to
+    /// [`World::with_containers`] instead.
     pub fn new() -> Self {
+        Self::with_containers(WC::new())
+    }
+
+    /// Creates an empty world around already-constructed containers.
+    ///
+    /// Unlike [`World::new`] this is a `const fn`, so a world whose containers
+    /// can be built at compile time (e.g. with
+    /// [`VecComponentContainer::new_empty`](crate::VecComponentContainer::new_empty))
+    /// can initialize a `static`.
+    pub const fn with_containers(containers: WC) -> Self {
         Self {
             last_entity: 0,
-            containers: WC::new(),
+            containers,
+            #[cfg(feature = "validation")]
+            validation: ValidationLevel::Debug,
+            disabled: Vec::new(),
+            disabled_count: 0,
+            reserved: Vec::new(),
+            generations: Vec::new(),
+            free_indices: Vec::new(),
+            live: Vec::new(),
+            signatures: Vec::new(),
+            on_spawn: None,
+            on_despawn: None,
+            #[cfg(debug_assertions)]
+            id: 0,
+        }
+    }
+
+    /// Returns how much checking queries perform before iterating.
+    ///
+    /// Always [`ValidationLevel::Debug`] without the `validation` feature.
+    #[inline]
+    pub fn validation_level(&self) -> ValidationLevel {
+        #[cfg(feature = "validation")]
+        {
+            self.validation
+        }
+        #[cfg(not(feature = "validation"))]
+        {
+            ValidationLevel::Debug
+        }
+    }
+
+    /// Sets how much checking queries perform before iterating.
+    #[cfg(feature = "validation")]
+    pub fn set_validation_level(&mut self, level: ValidationLevel) {
+        self.validation = level;
+    }
+
+    /// Disables `entity`, hiding it from [`Query::for_each`] and
+    /// [`Query::for_each_sparse`] without removing its components.
+    ///
+    /// [`Query::for_each`]: crate::Query::for_each
+    /// [`Query::for_each_sparse`]: crate::Query::for_each_sparse
+    pub fn disable(&mut self, entity: Entity) {
+        let (word, bit) = (entity.index / 32, 1 << (entity.index % 32));
+        if self.disabled.len() <= word {
+            self.disabled.resize(word + 1, 0);
+        }
+
+        if self.disabled[word] & bit == 0 {
+            self.disabled[word] |= bit;
+            self.disabled_count += 1;
+        }
+    }
+
+    /// Re-enables an entity previously passed to [`World::disable`].
+    pub fn enable(&mut self, entity: Entity) {
+        self.clear_disabled(entity.index);
+    }
+
+    /// Returns whether `entity` is currently disabled.
+    pub fn is_disabled(&self, entity: Entity) -> bool {
+        self.is_index_disabled(entity.index)
+    }
+
+    pub(crate) fn has_disabled(&self) -> bool {
+        self.disabled_count != 0
+    }
+
+    pub(crate) fn is_index_disabled(&self, index: usize) -> bool {
+        is_disabled_in(&self.disabled, index)
+    }
+
+    fn clear_disabled(&mut self, index: usize) {
+        let bit = 1 << (index % 32);
+        if let Some(word) = self.disabled.get_mut(index / 32) {
+            if *word & bit != 0 {
+                *word &= !bit;
+                self.disabled_count -= 1;
+            }
         }
     }
 
+    pub(crate) fn clear_all_disabled(&mut self) {
+        self.disabled.clear();
+        self.disabled_count = 0;
+    }
+
+    /// Creates a new entity, reusing the index of a despawned one if possible.
+    ///
+    /// A reused index comes back with a bumped generation, so handles to the
+    /// despawned entity no longer reach its replacement.
     pub fn spawn(&mut self) -> Entity {
-        let entity = Entity::new(self.last_entity);
+        self.assign_id();
+
+        if let Some(index) = self.free_indices.pop() {
+            self.live[index] = true;
+            let entity = self.entity_at(index);
+            self.containers.add_entity(entity);
+            self.notify_spawn(entity);
+            return entity;
+        }
+
+        // Indices past `last_entity` keep their generation across `clear`.
+        let index = self.last_entity;
+        if index == self.generations.len() {
+            self.generations.push(0);
+        }
+
+        let entity = self.entity_at(index);
         self.last_entity += 1;
+        self.live.push(true);
         self.containers.add_entity(entity);
+        self.notify_spawn(entity);
+        entity
+    }
+
+    /// Hands out a new entity id without adding it to any container.
+    ///
+    /// The entity is alive right away but owns no container slots until it is
+    /// materialized, either explicitly with [`World::materialize`] or lazily
+    /// by the first [`World::add`] or [`World::try_add`] on it. Until then it
+    /// has no components, so `get` on it returns `None` like on any entity
+    /// lacking the component. Despawned indices are not reused, and the spawn
+    /// observer is only called on materialization.
+    pub fn reserve_entity(&mut self) -> Entity {
+        self.assign_id();
+
+        let index = self.last_entity;
+        if index == self.generations.len() {
+            self.generations.push(0);
+        }
+        self.last_entity += 1;
+        self.live.push(true);
+
+        let (word, bit) = (index / 32, 1 << (index % 32));
+        if self.reserved.len() <= word {
+            self.reserved.resize(word + 1, 0);
+        }
+        self.reserved[word] |= bit;
+        self.entity_at(index)
+    }
+
+    /// Adds a reserved entity to every container, see [`World::reserve_entity`].
+    ///
+    /// Does nothing if `entity` is stale or already materialized.
+    pub fn materialize(&mut self, entity: Entity) {
+        if self.is_current(entity) && self.take_reserved(entity.index) {
+            self.containers.add_entity(entity);
+            self.notify_spawn(entity);
+        }
+    }
+
+    /// Returns whether `entity` was reserved and not materialized yet.
+    pub fn is_reserved(&self, entity: Entity) -> bool {
+        self.is_current(entity)
+            && self
+                .reserved
+                .get(entity.index / 32)
+                .is_some_and(|word| word & (1 << (entity.index % 32)) != 0)
+    }
+
+    /// Clears the reserved bit of `index`, returning whether it was set.
+    fn take_reserved(&mut self, index: usize) -> bool {
+        let bit = 1 << (index % 32);
+        match self.reserved.get_mut(index / 32) {
+            Some(word) if *word & bit != 0 => {
+                *word &= !bit;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Returns the number of spawned entities not despawned since.
+    pub fn live_count(&self) -> usize {
+        self.last_entity - self.free_indices.len()
+    }
+
+    /// Sets the functions called with each entity right after it is spawned
+    /// or despawned, along with the resulting [`World::live_count`].
+    ///
+    /// Meant for hunting entity leaks, e.g. logging with `agb::println!` in
+    /// debug builds; leave them `None` in release. `spawn_batch` calls
+    /// `on_spawn` once per entity. `clear` and snapshot restores call
+    /// neither.
+    pub fn set_spawn_observer(
+        &mut self,
+        on_spawn: Option<fn(Entity, usize)>,
+        on_despawn: Option<fn(Entity, usize)>,
+    ) {
+        self.on_spawn = on_spawn;
+        self.on_despawn = on_despawn;
+    }
+
+    #[inline]
+    fn notify_spawn(&self, entity: Entity) {
+        if let Some(on_spawn) = self.on_spawn {
+            on_spawn(entity, self.live_count());
+        }
+    }
+
+    /// Reserves room for `additional` more entities in every container.
+    ///
+    /// Call it at startup with the game's entity ceiling so spawning never
+    /// reallocates mid-frame. The memory is taken immediately, see
+    /// [`VecComponentContainer::with_capacity_in`](crate::VecComponentContainer::with_capacity_in)
+    /// for the per-slot cost.
+    pub fn reserve(&mut self, additional: usize) {
+        self.containers.reserve(additional);
+        self.generations.reserve(additional);
+        let words = (self.last_entity + additional).div_ceil(32);
+        self.disabled
+            .reserve(words.saturating_sub(self.disabled.len()));
+    }
+
+    /// Frees the memory containers keep for despawned entities, see
+    /// [`ComponentContainer::shrink_to_fit`].
+    ///
+    /// Entities are not renumbered, so every handle stays valid. Each container
+    /// is cut at its own last component, so their lengths may differ
+    /// afterwards; zipped queries only walk the slots they share.
+    pub fn compact_all(&mut self) {
+        self.containers.compact();
+    }
+
+    /// Spawns `count` entities with consecutive indices and returns their handles.
+    ///
+    /// Every container reserves room for all of them up front, so this
+    /// allocates once per container instead of growing it entity by entity.
+    /// Despawned indices are not reused.
+    pub fn spawn_batch(&mut self, count: usize) -> Vec<Entity> {
+        self.assign_id();
+        self.containers.reserve(count);
+
+        let start = self.last_entity;
+        let end = start + count;
+        if self.generations.len() < end {
+            self.generations.resize(end, 0);
+        }
+        self.last_entity = end;
+        self.live.resize(end, true);
+
+        let entities: Vec<Entity> = (start..end).map(|index| self.entity_at(index)).collect();
+        for &entity in &entities {
+            self.containers.add_entity(entity);
+        }
+        if let Some(on_spawn) = self.on_spawn {
+            let live = self.live_count() - count;
+            for (spawned, &entity) in entities.iter().enumerate() {
+                on_spawn(entity, live + spawned + 1);
+            }
+        }
+        entities
+    }
+
+    /// Removes every entity and component, keeping the containers' capacity.
+    ///
+    /// Every generation is bumped, so all existing [`Entity`] handles become
+    /// stale even once their index is spawned again.
+    pub fn clear(&mut self) {
+        self.containers.clear();
+        self.last_entity = 0;
+        self.clear_all_disabled();
+        self.reserved.clear();
+        self.free_indices.clear();
+        self.live.clear();
+        self.signatures.clear();
+        for generation in &mut self.generations {
+            *generation = generation.wrapping_add(1);
+        }
+    }
+
+    /// Picks this world's id if it does not have one yet.
+    #[inline]
+    fn assign_id(&mut self) {
+        #[cfg(debug_assertions)]
+        while self.id == 0 {
+            self.id = NEXT_WORLD_ID.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Panics in debug builds if `entity` was spawned by another world.
+    ///
+    /// Handles built with [`Entity::new`] carry no world and always pass.
+    #[inline]
+    fn check_world(&self, _entity: Entity) {
+        #[cfg(debug_assertions)]
+        debug_assert!(
+            _entity.world_id == 0 || _entity.world_id == self.id,
+            "entity {} belongs to another world",
+            _entity.index
+        );
+    }
+
+    /// Returns the `C` component of `entity`, or an error if `entity` is not
+    /// alive: `Ok(None)` only ever means the entity lacks the component.
+    pub fn get_checked<C>(&self, entity: Entity) -> Result<Option<&C>, EntityError>
+    where
+        WC: GetComponentContainer<C>,
+    {
+        if entity.index >= self.last_entity {
+            return Err(EntityError::OutOfRange(entity.index));
+        }
+        if !self.is_current(entity) {
+            return Err(EntityError::Stale);
+        }
+
+        Ok(self.get::<C>().get(entity))
+    }
+
+    /// Returns whether `entity` refers to the current occupant of its index.
+    pub(crate) fn is_current(&self, entity: Entity) -> bool {
+        self.check_world(entity);
+        self.generations.get(entity.index) == Some(&entity.generation)
+    }
+
+    /// Returns the handle of the current occupant of `index`.
+    pub(crate) fn entity_at(&self, index: usize) -> Entity {
+        #[allow(unused_mut)]
+        let mut entity =
+            Entity::with_generation(index, self.generations.get(index).copied().unwrap_or(0));
+        #[cfg(debug_assertions)]
+        {
+            entity.world_id = self.id;
+        }
         entity
     }
 
+    /// Moves every entity of `other` into this world.
+    ///
+    /// `other`'s entity indices are shifted past every index this world has
+    /// ever used, so an entity at index `i` in `other` ends up at index
+    /// `i + offset` in every container. Indices freed by [`World::clear`] keep
+    /// their bumped generations and are left free for `spawn`, so handles
+    /// made stale by `clear` stay stale.
+    pub fn merge(&mut self, other: World<WC>) {
+        let offset = self.generations.len();
+        self.free_indices.extend(self.last_entity..offset);
+        self.live.resize(offset, false);
+
+        for index in 0..other.last_entity {
+            self.containers.add_entity(Entity::new(offset + index));
+        }
+
+        for index in 0..other.last_entity {
+            if other.is_index_disabled(index) {
+                self.disable(Entity::new(offset + index));
+            }
+        }
+
+        self.generations.extend_from_slice(&other.generations);
+        self.signatures.resize(offset, 0);
+        self.signatures.extend_from_slice(&other.signatures);
+        self.free_indices
+            .extend(other.free_indices.iter().map(|&index| index + offset));
+        self.live.extend_from_slice(&other.live);
+
+        self.containers.merge_from(other.containers, offset);
+        self.last_entity = offset + other.last_entity;
+    }
+
+    /// Returns whether `entity` was spawned in this world and not despawned since.
+    pub fn is_alive(&self, entity: Entity) -> bool {
+        self.is_current(entity) && self.live.get(entity.index) == Some(&true)
+    }
+
+    /// Returns the handle of every live entity, in ascending index order.
+    ///
+    /// Reserved entities are included. Useful for tooling that must walk every
+    /// entity before knowing which components it has, such as an inspector.
+    pub fn entities(&self) -> impl Iterator<Item = Entity> + '_ {
+        self.live
+            .iter()
+            .enumerate()
+            .filter(|&(_, &live)| live)
+            .map(|(index, _)| self.entity_at(index))
+    }
+
+    /// Returns whether `entity` has a `C` component.
+    pub fn has<C>(&self, entity: Entity) -> bool
+    where
+        WC: GetComponentContainer<C>,
+    {
+        self.check_world(entity);
+        self.get::<C>().contains(entity)
+    }
+
+    /// Returns the component bitmask of `entity`, with bit
+    /// [`COMPONENT_ID`](GetComponentContainer::COMPONENT_ID) set for each
+    /// component it has.
+    ///
+    /// Signatures are kept up to date by [`World::add`], [`World::remove`] and
+    /// [`World::despawn`]; components set directly on a container are missed.
+    pub fn signature(&self, entity: Entity) -> u64 {
+        if !self.is_current(entity) {
+            return 0;
+        }
+
+        self.signatures.get(entity.index).copied().unwrap_or(0)
+    }
+
+    /// Returns the signature bit of `C`.
+    ///
+    /// Panics if the world assigns no id to `C`, e.g. a hand-written
+    /// [`WorldContainer`] that does not set
+    /// [`COMPONENT_ID`](GetComponentContainer::COMPONENT_ID).
+    pub fn component_mask<C>(&self) -> u64
+    where
+        WC: GetComponentContainer<C>,
+    {
+        match <WC as GetComponentContainer<C>>::COMPONENT_ID {
+            Some(id) => 1 << id,
+            None => panic!("component has no signature id"),
+        }
+    }
+
+    /// Calls `f` with the index of every enabled entity whose signature
+    /// contains all the bits of `mask`.
+    ///
+    /// Only the signatures are read, so sparse combinations cost no failed
+    /// container lookups.
+    ///
+    /// ```ignore
+    /// let mask = world.component_mask::<Position>() | world.component_mask::<Boss>();
+    /// world.for_each_matching(mask, |entity_index| {});
+    /// ```
+    pub fn for_each_matching<F>(&self, mask: u64, mut f: F)
+    where
+        F: FnMut(usize),
+    {
+        for (index, &signature) in self.signatures.iter().enumerate() {
+            if signature & mask == mask && !self.is_index_disabled(index) {
+                f(index);
+            }
+        }
+    }
+
+    pub(crate) fn set_signature_bit(&mut self, index: usize, id: u8, value: bool) {
+        if self.signatures.len() <= index {
+            if !value {
+                return;
+            }
+            self.signatures.resize(index + 1, 0);
+        }
+
+        if value {
+            self.signatures[index] |= 1 << id;
+        } else {
+            self.signatures[index] &= !(1 << id);
+        }
+    }
+
+    /// Returns how many live `C` components exist.
+    pub fn count<C>(&self) -> usize
+    where
+        WC: GetComponentContainer<C>,
+    {
+        self.get::<C>().count()
+    }
+
+    /// Returns the structural version of the `C` container, see
+    /// [`ComponentContainer::version`].
+    pub fn version<C>(&self) -> u64
+    where
+        WC: GetComponentContainer<C>,
+    {
+        self.get::<C>().version()
+    }
+
+    /// Sets the `C` component of `entity`.
+    ///
+    /// Does nothing if `entity` has been despawned.
     pub fn add<C>(&mut self, entity: Entity, component: C)
     where
         WC: GetComponentContainer<C>,
     {
+        if !self.is_current(entity) {
+            return;
+        }
+        self.materialize(entity);
+
         self.containers.get_components_mut().set(entity, component);
+        if let Some(id) = <WC as GetComponentContainer<C>>::COMPONENT_ID {
+            self.set_signature_bit(entity.index, id, true);
+        }
+    }
+
+    /// Like [`World::add`], but reports why the component could not be stored
+    /// instead of ignoring stale entities or panicking.
+    pub fn try_add<C>(&mut self, entity: Entity, component: C) -> Result<(), SetError>
+    where
+        WC: GetComponentContainer<C>,
+    {
+        if !self.is_current(entity) {
+            return Err(SetError::StaleEntity);
+        }
+        self.materialize(entity);
+
+        self.containers
+            .get_components_mut()
+            .try_set(entity, component)?;
+        if let Some(id) = <WC as GetComponentContainer<C>>::COMPONENT_ID {
+            self.set_signature_bit(entity.index, id, true);
+        }
+        Ok(())
+    }
+
+    /// Removes the `C` component of `entity`, returning it if there was one.
+    pub fn remove<C>(&mut self, entity: Entity) -> Option<C>
+    where
+        WC: GetComponentContainer<C>,
+    {
+        if !self.is_current(entity) {
+            return None;
+        }
+
+        if let Some(id) = <WC as GetComponentContainer<C>>::COMPONENT_ID {
+            self.set_signature_bit(entity.index, id, false);
+        }
+        self.containers.get_components_mut().remove(entity)
+    }
+
+    /// Exchanges the `C` components of `a` and `b`. If only one of them has
+    /// one, it moves to the other; if neither does, nothing happens.
+    ///
+    /// Does nothing if either entity has been despawned.
+    pub fn swap<C>(&mut self, a: Entity, b: Entity)
+    where
+        WC: GetComponentContainer<C>,
+    {
+        if !self.is_current(a) || !self.is_current(b) {
+            return;
+        }
+
+        let container = self.containers.get_components_mut();
+        let has_a = container.contains(a);
+        let has_b = container.contains(b);
+        container.swap(a, b);
+        if let Some(id) = <WC as GetComponentContainer<C>>::COMPONENT_ID {
+            self.set_signature_bit(a.index, id, has_b);
+            self.set_signature_bit(b.index, id, has_a);
+        }
+    }
+
+    /// Moves the `C` component of `from` to `to`, replacing any `to` had.
+    /// Returns whether anything moved.
+    ///
+    /// Does nothing if either entity has been despawned.
+    pub fn move_component<C>(&mut self, from: Entity, to: Entity) -> bool
+    where
+        WC: GetComponentContainer<C>,
+    {
+        if !self.is_current(from) || !self.is_current(to) {
+            return false;
+        }
+
+        let moved = self
+            .containers
+            .get_components_mut()
+            .move_component(from, to);
+        if let (true, Some(id)) = (moved, <WC as GetComponentContainer<C>>::COMPONENT_ID) {
+            self.set_signature_bit(from.index, id, false);
+            self.set_signature_bit(to.index, id, true);
+        }
+        moved
+    }
+
+    /// Removes every `C` component for which `pred` returns true, handing each
+    /// one to `f` with its entity index. Disabled entities are drained too.
+    ///
+    /// ```ignore
+    /// world.drain::<Timer, _, _>(|timer| timer.expired(), |index, timer| {
+    ///     // React to the expired timer.
+    /// });
+    /// ```
+    pub fn drain<C, P, F>(&mut self, pred: P, mut f: F)
+    where
+        WC: GetComponentContainer<C>,
+        P: FnMut(&C) -> bool,
+        F: FnMut(usize, C),
+    {
+        let id = <WC as GetComponentContainer<C>>::COMPONENT_ID;
+        let signatures = &mut self.signatures;
+        self.containers
+            .get_components_mut()
+            .drain_where(pred, |index, component| {
+                if let (Some(id), Some(signature)) = (id, signatures.get_mut(index)) {
+                    *signature &= !(1 << id);
+                }
+                f(index, component);
+            });
+    }
+
+    /// Removes every `C` component, returning them with their entity indices,
+    /// e.g. to carry projectiles over a level transition.
+    ///
+    /// The container keeps its capacity. See
+    /// [`VecComponentContainer::take_all`](crate::VecComponentContainer::take_all)
+    /// to collect into the container's own allocator instead.
+    pub fn take_all<C>(&mut self) -> Vec<(usize, C)>
+    where
+        WC: GetComponentContainer<C>,
+    {
+        let mut taken = Vec::with_capacity(self.get::<C>().count());
+        self.drain(|_| true, |index, component| taken.push((index, component)));
+        taken
+    }
+
+    /// Removes every component of `entity` and frees its index for reuse.
+    ///
+    /// The index's generation is bumped, so `entity` and any copies of it become
+    /// stale: lookups through them return `None` and writes are ignored. Since
+    /// stale handles cannot write, queries never visit a slot belonging to a
+    /// previous occupant. Does nothing if `entity` is already stale.
+    pub fn despawn(&mut self, entity: Entity) {
+        if !self.is_current(entity) {
+            return;
+        }
+
+        let index = entity.index;
+        if !self.take_reserved(index) {
+            self.containers.remove_entity(entity);
+        }
+        self.clear_disabled(index);
+        if let Some(signature) = self.signatures.get_mut(index) {
+            *signature = 0;
+        }
+        self.generations[index] = self.generations[index].wrapping_add(1);
+        self.free_indices.push(index);
+        self.live[index] = false;
+
+        if let Some(on_despawn) = self.on_despawn {
+            on_despawn(entity, self.live_count());
+        }
+    }
+
+    /// Despawns every entity that currently has a `C` component.
+    ///
+    /// All of the components of those entities are removed, not only `C`.
+    pub fn despawn_all_with<C>(&mut self)
+    where
+        WC: GetComponentContainer<C>,
+    {
+        let indices: Vec<usize> = self.get::<C>().iter_indices().collect();
+
+        for index in indices {
+            self.despawn(self.entity_at(index));
+        }
+    }
+
+    /// Reports the kind of container `C` is stored in.
+    ///
+    /// Useful to understand why a query took the fallback path: only
+    /// [`StorageKind::Vec`] containers take the vec fast path.
+    pub fn component_storage_kind<C>(&self) -> StorageKind
+    where
+        WC: GetComponentContainer<C>,
+    {
+        self.get::<C>().storage_kind()
     }
 
     pub fn get<C>(&self) -> &<WC as GetComponentContainer<C>>::Container
@@ -41,6 +738,90 @@ impl<WC: WorldContainer> World<WC> {
         self.containers.get_components_mut()
     }
 
+    /// Raw pointers to the `A` and `B` containers, or `None` if they overlap,
+    /// e.g. when `A` and `B` are the same type.
+    ///
+    /// Both are projected from a single pointer to `self.containers`, with no
+    /// `&mut` borrow in between that would invalidate the first, so they stay
+    /// usable together until `self.containers` is touched again.
+    pub(crate) fn container_ptrs<A, B>(
+        &mut self,
+    ) -> Option<(ContainerPtr<WC, A>, ContainerPtr<WC, B>)>
+    where
+        WC: GetComponentContainer<A> + GetComponentContainer<B>,
+    {
+        let offset_a = self.container_offset::<A>();
+        let offset_b = self.container_offset::<B>();
+        let end_a = offset_a + size_of::<<WC as GetComponentContainer<A>>::Container>();
+        let end_b = offset_b + size_of::<<WC as GetComponentContainer<B>>::Container>();
+        // Equal offsets are rejected even for zero-sized containers, which
+        // the range check alone would let through.
+        if offset_a == offset_b || (end_a > offset_b && end_b > offset_a) {
+            return None;
+        }
+
+        let containers = ptr::addr_of_mut!(self.containers);
+        // SAFETY: `container_offset` checked that both containers lie inside
+        // `self.containers`.
+        unsafe {
+            Some((
+                containers.byte_add(offset_a).cast(),
+                containers.byte_add(offset_b).cast(),
+            ))
+        }
+    }
+
+    /// Byte offset of the `C` container inside `self.containers`.
+    ///
+    /// Panics if [`GetComponentContainer::get_components`] returns something
+    /// that is not stored inside the world container.
+    fn container_offset<C>(&self) -> usize
+    where
+        WC: GetComponentContainer<C>,
+    {
+        let base = ptr::addr_of!(self.containers) as usize;
+        let container = self.containers.get_components() as *const _ as *const u8 as usize;
+        let offset = container.wrapping_sub(base);
+        assert!(
+            offset <= size_of::<WC>()
+                && size_of::<<WC as GetComponentContainer<C>>::Container>()
+                    <= size_of::<WC>() - offset,
+            "get_components must return a container stored in the world container"
+        );
+        offset
+    }
+
+    /// Borrows the `A` and `B` containers mutably at the same time.
+    ///
+    /// Panics if both components resolve to the same or overlapping
+    /// containers, e.g. when `A` and `B` are the same type.
+    ///
+    /// ```ignore
+    /// let (positions, velocities) = world.get_two_mut::<Position, Velocity>();
+    /// if let (Some(position), Some(velocity)) =
+    ///     (positions.get_mut(entity), velocities.get_mut(entity))
+    /// {
+    ///     position.x += velocity.dx;
+    /// }
+    /// ```
+    pub fn get_two_mut<A, B>(
+        &mut self,
+    ) -> (
+        &mut <WC as GetComponentContainer<A>>::Container,
+        &mut <WC as GetComponentContainer<B>>::Container,
+    )
+    where
+        WC: GetComponentContainer<A> + GetComponentContainer<B>,
+    {
+        let (container_a, container_b) = self
+            .container_ptrs::<A, B>()
+            .expect("get_two_mut components A and B share a container");
+
+        // SAFETY: both pointers come from `self.containers`, which is borrowed
+        // mutably for the returned lifetime, and the containers do not overlap.
+        unsafe { (&mut *container_a, &mut *container_b) }
+    }
+
     pub fn for_each<Q, F>(&self, f: F)
     where
         Q: for<'a> crate::query::Query<'a, WC>,
@@ -49,6 +830,104 @@ impl<WC: WorldContainer> World<WC> {
         Q::for_each(self, f);
     }
 
+    /// Visits every live, enabled entity in ascending index order with its
+    /// `C` component, or `C::default()` if it has none, e.g. to dump a full
+    /// table of entities.
+    ///
+    /// Unlike a query this does not skip entities lacking `C`. Components are
+    /// cloned so both cases can be passed by value.
+    pub fn for_each_or_default<C, F>(&self, mut f: F)
+    where
+        C: Default + Clone,
+        WC: GetComponentContainer<C>,
+        F: FnMut(usize, C),
+    {
+        let container = self.get::<C>();
+        for index in 0..self.last_entity {
+            if self.is_index_disabled(index) || !self.live[index] {
+                continue;
+            }
+
+            f(
+                index,
+                container.get_index(index).cloned().unwrap_or_default(),
+            );
+        }
+    }
+
+    /// Visits every enabled `C` by value, see
+    /// [`Query::for_each_copied`](crate::Query::for_each_copied).
+    pub fn for_each_copied<C, F>(&self, f: F)
+    where
+        C: Copy,
+        WC: GetComponentContainer<C>,
+        F: FnMut(usize, C),
+    {
+        <&C as crate::query::Query<'_, WC>>::for_each_copied(self, f);
+    }
+
+    /// Like [`World::for_each`], but stops early, see
+    /// [`Query::try_for_each`](crate::Query::try_for_each).
+    pub fn try_for_each<Q, B, F>(&self, f: F) -> ControlFlow<B>
+    where
+        Q: for<'a> crate::query::Query<'a, WC>,
+        F: FnMut(usize, <Q as crate::query::Query<'_, WC>>::Item) -> ControlFlow<B>,
+    {
+        Q::try_for_each(self, f)
+    }
+
+    /// Like [`World::for_each`], but passes each match's [`Entity`] rather than
+    /// its index, see [`Query::for_each_entity`](crate::Query::for_each_entity).
+    pub fn for_each_entity<Q, F>(&self, f: F)
+    where
+        Q: for<'a> crate::query::Query<'a, WC>,
+        F: FnMut(Entity, <Q as crate::query::Query<'_, WC>>::Item),
+    {
+        Q::for_each_entity(self, f);
+    }
+
+    /// Like [`World::for_each`], but always visits entities in ascending index order.
+    ///
+    /// Hash-backed containers iterate in an unspecified order; this collects the
+    /// matches into a temporary `Vec` and sorts them first, trading an allocation
+    /// for deterministic results.
+    pub fn for_each_sorted<Q, F>(&self, mut f: F)
+    where
+        Q: for<'a> crate::query::Query<'a, WC>,
+        F: FnMut(usize, <Q as crate::query::Query<'_, WC>>::Item),
+    {
+        let mut matches = Vec::new();
+        Q::for_each(self, |index, item| matches.push((index, item)));
+        matches.sort_unstable_by_key(|&(index, _)| index);
+
+        for (index, item) in matches {
+            f(index, item);
+        }
+    }
+
+    /// Visits the `C` components written since the last
+    /// [`ComponentContainer::clear_change_ticks`], skipping disabled entities.
+    pub fn for_each_changed<C, F>(&self, mut f: F)
+    where
+        WC: GetComponentContainer<C>,
+        F: FnMut(usize, &C),
+    {
+        self.get::<C>().for_each_changed(|index, component| {
+            if !self.is_index_disabled(index) {
+                f(index, component);
+            }
+        });
+    }
+
+    /// Like [`World::for_each`], but also visits disabled entities.
+    pub fn for_each_include_disabled<Q, F>(&self, f: F)
+    where
+        Q: for<'a> crate::query::Query<'a, WC>,
+        F: FnMut(usize, <Q as crate::query::Query<'_, WC>>::Item),
+    {
+        Q::for_each_include_disabled(self, f);
+    }
+
     pub fn for_each_sparse<Q, F>(&self, f: F)
     where
         Q: for<'a> crate::query::Query<'a, WC>,
@@ -56,6 +935,15 @@ impl<WC: WorldContainer> World<WC> {
     {
         Q::for_each_sparse(self, f);
     }
+
+    /// See [`Query::for_each_sparse_ordered`](crate::Query::for_each_sparse_ordered).
+    pub fn for_each_sparse_ordered<Q, F>(&self, scratch: &mut Vec<usize>, f: F)
+    where
+        Q: for<'a> crate::query::Query<'a, WC>,
+        F: FnMut(usize, <Q as crate::query::Query<'_, WC>>::Item),
+    {
+        Q::for_each_sparse_ordered(self, scratch, f);
+    }
 }
 
 impl<WC: WorldContainer> Default for World<WC> {
@@ -64,152 +952,402 @@ impl<WC: WorldContainer> Default for World<WC> {
     }
 }
 
+/// Raw pointer to the container storing `C` in `WC`.
+type ContainerPtr<WC, C> = *mut <WC as GetComponentContainer<C>>::Container;
+
+/// Returns whether bit `index` of a disabled set like `World::disabled` is set.
+pub(crate) fn is_disabled_in(disabled: &[u32], index: usize) -> bool {
+    disabled
+        .get(index / 32)
+        .is_some_and(|word| word & (1 << (index % 32)) != 0)
+}
+
 pub trait WorldContainer {
     fn new() -> Self;
     fn add_entity(&mut self, entity: Entity);
+    fn remove_entity(&mut self, entity: Entity);
+    fn merge_from(&mut self, other: Self, offset: usize);
+
+    /// Clears every container, see [`World::clear`].
+    fn clear(&mut self);
+
+    /// Reserves room for `additional` more entities in every container, see
+    /// [`World::spawn_batch`].
+    fn reserve(&mut self, _additional: usize) {}
+
+    /// Frees unused memory in every container, see [`World::compact_all`].
+    fn compact(&mut self) {}
+
+    /// Updates every event channel, see [`World::update_events`].
+    fn update_events(&mut self) {}
+
+    /// Computes the signature of the entity at `index` from the containers,
+    /// used to rebuild signatures after a snapshot restore.
+    fn signature(&self, _index: usize) -> u64 {
+        0
+    }
+}
+
+/// Returns the position of `name` in `names`, for `world!`'s component ids.
+#[doc(hidden)]
+pub const fn __position(name: &str, names: &[&str]) -> u8 {
+    let mut i = 0;
+    while !str_eq(name, names[i]) {
+        i += 1;
+    }
+    i as u8
 }
 
-// #[macro_export]
-// macro_rules! world {
-//     // New syntax: (Component, Allocator) pairs
-//     ($name:ident { $(($component:ident, $allocator:path)),* $(,)? }) => {
-//         #[allow(non_snake_case)]
-//         struct $name {
-//             last_entity: usize,
-//             $(
-//                 $component: $crate::VecComponentContainer<$component, $allocator>,
-//             )*
-//         }
-//
-//         impl $crate::World for $name {
-//             fn new() -> Self {
-//                 Self {
-//                     last_entity: 0,
-//                     $(
-//                         $component: $crate::VecComponentContainer::new_in($allocator),
-//                     )*
-//                 }
-//             }
-//
-//             fn add_entity(&mut self) -> $crate::Entity {
-//                 let entity = $crate::Entity::new(self.last_entity);
-//                 self.last_entity += 1;
-//
-//                 $(
-//                     self.$component.add_entity(entity);
-//                 )*
-//
-//                 entity
-//             }
-//
-//             fn add_component<C>(&mut self, entity: $crate::Entity, component: C)
-//             where
-//                 Self: $crate::GetComponentContainer<C>,
-//             {
-//                 let container = self.get_components_mut::<C>();
-//                 container.set(entity, component);
-//             }
-//         }
-//
-//         impl $name {
-//             pub fn get_components<C>(&self) -> &<Self as $crate::GetComponentContainer<C>>::Container
-//             where
-//                 Self: $crate::GetComponentContainer<C>,
-//             {
-//                 $crate::GetComponentContainer::get_components(self)
-//             }
-//
-//             pub fn get_components_mut<C>(&mut self) -> &mut <Self as $crate::GetComponentContainer<C>>::Container
-//             where
-//                 Self: $crate::GetComponentContainer<C>,
-//             {
-//                 $crate::GetComponentContainer::get_components_mut(self)
-//             }
-//         }
-//
-//         $(
-//             impl $crate::GetComponentContainer<$component> for $name {
-//                 type Container = $crate::VecComponentContainer<$component, $allocator>;
-//
-//                 fn get_components(&self) -> &Self::Container {
-//                     &self.$component
-//                 }
-//
-//                 fn get_components_mut(&mut self) -> &mut Self::Container {
-//                     &mut self.$component
-//                 }
-//             }
-//         )*
-//     };
-//
-//     // Old syntax: just Component names (defaults to Global allocator)
-//     ($name:ident { $($component:ident),* $(,)? }) => {
-//         #[allow(non_snake_case)]
-//         struct $name {
-//             last_entity: usize,
-//             $(
-//                 $component: $crate::VecComponentContainer<$component>,
-//             )*
-//         }
-//
-//         impl $crate::World for $name {
-//             fn new() -> Self {
-//                 Self {
-//                     last_entity: 0,
-//                     $(
-//                         $component: $crate::VecComponentContainer::new(),
-//                     )*
-//                 }
-//             }
-//
-//             fn add_entity(&mut self) -> $crate::Entity {
-//                 let entity = $crate::Entity::new(self.last_entity);
-//                 self.last_entity += 1;
-//
-//                 $(
-//                     self.$component.add_entity(entity);
-//                 )*
-//
-//                 entity
-//             }
-//
-//             fn add_component<C>(&mut self, entity: $crate::Entity, component: C)
-//             where
-//                 Self: $crate::GetComponentContainer<C>,
-//             {
-//                 let container = self.get_components_mut::<C>();
-//                 container.set(entity, component);
-//             }
-//         }
-//
-//         impl $name {
-//             pub fn get_components<C>(&self) -> &<Self as $crate::GetComponentContainer<C>>::Container
-//             where
-//                 Self: $crate::GetComponentContainer<C>,
-//             {
-//                 $crate::GetComponentContainer::get_components(self)
-//             }
-//
-//             pub fn get_components_mut<C>(&mut self) -> &mut <Self as $crate::GetComponentContainer<C>>::Container
-//             where
-//                 Self: $crate::GetComponentContainer<C>,
-//             {
-//                 $crate::GetComponentContainer::get_components_mut(self)
-//             }
-//         }
-//
-//         $(
-//             impl $crate::GetComponentContainer<$component> for $name {
-//                 type Container = $crate::VecComponentContainer<$component>;
-//
-//                 fn get_components(&self) -> &Self::Container {
-//                     &self.$component
-//                 }
-//
-//                 fn get_components_mut(&mut self) -> &mut Self::Container {
-//                     &mut self.$component
-//                 }
-//             }
-//         )*
-//     };
-// }
+/// Declares a [`WorldContainer`] storing each listed component in a
+/// [`VecComponentContainer`](crate::VecComponentContainer).
+///
+/// Components are listed either as `Component`, stored with the `Global`
+/// allocator, or as `(Component, Allocator)`. Both forms can be mixed.
+///
+/// A `resources { ... }` section declares singletons, each stored in an
+/// `Option` field and reached through [`GetResource`](crate::GetResource).
+/// An `events { ... }` section declares [`EventChannel`](crate::EventChannel)s,
+/// listed like components.
+///
+/// A `snapshot { ... }` section implements [`WorldSnapshot`](crate::WorldSnapshot)
+/// for the listed components, which must implement
+/// [`Deserialize`](crate::Deserialize) and [`ComponentId`](crate::ComponentId).
+/// Two of them sharing an id fails to compile.
+///
+/// Each name may appear only once across all sections; a repeated one fails
+/// to compile with an error naming it, and nothing is generated:
+///
+/// ```compile_fail
+/// struct Position;
+/// struct Velocity;
+///
+/// // error: world! lists `Position` more than once
+/// gba_ecs_rs::world!(MyWorld { Position, Velocity, Position });
+/// ```
+///
+/// Components get signature ids in the order they are listed, up to 64 of
+/// them.
+///
+/// ```ignore
+/// world!(MyWorld {
+///     Position,
+///     (Velocity, ExternalAllocator),
+///     resources { CameraOffset, FrameCount },
+///     events { Collision, (InputEvent, ExternalAllocator) },
+///     snapshot { Position, Velocity },
+/// });
+///
+/// let mut world = World::<MyWorld>::new();
+/// world.insert_resource(FrameCount(0));
+/// ```
+#[macro_export]
+macro_rules! world {
+    (@munch $vis:vis $name:ident [$($done:tt)*] [$($resources:ident)*] [$($events:tt)*] [$($snapshot:ident)*] resources { $($resource:ident),* $(,)? } $(, $($rest:tt)*)?) => {
+        $crate::world!(@munch $vis $name [$($done)*] [$($resources)* $($resource)*] [$($events)*] [$($snapshot)*] $($($rest)*)?);
+    };
+
+    (@munch $vis:vis $name:ident [$($done:tt)*] [$($resources:ident)*] [$($events:tt)*] [$($snapshot:ident)*] snapshot { $($saved:ident),* $(,)? } $(, $($rest:tt)*)?) => {
+        $crate::world!(@munch $vis $name [$($done)*] [$($resources)*] [$($events)*] [$($snapshot)* $($saved)*] $($($rest)*)?);
+    };
+
+    (@munch $vis:vis $name:ident [$($done:tt)*] [$($resources:ident)*] [$($events:tt)*] [$($snapshot:ident)*] events { $($entries:tt)* } $(, $($rest:tt)*)?) => {
+        $crate::world!(@events $vis $name [$($done)*] [$($resources)*] [$($events)*] [$($snapshot)*] [$($($rest)*)?] $($entries)*);
+    };
+
+    (@munch $vis:vis $name:ident [$($done:tt)*] [$($resources:ident)*] [$($events:tt)*] [$($snapshot:ident)*] ($component:ident, $allocator:path) $(, $($rest:tt)*)?) => {
+        $crate::world!(@munch $vis $name [$($done)* ($component, $allocator)] [$($resources)*] [$($events)*] [$($snapshot)*] $($($rest)*)?);
+    };
+
+    (@munch $vis:vis $name:ident [$($done:tt)*] [$($resources:ident)*] [$($events:tt)*] [$($snapshot:ident)*] $component:ident $(, $($rest:tt)*)?) => {
+        $crate::world!(@munch $vis $name [$($done)* ($component)] [$($resources)*] [$($events)*] [$($snapshot)*] $($($rest)*)?);
+    };
+
+    (@events $vis:vis $name:ident [$($done:tt)*] [$($resources:ident)*] [$($events:tt)*] [$($snapshot:ident)*] [$($rest:tt)*] ($event:ident, $allocator:path) $(, $($entries:tt)*)?) => {
+        $crate::world!(@events $vis $name [$($done)*] [$($resources)*] [$($events)* ($event, $allocator)] [$($snapshot)*] [$($rest)*] $($($entries)*)?);
+    };
+
+    (@events $vis:vis $name:ident [$($done:tt)*] [$($resources:ident)*] [$($events:tt)*] [$($snapshot:ident)*] [$($rest:tt)*] $event:ident $(, $($entries:tt)*)?) => {
+        $crate::world!(@events $vis $name [$($done)*] [$($resources)*] [$($events)* ($event)] [$($snapshot)*] [$($rest)*] $($($entries)*)?);
+    };
+
+    (@events $vis:vis $name:ident [$($done:tt)*] [$($resources:ident)*] [$($events:tt)*] [$($snapshot:ident)*] [$($rest:tt)*]) => {
+        $crate::world!(@munch $vis $name [$($done)*] [$($resources)*] [$($events)*] [$($snapshot)*] $($rest)*);
+    };
+
+    (@munch $vis:vis $name:ident [$(($component:ident $(, $allocator:path)?))*] [$($resource:ident)*] [$(($event:ident $(, $event_allocator:path)?))*] [$($snapshot:ident)*]) => {
+        // Every entry becomes a field, so names must be unique across sections.
+        // They are checked before anything is generated, so a repeated name
+        // only produces the error below.
+        $crate::world!(
+            @dedup ($) [] [$($component)* $($resource)* $($event)*]
+            $vis $name [$(($component $(, $allocator)?))*] [$($resource)*] [$(($event $(, $event_allocator)?))*] [$($snapshot)*]
+        );
+    };
+
+    // Each step defines a macro whose patterns spell out the names already
+    // seen, so that the next names can be compared against them. `$d` is a
+    // `$` token, which the generated macro needs for its own metavariables.
+    // Names are checked four at a time to keep the recursion shallow.
+    (@dedup ($d:tt) [$($seen:ident)*] [] $($generate:tt)*) => {
+        $crate::world!(@generate $($generate)*);
+    };
+
+    (@dedup ($d:tt) [$($seen:ident)*] [$first:ident $second:ident $third:ident $fourth:ident $($names:ident)*] $($generate:tt)*) => {
+        macro_rules! __gba_ecs_world_dedup {
+            $(
+                ($seen $d _2:ident $d _3:ident $d _4:ident) => { $crate::world!(@duplicate $seen); };
+                ($d _1:ident $seen $d _3:ident $d _4:ident) => { $crate::world!(@duplicate $seen); };
+                ($d _1:ident $d _2:ident $seen $d _4:ident) => { $crate::world!(@duplicate $seen); };
+                ($d _1:ident $d _2:ident $d _3:ident $seen) => { $crate::world!(@duplicate $seen); };
+            )*
+            ($d _1:ident $first $d _3:ident $d _4:ident) => { $crate::world!(@duplicate $first); };
+            ($d _1:ident $d _2:ident $first $d _4:ident) => { $crate::world!(@duplicate $first); };
+            ($d _1:ident $d _2:ident $d _3:ident $first) => { $crate::world!(@duplicate $first); };
+            ($d _1:ident $d _2:ident $second $d _4:ident) => { $crate::world!(@duplicate $second); };
+            ($d _1:ident $d _2:ident $d _3:ident $second) => { $crate::world!(@duplicate $second); };
+            ($d _1:ident $d _2:ident $d _3:ident $third) => { $crate::world!(@duplicate $third); };
+            ($d _1:ident $d _2:ident $d _3:ident $d _4:ident) => {
+                $crate::world!(@dedup ($d) [$($seen)* $first $second $third $fourth] [$($names)*] $($generate)*);
+            };
+        }
+
+        __gba_ecs_world_dedup!($first $second $third $fourth);
+    };
+
+    (@dedup ($d:tt) [$($seen:ident)*] [$next:ident $($names:ident)*] $($generate:tt)*) => {
+        macro_rules! __gba_ecs_world_dedup {
+            $(
+                ($seen) => { $crate::world!(@duplicate $seen); };
+            )*
+            ($d _1:ident) => {
+                $crate::world!(@dedup ($d) [$($seen)* $next] [$($names)*] $($generate)*);
+            };
+        }
+
+        __gba_ecs_world_dedup!($next);
+    };
+
+    (@duplicate $name:ident) => {
+        ::core::compile_error!(concat!("world! lists `", stringify!($name), "` more than once"));
+    };
+
+    (@generate $vis:vis $name:ident [$(($component:ident $(, $allocator:path)?))*] [$($resource:ident)*] [$(($event:ident $(, $event_allocator:path)?))*] [$($snapshot:ident)*]) => {
+        const _: () = assert!(
+            $name::__COMPONENT_NAMES.len() <= 64,
+            "world! supports at most 64 components",
+        );
+
+        #[allow(non_snake_case)]
+        $vis struct $name {
+            $(
+                $component: $crate::VecComponentContainer<$component $(, $allocator)?>,
+            )*
+            $(
+                $resource: ::core::option::Option<$resource>,
+            )*
+            $(
+                $event: $crate::EventChannel<$event $(, $event_allocator)?>,
+            )*
+        }
+
+        impl $name {
+            #[doc(hidden)]
+            pub const __COMPONENT_NAMES: &'static [&'static str] = &[$(stringify!($component)),*];
+        }
+
+        impl $crate::WorldContainer for $name {
+            fn new() -> Self {
+                Self {
+                    $(
+                        $component: $crate::world!(@new $($allocator)?),
+                    )*
+                    $(
+                        $resource: ::core::option::Option::None,
+                    )*
+                    $(
+                        $event: $crate::world!(@new_events $($event_allocator)?),
+                    )*
+                }
+            }
+
+            fn add_entity(&mut self, entity: $crate::Entity) {
+                $(
+                    $crate::ComponentContainer::add_entity(&mut self.$component, entity);
+                )*
+            }
+
+            fn remove_entity(&mut self, entity: $crate::Entity) {
+                $(
+                    $crate::ComponentContainer::remove_entity(&mut self.$component, entity);
+                )*
+            }
+
+            fn merge_from(&mut self, other: Self, offset: usize) {
+                $(
+                    $crate::ComponentContainer::merge_from(
+                        &mut self.$component,
+                        other.$component,
+                        offset,
+                    );
+                )*
+            }
+
+            fn clear(&mut self) {
+                $(
+                    $crate::ComponentContainer::clear(&mut self.$component);
+                )*
+            }
+
+            fn reserve(&mut self, additional: usize) {
+                $(
+                    $crate::ComponentContainer::reserve(&mut self.$component, additional);
+                )*
+            }
+
+            fn compact(&mut self) {
+                $(
+                    $crate::ComponentContainer::shrink_to_fit(&mut self.$component);
+                )*
+            }
+
+            fn update_events(&mut self) {
+                $(
+                    self.$event.update();
+                )*
+            }
+
+            fn signature(&self, index: usize) -> u64 {
+                #[allow(unused_mut)]
+                let mut signature = 0;
+                $(
+                    if $crate::ComponentContainer::get_index(&self.$component, index).is_some() {
+                        signature |= 1 << <Self as $crate::GetComponentContainer<$component>>::COMPONENT_ID.unwrap();
+                    }
+                )*
+                signature
+            }
+        }
+
+        $(
+            impl $crate::GetComponentContainer<$component> for $name {
+                type Container = $crate::VecComponentContainer<$component $(, $allocator)?>;
+
+                const COMPONENT_ID: ::core::option::Option<u8> = ::core::option::Option::Some(
+                    $crate::__position(stringify!($component), $name::__COMPONENT_NAMES),
+                );
+
+                fn get_components(&self) -> &Self::Container {
+                    &self.$component
+                }
+
+                fn get_components_mut(&mut self) -> &mut Self::Container {
+                    &mut self.$component
+                }
+            }
+        )*
+
+        $(
+            impl $crate::GetResource<$resource> for $name {
+                fn get_resource(&self) -> &::core::option::Option<$resource> {
+                    &self.$resource
+                }
+
+                fn get_resource_mut(&mut self) -> &mut ::core::option::Option<$resource> {
+                    &mut self.$resource
+                }
+            }
+        )*
+
+        $(
+            impl $crate::GetEventChannel<$event> for $name {
+                type Allocator = $crate::world!(@allocator $($event_allocator)?);
+
+                fn get_events(&self) -> &$crate::EventChannel<$event, Self::Allocator> {
+                    &self.$event
+                }
+
+                fn get_events_mut(&mut self) -> &mut $crate::EventChannel<$event, Self::Allocator> {
+                    &mut self.$event
+                }
+            }
+        )*
+
+        $crate::world!(@snapshot $name [$($snapshot)*]);
+    };
+
+    (@snapshot $name:ident []) => {};
+
+    (@snapshot $name:ident [$($component:ident)+]) => {
+        const _: () = {
+            let ids: &[u16] = &[$(<$component as $crate::ComponentId>::ID),+];
+            $(
+                assert!(
+                    $crate::__id_occurrences(<$component as $crate::ComponentId>::ID, ids) == 1,
+                    concat!("snapshot id of `", stringify!($component), "` is used more than once"),
+                );
+            )+
+        };
+
+        impl $crate::WorldSnapshot for $name {
+            fn save_components(&self, out: &mut dyn $crate::SnapshotBuffer) {
+                $(
+                    $crate::save_container::<$component, _, _>(
+                        <$component as $crate::ComponentId>::ID,
+                        &self.$component,
+                        out,
+                    );
+                )+
+            }
+
+            fn component_size(id: u16) -> ::core::option::Option<usize> {
+                $(
+                    if id == <$component as $crate::ComponentId>::ID {
+                        return ::core::option::Option::Some(<$component as $crate::Serialize>::SIZE);
+                    }
+                )+
+                ::core::option::Option::None
+            }
+
+            fn restore_component(&mut self, id: u16, entity: $crate::Entity, bytes: &[u8]) {
+                $(
+                    if id == <$component as $crate::ComponentId>::ID {
+                        $crate::ComponentContainer::set(
+                            &mut self.$component,
+                            entity,
+                            <$component as $crate::Deserialize>::from_bytes(bytes),
+                        );
+                        return;
+                    }
+                )+
+            }
+        }
+    };
+
+    (@new) => {
+        $crate::VecComponentContainer::new()
+    };
+
+    (@new $allocator:path) => {
+        $crate::VecComponentContainer::new_in($allocator)
+    };
+
+    (@allocator) => {
+        $crate::__GlobalAllocator
+    };
+
+    (@allocator $allocator:path) => {
+        $allocator
+    };
+
+    (@new_events) => {
+        $crate::EventChannel::new()
+    };
+
+    (@new_events $allocator:path) => {
+        $crate::EventChannel::new_in($allocator)
+    };
+
+    ($vis:vis $name:ident { $($components:tt)* }) => {
+        $crate::world!(@munch $vis $name [] [] [] [] $($components)*);
+    };
+}
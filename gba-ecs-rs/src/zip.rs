@@ -1,7 +1,13 @@
 use alloc::alloc::Allocator;
 use core::marker::PhantomData;
 
-use crate::VecComponentContainer;
+use crate::instrumentation::record_lookup;
+use crate::vec_container::IterationGuard;
+use crate::{ComponentContainer, HashComponentContainer, VecComponentContainer};
+
+/// Slots per live component above which zipped iteration walks active indices
+/// instead of every slot.
+pub const SPARSE_RATIO: usize = 4;
 
 pub struct ZippedQuery2<'a, T1, T2> {
     container1: *const Option<T1>,
@@ -9,6 +15,7 @@ pub struct ZippedQuery2<'a, T1, T2> {
     len: usize,
     shortest_active_indices: *const usize,
     shortest_active_len: usize,
+    _guards: [IterationGuard<'a>; 2],
     _phantom: PhantomData<&'a ()>,
 }
 
@@ -19,7 +26,6 @@ impl<'a, T1: 'a, T2: 'a> ZippedQuery2<'a, T1, T2> {
     ) -> Self {
         let container1 = &container1_full.container;
         let container2 = &container2_full.container;
-        assert_eq!(container1.len(), container2.len());
 
         let (shortest_active_indices, shortest_active_len) =
             if container1_full.active_indices.len() <= container2_full.active_indices.len() {
@@ -34,16 +40,30 @@ impl<'a, T1: 'a, T2: 'a> ZippedQuery2<'a, T1, T2> {
                 )
             };
 
+        // Containers grow on their own when `set` reaches past their end, so
+        // only the slots both of them have are visited.
+        let len = container1.len().min(container2.len());
         Self {
             container1: container1.as_ptr(),
             container2: container2.as_ptr(),
-            len: container1.len(),
+            len,
             shortest_active_indices,
             shortest_active_len,
+            _guards: [
+                IterationGuard::new(&container1_full.iterating),
+                IterationGuard::new(&container2_full.iterating),
+            ],
             _phantom: PhantomData,
         }
     }
 
+    /// Returns whether fewer than one slot in [`SPARSE_RATIO`] can match, in
+    /// which case [`ZippedQuery2::for_each_sparse`] beats a linear scan.
+    #[inline]
+    pub fn is_sparse(&self) -> bool {
+        self.shortest_active_len.saturating_mul(SPARSE_RATIO) < self.len
+    }
+
     #[inline]
     pub fn for_each<F>(self, mut f: F)
     where
@@ -70,6 +90,9 @@ impl<'a, T1: 'a, T2: 'a> ZippedQuery2<'a, T1, T2> {
     {
         for i in 0..self.shortest_active_len {
             let index = unsafe { *self.shortest_active_indices.add(i) };
+            if index >= self.len {
+                continue;
+            }
             unsafe {
                 let val1 = &*self.container1.add(index);
                 let val2 = &*self.container2.add(index);
@@ -102,6 +125,27 @@ impl<'a, T1: 'a, T2: 'a> ZippedQuery2<'a, T1, T2> {
         }
     }
 
+    /// Only reachable through [`ZippedQueryMut2`], whose constructor borrows
+    /// both containers mutably, so nothing else can read them.
+    #[inline]
+    fn for_each_mut2<F>(self, mut f: F)
+    where
+        F: FnMut(usize, &'a mut T1, &'a mut T2),
+    {
+        for i in 0..self.len {
+            unsafe {
+                let val1 = &mut *(self.container1 as *mut Option<T1>).add(i);
+                let val2 = &mut *(self.container2 as *mut Option<T2>).add(i);
+
+                if let Some(ref1) = val1 {
+                    if let Some(ref2) = val2 {
+                        f(i, ref1, ref2);
+                    }
+                }
+            }
+        }
+    }
+
     #[inline]
     pub fn for_each_sparse_mut<F>(self, mut f: F)
     where
@@ -109,6 +153,9 @@ impl<'a, T1: 'a, T2: 'a> ZippedQuery2<'a, T1, T2> {
     {
         for i in 0..self.shortest_active_len {
             let index = unsafe { *self.shortest_active_indices.add(i) };
+            if index >= self.len {
+                continue;
+            }
             unsafe {
                 let val1 = &mut *(self.container1 as *mut Option<T1>).add(index);
                 let val2 = &*self.container2.add(index);
@@ -123,6 +170,24 @@ impl<'a, T1: 'a, T2: 'a> ZippedQuery2<'a, T1, T2> {
     }
 }
 
+/// Two vec containers zipped with both borrowed mutably, built by
+/// [`zip_mut`].
+pub struct ZippedQueryMut2<'a, T1, T2> {
+    zipped: ZippedQuery2<'a, T1, T2>,
+}
+
+impl<'a, T1: 'a, T2: 'a> ZippedQueryMut2<'a, T1, T2> {
+    /// Like [`ZippedQuery2::for_each_mut`], with the second component mutable
+    /// too, e.g. `&mut Position, &mut Velocity`.
+    #[inline]
+    pub fn for_each_mut2<F>(self, f: F)
+    where
+        F: FnMut(usize, &'a mut T1, &'a mut T2),
+    {
+        self.zipped.for_each_mut2(f);
+    }
+}
+
 pub struct ZippedQuery3<'a, T1, T2, T3> {
     container1: *const Option<T1>,
     container2: *const Option<T2>,
@@ -130,6 +195,7 @@ pub struct ZippedQuery3<'a, T1, T2, T3> {
     len: usize,
     shortest_active_indices: *const usize,
     shortest_active_len: usize,
+    _guards: [IterationGuard<'a>; 3],
     _phantom: PhantomData<&'a ()>,
 }
 
@@ -142,7 +208,6 @@ impl<'a, T1: 'a, T2: 'a, T3: 'a> ZippedQuery3<'a, T1, T2, T3> {
         let container1 = &container1_full.container;
         let container2 = &container2_full.container;
         let container3 = &container3_full.container;
-        assert_eq!(container1.len(), container2.len());
 
         let (shortest_active_indices, shortest_active_len) = if container1_full.active_indices.len()
             <= container2_full.active_indices.len()
@@ -164,7 +229,9 @@ impl<'a, T1: 'a, T2: 'a, T3: 'a> ZippedQuery3<'a, T1, T2, T3> {
             )
         };
 
-        let len = container1.len().min(container2.len());
+        // Containers of a hand-written world may not grow together, so only the
+        // slots every one of them has are visited.
+        let len = container1.len().min(container2.len()).min(container3.len());
         Self {
             container1: container1.as_ptr(),
             container2: container2.as_ptr(),
@@ -172,10 +239,22 @@ impl<'a, T1: 'a, T2: 'a, T3: 'a> ZippedQuery3<'a, T1, T2, T3> {
             len,
             shortest_active_indices,
             shortest_active_len,
+            _guards: [
+                IterationGuard::new(&container1_full.iterating),
+                IterationGuard::new(&container2_full.iterating),
+                IterationGuard::new(&container3_full.iterating),
+            ],
             _phantom: PhantomData,
         }
     }
 
+    /// Returns whether fewer than one slot in [`SPARSE_RATIO`] can match, in
+    /// which case [`ZippedQuery3::for_each_sparse`] beats a linear scan.
+    #[inline]
+    pub fn is_sparse(&self) -> bool {
+        self.shortest_active_len.saturating_mul(SPARSE_RATIO) < self.len
+    }
+
     #[inline]
     pub fn for_each<F>(self, mut f: F)
     where
@@ -205,6 +284,9 @@ impl<'a, T1: 'a, T2: 'a, T3: 'a> ZippedQuery3<'a, T1, T2, T3> {
     {
         for i in 0..self.shortest_active_len {
             let index = unsafe { *self.shortest_active_indices.add(i) };
+            if index >= self.len {
+                continue;
+            }
             unsafe {
                 let val1 = &*self.container1.add(index);
                 let val2 = &*self.container2.add(index);
@@ -243,6 +325,30 @@ impl<'a, T1: 'a, T2: 'a, T3: 'a> ZippedQuery3<'a, T1, T2, T3> {
         }
     }
 
+    /// Only reachable through [`ZippedQueryMut3`], whose constructor borrows
+    /// the first two containers mutably, so nothing else can read them.
+    #[inline]
+    fn for_each_mut2<F>(self, mut f: F)
+    where
+        F: FnMut(usize, &'a mut T1, &'a mut T2, &'a T3),
+    {
+        for i in 0..self.len {
+            unsafe {
+                let val1 = &mut *(self.container1 as *mut Option<T1>).add(i);
+                let val2 = &mut *(self.container2 as *mut Option<T2>).add(i);
+                let val3 = &*self.container3.add(i);
+
+                if let Some(ref1) = val1 {
+                    if let Some(ref2) = val2 {
+                        if let Some(ref3) = val3 {
+                            f(i, ref1, ref2, ref3);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
     #[inline]
     pub fn for_each_sparse_mut<F>(self, mut f: F)
     where
@@ -250,6 +356,9 @@ impl<'a, T1: 'a, T2: 'a, T3: 'a> ZippedQuery3<'a, T1, T2, T3> {
     {
         for i in 0..self.shortest_active_len {
             let index = unsafe { *self.shortest_active_indices.add(i) };
+            if index >= self.len {
+                continue;
+            }
             unsafe {
                 let val1 = &mut *(self.container1 as *mut Option<T1>).add(index);
                 let val2 = &*self.container2.add(index);
@@ -267,6 +376,242 @@ impl<'a, T1: 'a, T2: 'a, T3: 'a> ZippedQuery3<'a, T1, T2, T3> {
     }
 }
 
+/// Three vec containers zipped with the first two borrowed mutably, built by
+/// [`zip3_mut`].
+pub struct ZippedQueryMut3<'a, T1, T2, T3> {
+    zipped: ZippedQuery3<'a, T1, T2, T3>,
+}
+
+impl<'a, T1: 'a, T2: 'a, T3: 'a> ZippedQueryMut3<'a, T1, T2, T3> {
+    /// Like [`ZippedQuery3::for_each_mut`], with the second component mutable
+    /// too, e.g. `&mut Position, &mut Velocity, &Acceleration`.
+    #[inline]
+    pub fn for_each_mut2<F>(self, f: F)
+    where
+        F: FnMut(usize, &'a mut T1, &'a mut T2, &'a T3),
+    {
+        self.zipped.for_each_mut2(f);
+    }
+}
+
+pub struct ZippedQuery4<'a, T1, T2, T3, T4> {
+    container1: *const Option<T1>,
+    container2: *const Option<T2>,
+    container3: *const Option<T3>,
+    container4: *const Option<T4>,
+    len: usize,
+    shortest_active_indices: *const usize,
+    shortest_active_len: usize,
+    _guards: [IterationGuard<'a>; 4],
+    _phantom: PhantomData<&'a ()>,
+}
+
+impl<'a, T1: 'a, T2: 'a, T3: 'a, T4: 'a> ZippedQuery4<'a, T1, T2, T3, T4> {
+    fn new<
+        A1: Allocator + Clone,
+        A2: Allocator + Clone,
+        A3: Allocator + Clone,
+        A4: Allocator + Clone,
+    >(
+        container1_full: &'a VecComponentContainer<T1, A1>,
+        container2_full: &'a VecComponentContainer<T2, A2>,
+        container3_full: &'a VecComponentContainer<T3, A3>,
+        container4_full: &'a VecComponentContainer<T4, A4>,
+    ) -> Self {
+        let container1 = &container1_full.container;
+        let container2 = &container2_full.container;
+        let container3 = &container3_full.container;
+        let container4 = &container4_full.container;
+
+        let (shortest_active_indices, shortest_active_len) = [
+            (
+                container1_full.active_indices.as_ptr(),
+                container1_full.active_indices.len(),
+            ),
+            (
+                container2_full.active_indices.as_ptr(),
+                container2_full.active_indices.len(),
+            ),
+            (
+                container3_full.active_indices.as_ptr(),
+                container3_full.active_indices.len(),
+            ),
+            (
+                container4_full.active_indices.as_ptr(),
+                container4_full.active_indices.len(),
+            ),
+        ]
+        .into_iter()
+        .min_by_key(|&(_, len)| len)
+        .unwrap();
+
+        let len = container1
+            .len()
+            .min(container2.len())
+            .min(container3.len())
+            .min(container4.len());
+        Self {
+            container1: container1.as_ptr(),
+            container2: container2.as_ptr(),
+            container3: container3.as_ptr(),
+            container4: container4.as_ptr(),
+            len,
+            shortest_active_indices,
+            shortest_active_len,
+            _guards: [
+                IterationGuard::new(&container1_full.iterating),
+                IterationGuard::new(&container2_full.iterating),
+                IterationGuard::new(&container3_full.iterating),
+                IterationGuard::new(&container4_full.iterating),
+            ],
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Returns the four slots at `index`, if all of them hold a component.
+    ///
+    /// The first one is returned as a pointer so callers decide whether to
+    /// borrow it mutably.
+    ///
+    /// # Safety
+    ///
+    /// `index` must be below every container's length.
+    #[inline]
+    unsafe fn slots(&self, index: usize) -> Option<(*mut T1, &'a T2, &'a T3, &'a T4)> {
+        let val1 = &*self.container1.add(index);
+        let val2 = &*self.container2.add(index);
+        let val3 = &*self.container3.add(index);
+        let val4 = &*self.container4.add(index);
+
+        match (val1, val2, val3, val4) {
+            (Some(ref1), Some(ref2), Some(ref3), Some(ref4)) => {
+                Some((ref1 as *const T1 as *mut T1, ref2, ref3, ref4))
+            }
+            _ => None,
+        }
+    }
+
+    #[inline]
+    pub fn for_each<F>(self, mut f: F)
+    where
+        F: FnMut(usize, &'a T1, &'a T2, &'a T3, &'a T4),
+    {
+        for i in 0..self.len {
+            unsafe {
+                if let Some((ref1, ref2, ref3, ref4)) = self.slots(i) {
+                    f(i, &*ref1, ref2, ref3, ref4);
+                }
+            }
+        }
+    }
+
+    #[inline]
+    pub fn for_each_sparse<F>(self, mut f: F)
+    where
+        F: FnMut(usize, &'a T1, &'a T2, &'a T3, &'a T4),
+    {
+        for i in 0..self.shortest_active_len {
+            let index = unsafe { *self.shortest_active_indices.add(i) };
+            if index >= self.len {
+                continue;
+            }
+            unsafe {
+                if let Some((ref1, ref2, ref3, ref4)) = self.slots(index) {
+                    f(index, &*ref1, ref2, ref3, ref4);
+                }
+            }
+        }
+    }
+
+    #[inline]
+    pub fn for_each_mut<F>(self, mut f: F)
+    where
+        F: FnMut(usize, &'a mut T1, &'a T2, &'a T3, &'a T4),
+    {
+        for i in 0..self.len {
+            unsafe {
+                if let Some((ref1, ref2, ref3, ref4)) = self.slots(i) {
+                    f(i, &mut *ref1, ref2, ref3, ref4);
+                }
+            }
+        }
+    }
+
+    #[inline]
+    pub fn for_each_sparse_mut<F>(self, mut f: F)
+    where
+        F: FnMut(usize, &'a mut T1, &'a T2, &'a T3, &'a T4),
+    {
+        for i in 0..self.shortest_active_len {
+            let index = unsafe { *self.shortest_active_indices.add(i) };
+            if index >= self.len {
+                continue;
+            }
+            unsafe {
+                if let Some((ref1, ref2, ref3, ref4)) = self.slots(index) {
+                    f(index, &mut *ref1, ref2, ref3, ref4);
+                }
+            }
+        }
+    }
+}
+
+/// A [`VecComponentContainer`] zipped with a [`HashComponentContainer`],
+/// built by [`zip_vec_hash`].
+pub struct ZippedVecHash<'a, T1, T2, H> {
+    slots: &'a [Option<T1>],
+    active_indices: &'a [usize],
+    hash: &'a H,
+    _guard: IterationGuard<'a>,
+    _phantom: PhantomData<&'a T2>,
+}
+
+impl<'a, T1: 'a, T2: 'a, H: ComponentContainer<T2>> ZippedVecHash<'a, T1, T2, H> {
+    /// Takes any container as the lookup side; queries only pass hash ones.
+    pub(crate) fn new<A1: Allocator + Clone>(
+        vec: &'a VecComponentContainer<T1, A1>,
+        hash: &'a H,
+    ) -> Self {
+        Self {
+            slots: &vec.container,
+            active_indices: &vec.active_indices,
+            hash,
+            _guard: IterationGuard::new(&vec.iterating),
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Visits every index holding both components, driven by whichever side
+    /// holds fewer: the vec's active indices with one hash lookup each, or the
+    /// hash's keys with a direct slot read each and a hash lookup on matches.
+    #[inline]
+    pub fn for_each<F>(self, mut f: F)
+    where
+        F: FnMut(usize, &'a T1, &'a T2),
+    {
+        let hash = self.hash;
+        if hash.count() < self.active_indices.len() {
+            for index in hash.iter_indices() {
+                record_lookup();
+                if let Some(Some(component1)) = self.slots.get(index) {
+                    if let Some(component2) = hash.get_index(index) {
+                        f(index, component1, component2);
+                    }
+                }
+            }
+        } else {
+            for &index in self.active_indices {
+                record_lookup();
+                if let Some(component1) = &self.slots[index] {
+                    if let Some(component2) = hash.get_index(index) {
+                        f(index, component1, component2);
+                    }
+                }
+            }
+        }
+    }
+}
+
 pub fn zip<'a, T1, T2, A1: Allocator + Clone, A2: Allocator + Clone>(
     container1: &'a VecComponentContainer<T1, A1>,
     container2: &'a VecComponentContainer<T2, A2>,
@@ -274,6 +619,17 @@ pub fn zip<'a, T1, T2, A1: Allocator + Clone, A2: Allocator + Clone>(
     ZippedQuery2::new(container1, container2)
 }
 
+/// Like [`zip`], but borrows both containers mutably so both of their
+/// components can be written, see [`ZippedQueryMut2::for_each_mut2`].
+pub fn zip_mut<'a, T1, T2, A1: Allocator + Clone, A2: Allocator + Clone>(
+    container1: &'a mut VecComponentContainer<T1, A1>,
+    container2: &'a mut VecComponentContainer<T2, A2>,
+) -> ZippedQueryMut2<'a, T1, T2> {
+    ZippedQueryMut2 {
+        zipped: ZippedQuery2::new(container1, container2),
+    }
+}
+
 pub fn zip3<'a, T1, T2, T3, A1: Allocator + Clone, A2: Allocator + Clone, A3: Allocator + Clone>(
     container1: &'a VecComponentContainer<T1, A1>,
     container2: &'a VecComponentContainer<T2, A2>,
@@ -281,3 +637,51 @@ pub fn zip3<'a, T1, T2, T3, A1: Allocator + Clone, A2: Allocator + Clone, A3: Al
 ) -> ZippedQuery3<'a, T1, T2, T3> {
     ZippedQuery3::new(container1, container2, container3)
 }
+
+/// Like [`zip3`], but borrows the first two containers mutably so both of
+/// their components can be written, see [`ZippedQueryMut3::for_each_mut2`].
+pub fn zip3_mut<
+    'a,
+    T1,
+    T2,
+    T3,
+    A1: Allocator + Clone,
+    A2: Allocator + Clone,
+    A3: Allocator + Clone,
+>(
+    container1: &'a mut VecComponentContainer<T1, A1>,
+    container2: &'a mut VecComponentContainer<T2, A2>,
+    container3: &'a VecComponentContainer<T3, A3>,
+) -> ZippedQueryMut3<'a, T1, T2, T3> {
+    ZippedQueryMut3 {
+        zipped: ZippedQuery3::new(container1, container2, container3),
+    }
+}
+
+pub fn zip4<
+    'a,
+    T1,
+    T2,
+    T3,
+    T4,
+    A1: Allocator + Clone,
+    A2: Allocator + Clone,
+    A3: Allocator + Clone,
+    A4: Allocator + Clone,
+>(
+    container1: &'a VecComponentContainer<T1, A1>,
+    container2: &'a VecComponentContainer<T2, A2>,
+    container3: &'a VecComponentContainer<T3, A3>,
+    container4: &'a VecComponentContainer<T4, A4>,
+) -> ZippedQuery4<'a, T1, T2, T3, T4> {
+    ZippedQuery4::new(container1, container2, container3, container4)
+}
+
+/// Zips a vec container with a hash container, for worlds mixing both
+/// storages. See [`ZippedVecHash::for_each`].
+pub fn zip_vec_hash<'a, T1, T2, A1: Allocator + Clone, A2: Allocator + Clone>(
+    vec: &'a VecComponentContainer<T1, A1>,
+    hash: &'a HashComponentContainer<T2, A2>,
+) -> ZippedVecHash<'a, T1, T2, HashComponentContainer<T2, A2>> {
+    ZippedVecHash::new(vec, hash)
+}
@@ -1,4 +1,5 @@
 use agb::timer::Timers;
+use gba_ecs_rs::{query_counters, reset_query_counters};
 
 const MAX_BENCHMARKS: usize = 16;
 const MAX_STACK_DEPTH: usize = 8;
@@ -96,6 +97,40 @@ pub fn stop(tag: &'static str) {
     }
 }
 
+/// Times `query` like a `start`/`stop` pair under `tag` and prints the cycles
+/// spent per entity it visited, taken from the library's query counters.
+///
+/// `query` should run a single query so the entity count matches. Returns the
+/// elapsed timer ticks, or 0 before `init`.
+pub fn measure_query<F: FnOnce()>(tag: &'static str, query: F) -> u32 {
+    let timers = unsafe {
+        match TIMERS {
+            Some(timers_ptr) => &*timers_ptr,
+            None => {
+                query();
+                return 0;
+            }
+        }
+    };
+
+    reset_query_counters();
+    let start_time = (timers.timer3.value(), timers.timer2.value());
+    query();
+    let end_time = (timers.timer3.value(), timers.timer2.value());
+
+    let elapsed = calc_elapsed(start_time, end_time);
+    let visited = query_counters().visited as u32;
+    add_to_bench(tag, elapsed);
+    agb::println!(
+        "[BENCH] {}: {} cycles, {} entities, {} cycles/entity",
+        tag,
+        elapsed,
+        visited,
+        elapsed / visited.max(1)
+    );
+    elapsed
+}
+
 pub fn log() {
     unsafe {
         agb::println!("[BENCH] Results:");
@@ -38,6 +38,7 @@ fn main(mut gba: agb::Gba) -> ! {
 
         if i == ITERATIONS / 2 {
             world.add(entity, Unique1(i as i32));
+            world.add(entity, Unique2(i as i32));
         }
 
         if i.is_multiple_of(2) {
@@ -201,6 +202,24 @@ fn main(mut gba: agb::Gba) -> ! {
     agb::println!("sum={}", sum);
     sum = 0;
 
+    // Both containers are vecs, so this takes the fast path...
+    bench::measure_query("query mod1 + mod2", || {
+        world.for_each::<(&Modulo1, &Modulo2), _>(|_e, (m1, m2)| {
+            sum += m1.0 + m2.0;
+        });
+    });
+    agb::println!("sum={}", sum);
+    sum = 0;
+
+    // ...while the hash container takes the vec-hash path, one lookup per entity.
+    bench::measure_query("query mod1 + unique hash", || {
+        world.for_each::<(&Modulo1, &Unique2), _>(|_e, (m1, u)| {
+            sum += m1.0 + u.0;
+        });
+    });
+    agb::println!("sum={}", sum);
+    sum = 0;
+
     bench::start("unique hand");
     let ou = unique_hash_container.get(Entity::new(ITERATIONS / 2));
     let om1 = modulo1_container.get(Entity::new(ITERATIONS / 2));
@@ -212,6 +231,19 @@ fn main(mut gba: agb::Gba) -> ! {
     bench::stop("unique hand");
     agb::println!("sum={}", sum);
 
+    let mut spawn_world = World::<MyWorldContainer>::new();
+    bench::start("spawn x1000");
+    for _ in 0..1000 {
+        spawn_world.spawn();
+    }
+    bench::stop("spawn x1000");
+
+    let mut batch_world = World::<MyWorldContainer>::new();
+    bench::start("spawn_batch 1000");
+    let bullets = batch_world.spawn_batch(1000);
+    bench::stop("spawn_batch 1000");
+    agb::println!("spawned={}", bullets.len());
+
     bench::log();
     loop {
         agb::halt();
@@ -3,7 +3,11 @@ mod test {
     use agb::{ExternalAllocator, InternalAllocator};
     use alloc::vec;
     use alloc::vec::Vec;
-    use gba_ecs_rs::{ComponentContainer, Entity, VecComponentContainer, World};
+    use gba_ecs_rs::{
+        Children, ComponentContainer, ComponentId, Deserialize, Entity, HashComponentContainer,
+        PackedComponentContainer, Parent, Query, QueryMut, RestoreError, Serialize, SnapshotBuffer,
+        StorageKind, ValidationLevel, VecComponentContainer, World, WorldSnapshot, WorldView,
+    };
 
     #[derive(Clone, Copy, Debug, PartialEq)]
     struct TestPosition {
@@ -17,43 +21,14 @@ mod test {
         dy: i32,
     }
 
-    struct MacroTestWorld {
-        test_position: VecComponentContainer<TestPosition, ExternalAllocator>,
-        test_velocity: VecComponentContainer<TestVelocity, InternalAllocator>,
-    }
-
-    impl gba_ecs_rs::WorldContainer for MacroTestWorld {
-        fn new() -> Self {
-            Self {
-                test_position: VecComponentContainer::new_in(ExternalAllocator),
-                test_velocity: VecComponentContainer::new_in(InternalAllocator),
-            }
-        }
-        fn add_entity(&mut self, entity: Entity) {
-            self.test_position.add_entity(entity);
-            self.test_velocity.add_entity(entity);
-        }
-    }
-
-    impl gba_ecs_rs::GetComponentContainer<TestPosition> for MacroTestWorld {
-        type Container = VecComponentContainer<TestPosition, ExternalAllocator>;
-        fn get_components(&self) -> &Self::Container {
-            &self.test_position
-        }
-        fn get_components_mut(&mut self) -> &mut Self::Container {
-            &mut self.test_position
-        }
-    }
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    struct TestProjectile;
 
-    impl gba_ecs_rs::GetComponentContainer<TestVelocity> for MacroTestWorld {
-        type Container = VecComponentContainer<TestVelocity, InternalAllocator>;
-        fn get_components(&self) -> &Self::Container {
-            &self.test_velocity
-        }
-        fn get_components_mut(&mut self) -> &mut Self::Container {
-            &mut self.test_velocity
-        }
-    }
+    gba_ecs_rs::world!(MacroTestWorld {
+        (TestPosition, ExternalAllocator),
+        (TestVelocity, InternalAllocator),
+        TestProjectile,
+    });
 
     #[test_case]
     fn test_world_macro(_agb: &mut agb::Gba) {
@@ -307,4 +282,3472 @@ mod test {
             assert_eq!(unique_val, (expected_entity * 10) as i32);
         }
     }
+
+    #[test_case]
+    fn test_hash_container_dense_iteration(_agb: &mut agb::Gba) {
+        let mut container = HashComponentContainer::<TestPosition>::new();
+
+        for i in 0..8 {
+            if i != 5 {
+                container.set(Entity::new(i), TestPosition { x: i as i32, y: 0 });
+            }
+        }
+
+        let mut hashed = Vec::new();
+        container.for_each(|index, pos| {
+            hashed.push((index, pos.x));
+        });
+        hashed.sort();
+
+        let mut dense = Vec::new();
+        container.for_each_dense(8, |index, pos| {
+            dense.push((index, pos.x));
+        });
+
+        assert_eq!(dense.len(), 7);
+        assert_eq!(hashed, dense);
+    }
+
+    #[test_case]
+    fn test_query_for_each_alive(_agb: &mut agb::Gba) {
+        let mut world = World::<MacroTestWorld>::new();
+
+        for i in 0..4 {
+            let entity = world.spawn();
+            world.add(entity, TestPosition { x: i, y: 0 });
+            world.add(entity, TestVelocity { dx: 1, dy: 1 });
+        }
+
+        // Entity 2 matches the query but is marked dead
+        let alive = [0b1011u32];
+
+        let mut visited = Vec::new();
+        <(&TestPosition, &TestVelocity) as Query<MacroTestWorld>>::for_each_alive(
+            &world,
+            &alive,
+            |entity, (pos, _vel)| {
+                visited.push((entity, pos.x));
+            },
+        );
+
+        assert_eq!(visited, vec![(0, 0), (1, 1), (3, 3)]);
+    }
+
+    /// A vec container that reports broken bookkeeping, standing in for a corrupted one.
+    struct CorruptedContainer(VecComponentContainer<TestPosition>);
+
+    impl ComponentContainer<TestPosition> for CorruptedContainer {
+        fn add_entity(&mut self, entity: Entity) {
+            self.0.add_entity(entity);
+        }
+        fn remove_entity(&mut self, entity: Entity) {
+            self.0.remove_entity(entity);
+        }
+        fn remove(&mut self, entity: Entity) -> Option<TestPosition> {
+            self.0.remove(entity)
+        }
+        fn clear(&mut self) {
+            self.0.clear();
+        }
+        fn merge_from(&mut self, other: Self, offset: usize) {
+            self.0.merge_from(other.0, offset);
+        }
+        fn set(&mut self, entity: Entity, component: TestPosition) {
+            self.0.set(entity, component);
+        }
+        fn get(&self, entity: Entity) -> Option<&TestPosition> {
+            self.0.get(entity)
+        }
+        fn get_index(&self, entity: usize) -> Option<&TestPosition> {
+            self.0.get_index(entity)
+        }
+        fn get_mut(&mut self, entity: Entity) -> Option<&mut TestPosition> {
+            self.0.get_mut(entity)
+        }
+        fn get_index_mut(&mut self, entity: usize) -> Option<&mut TestPosition> {
+            self.0.get_index_mut(entity)
+        }
+        fn len(&self) -> usize {
+            self.0.len()
+        }
+        fn version(&self) -> u64 {
+            self.0.version()
+        }
+        fn iter_indices(&self) -> impl Iterator<Item = usize> + '_ {
+            self.0.iter_indices()
+        }
+        fn for_each<F>(&self, f: F)
+        where
+            F: FnMut(usize, &TestPosition),
+        {
+            self.0.for_each(f);
+        }
+        fn for_each_mut<F>(&mut self, f: F)
+        where
+            F: FnMut(usize, &mut TestPosition),
+        {
+            self.0.for_each_mut(f);
+        }
+        fn check_invariants(&self) -> bool {
+            false
+        }
+    }
+
+    struct CorruptedTestWorld {
+        test_position: CorruptedContainer,
+    }
+
+    impl gba_ecs_rs::WorldContainer for CorruptedTestWorld {
+        fn new() -> Self {
+            Self {
+                test_position: CorruptedContainer(VecComponentContainer::new()),
+            }
+        }
+        fn add_entity(&mut self, entity: Entity) {
+            self.test_position.add_entity(entity);
+        }
+        fn remove_entity(&mut self, entity: Entity) {
+            self.test_position.remove_entity(entity);
+        }
+        fn merge_from(&mut self, other: Self, offset: usize) {
+            self.test_position.merge_from(other.test_position, offset);
+        }
+
+        fn clear(&mut self) {
+            self.test_position.clear();
+        }
+    }
+
+    impl gba_ecs_rs::GetComponentContainer<TestPosition> for CorruptedTestWorld {
+        type Container = CorruptedContainer;
+        fn get_components(&self) -> &Self::Container {
+            &self.test_position
+        }
+        fn get_components_mut(&mut self) -> &mut Self::Container {
+            &mut self.test_position
+        }
+    }
+
+    #[test_case]
+    fn test_validation_levels(_agb: &mut agb::Gba) {
+        // Only `Paranoid` runs `check_invariants`, and panics on this container.
+        let mut world = World::<CorruptedTestWorld>::new();
+        let entity = world.spawn();
+        world.add(entity, TestPosition { x: 1, y: 1 });
+
+        let mut visited = 0;
+        world.for_each::<&TestPosition, _>(|_entity, _pos| visited += 1);
+        assert_eq!(visited, 1);
+
+        world.set_validation_level(ValidationLevel::Off);
+        assert_eq!(world.validation_level(), ValidationLevel::Off);
+        let mut visited = 0;
+        world.for_each::<&TestPosition, _>(|_entity, _pos| visited += 1);
+        assert_eq!(visited, 1);
+
+        // A healthy container passes the paranoid checks
+        let mut healthy = World::<MacroTestWorld>::new();
+        healthy.set_validation_level(ValidationLevel::Paranoid);
+        let entity = healthy.spawn();
+        healthy.add(entity, TestPosition { x: 1, y: 1 });
+        let mut visited = 0;
+        healthy.for_each::<&TestPosition, _>(|_entity, _pos| visited += 1);
+        assert_eq!(visited, 1);
+    }
+
+    #[test_case]
+    fn test_despawn_all_with(_agb: &mut agb::Gba) {
+        let mut world = World::<MacroTestWorld>::new();
+
+        for i in 0..6 {
+            let entity = world.spawn();
+            world.add(entity, TestPosition { x: i, y: 0 });
+            if i % 3 == 0 {
+                world.add(entity, TestProjectile);
+                world.add(entity, TestVelocity { dx: 1, dy: 0 });
+            }
+        }
+
+        world.despawn_all_with::<TestProjectile>();
+
+        assert_eq!(world.get::<TestProjectile>().iter_indices().count(), 0);
+        assert_eq!(world.get::<TestVelocity>().iter_indices().count(), 0);
+
+        let mut remaining = Vec::new();
+        world.for_each::<&TestPosition, _>(|entity, pos| {
+            remaining.push((entity, pos.x));
+        });
+        remaining.sort();
+        assert_eq!(remaining, vec![(1, 1), (2, 2), (4, 4), (5, 5)]);
+    }
+
+    #[test_case]
+    fn test_container_entry(_agb: &mut agb::Gba) {
+        let mut container = VecComponentContainer::<TestPosition>::new();
+        let entity0 = Entity::new(0);
+        let entity1 = Entity::new(1);
+        container.add_entity(entity0);
+        container.add_entity(entity1);
+
+        // or_insert on an empty slot inserts and registers the index
+        let pos = container
+            .entry(entity0)
+            .or_insert(TestPosition { x: 1, y: 2 })
+            .unwrap();
+        pos.x += 10;
+        assert_eq!(container.get(entity0), Some(&TestPosition { x: 11, y: 2 }));
+        assert_eq!(container.iter_indices().count(), 1);
+
+        // and_modify on a present slot modifies, or_insert leaves it alone
+        container
+            .entry(entity0)
+            .and_modify(|pos| pos.y += 1)
+            .or_insert(TestPosition { x: 0, y: 0 });
+        assert_eq!(container.get(entity0), Some(&TestPosition { x: 11, y: 3 }));
+
+        // Combined chain on an empty slot only inserts
+        container
+            .entry(entity1)
+            .and_modify(|pos| pos.x = -1)
+            .or_insert_with(|| TestPosition { x: 5, y: 5 });
+        assert_eq!(container.get(entity1), Some(&TestPosition { x: 5, y: 5 }));
+        assert_eq!(container.iter_indices().count(), 2);
+
+        let mut hash = HashComponentContainer::<TestPosition>::new();
+        *hash
+            .entry(entity1)
+            .or_insert(TestPosition { x: 0, y: 0 })
+            .unwrap() = TestPosition { x: 3, y: 4 };
+        hash.entry(entity1).and_modify(|pos| pos.x *= 2);
+        assert_eq!(hash.get(entity1), Some(&TestPosition { x: 6, y: 4 }));
+        assert_eq!(hash.len(), 1);
+    }
+
+    #[test_case]
+    fn test_iteration_guard_engaged_during_for_each(_agb: &mut agb::Gba) {
+        let mut container = VecComponentContainer::<TestPosition>::new();
+        let mut other = VecComponentContainer::<TestVelocity>::new();
+        container.add_entity(Entity::new(0));
+        other.add_entity(Entity::new(0));
+        container.set(Entity::new(0), TestPosition { x: 0, y: 0 });
+
+        assert!(!container.is_iterating());
+
+        // Any `set`/`add_entity` reaching the container from inside these closures
+        // trips the debug guard instead of writing through a dangling pointer.
+        let mut guarded = Vec::new();
+        container.for_each(|_index, _pos| guarded.push(container.is_iterating()));
+        container.for_each_fast(|_index, _pos| guarded.push(container.is_iterating()));
+        assert_eq!(guarded, vec![true, true]);
+
+        let zipped = gba_ecs_rs::zip(&container, &other);
+        assert!(container.is_iterating());
+        assert!(other.is_iterating());
+        zipped.for_each(|_index, _pos, _vel| {});
+
+        assert!(!container.is_iterating());
+        assert!(!other.is_iterating());
+        container.set(Entity::new(0), TestPosition { x: 1, y: 1 });
+    }
+
+    impl Serialize for TestPosition {
+        const SIZE: usize = 8;
+        fn to_bytes(&self, out: &mut [u8]) {
+            out[..4].copy_from_slice(&self.x.to_le_bytes());
+            out[4..].copy_from_slice(&self.y.to_le_bytes());
+        }
+    }
+
+    impl Deserialize for TestPosition {
+        fn from_bytes(bytes: &[u8]) -> Self {
+            TestPosition {
+                x: i32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]),
+                y: i32::from_le_bytes([bytes[4], bytes[5], bytes[6], bytes[7]]),
+            }
+        }
+    }
+
+    struct SnapshotTestWorld {
+        test_position: VecComponentContainer<TestPosition>,
+    }
+
+    impl gba_ecs_rs::WorldContainer for SnapshotTestWorld {
+        fn new() -> Self {
+            Self {
+                test_position: VecComponentContainer::new(),
+            }
+        }
+        fn add_entity(&mut self, entity: Entity) {
+            self.test_position.add_entity(entity);
+        }
+        fn remove_entity(&mut self, entity: Entity) {
+            self.test_position.remove_entity(entity);
+        }
+        fn merge_from(&mut self, other: Self, offset: usize) {
+            self.test_position.merge_from(other.test_position, offset);
+        }
+
+        fn clear(&mut self) {
+            self.test_position.clear();
+        }
+    }
+
+    impl gba_ecs_rs::GetComponentContainer<TestPosition> for SnapshotTestWorld {
+        type Container = VecComponentContainer<TestPosition>;
+        fn get_components(&self) -> &Self::Container {
+            &self.test_position
+        }
+        fn get_components_mut(&mut self) -> &mut Self::Container {
+            &mut self.test_position
+        }
+    }
+
+    impl WorldSnapshot for SnapshotTestWorld {
+        fn save_components(&self, out: &mut dyn SnapshotBuffer) {
+            gba_ecs_rs::save_container(0, &self.test_position, out);
+        }
+        fn component_size(id: u16) -> Option<usize> {
+            match id {
+                0 => Some(TestPosition::SIZE),
+                _ => None,
+            }
+        }
+        fn restore_component(&mut self, id: u16, entity: Entity, bytes: &[u8]) {
+            if id == 0 {
+                self.test_position
+                    .set(entity, TestPosition::from_bytes(bytes));
+            }
+        }
+    }
+
+    fn snapshot_test_world() -> World<SnapshotTestWorld> {
+        let mut world = World::<SnapshotTestWorld>::new();
+        for i in 0..3 {
+            let entity = world.spawn();
+            if i != 1 {
+                world.add(entity, TestPosition { x: i, y: -i });
+            }
+        }
+        world
+    }
+
+    #[test_case]
+    fn test_snapshot_round_trip(_agb: &mut agb::Gba) {
+        let world = snapshot_test_world();
+        let mut bytes = Vec::new();
+        world.save(&mut bytes);
+
+        let mut restored = World::<SnapshotTestWorld>::new();
+        assert_eq!(restored.restore(&bytes), Ok(()));
+
+        let positions = restored.get::<TestPosition>();
+        assert_eq!(
+            positions.get(Entity::new(0)),
+            Some(&TestPosition { x: 0, y: 0 })
+        );
+        assert_eq!(positions.get(Entity::new(1)), None);
+        assert_eq!(
+            positions.get(Entity::new(2)),
+            Some(&TestPosition { x: 2, y: -2 })
+        );
+
+        // Spawning continues after the restored entities
+        let spawned = restored.spawn();
+        restored.add(spawned, TestPosition { x: 3, y: 3 });
+        assert!(restored.get::<TestPosition>().get(Entity::new(3)).is_some());
+    }
+
+    #[test_case]
+    fn test_snapshot_restore_errors(_agb: &mut agb::Gba) {
+        let world = snapshot_test_world();
+        let mut bytes = Vec::new();
+        world.save(&mut bytes);
+
+        let mut target = World::<SnapshotTestWorld>::new();
+        let entity = target.spawn();
+        target.add(entity, TestPosition { x: 7, y: 7 });
+
+        let truncated = &bytes[..bytes.len() - 3];
+        assert_eq!(target.restore(truncated), Err(RestoreError::Truncated));
+        assert_eq!(target.restore(&bytes[..1]), Err(RestoreError::Truncated));
+
+        let mut version_bumped = bytes.clone();
+        version_bumped[0] = version_bumped[0].wrapping_add(1);
+        assert!(matches!(
+            target.restore(&version_bumped),
+            Err(RestoreError::VersionMismatch { .. })
+        ));
+
+        let mut unknown = bytes.clone();
+        unknown[6] = 9;
+        assert_eq!(
+            target.restore(&unknown),
+            Err(RestoreError::UnknownComponent(9))
+        );
+
+        // Failed restores leave the world untouched
+        assert_eq!(
+            target.get::<TestPosition>().get(entity),
+            Some(&TestPosition { x: 7, y: 7 })
+        );
+    }
+
+    #[test_case]
+    fn test_for_each_range_chunks(_agb: &mut agb::Gba) {
+        let mut container = VecComponentContainer::<TestPosition>::new();
+
+        for i in 0..10 {
+            container.add_entity(Entity::new(i));
+            if i % 3 != 1 {
+                container.set(Entity::new(i), TestPosition { x: i as i32, y: 0 });
+            }
+        }
+
+        let mut full = Vec::new();
+        container.for_each(|index, pos| full.push((index, pos.x)));
+        full.sort();
+
+        let mut chunked = Vec::new();
+        container.for_each_range(0..4, |index, pos| chunked.push((index, pos.x)));
+        container.for_each_range(4..100, |index, pos| chunked.push((index, pos.x)));
+        assert_eq!(chunked, full);
+
+        container.for_each_range_mut(2..5, |_index, pos| pos.y = 1);
+        let mut touched = Vec::new();
+        container.for_each(|index, pos| {
+            if pos.y == 1 {
+                touched.push(index);
+            }
+        });
+        touched.sort();
+        assert_eq!(touched, vec![2, 3]);
+    }
+
+    #[test_case]
+    fn test_world_merge(_agb: &mut agb::Gba) {
+        let mut main = World::<MacroTestWorld>::new();
+        for i in 0..3 {
+            let entity = main.spawn();
+            main.add(entity, TestPosition { x: i, y: 0 });
+        }
+
+        let mut chunk = World::<MacroTestWorld>::new();
+        for i in 0..2 {
+            let entity = chunk.spawn();
+            chunk.add(entity, TestPosition { x: 10 + i, y: 1 });
+            if i == 1 {
+                chunk.add(entity, TestVelocity { dx: 5, dy: 5 });
+            }
+        }
+
+        main.merge(chunk);
+
+        let mut positions = Vec::new();
+        main.for_each::<&TestPosition, _>(|entity, pos| positions.push((entity, pos.x, pos.y)));
+        positions.sort();
+        assert_eq!(
+            positions,
+            vec![(0, 0, 0), (1, 1, 0), (2, 2, 0), (3, 10, 1), (4, 11, 1)]
+        );
+
+        let velocities = main.get::<TestVelocity>();
+        assert!(velocities.get(Entity::new(1)).is_none());
+        assert_eq!(
+            velocities.get(Entity::new(4)),
+            Some(&TestVelocity { dx: 5, dy: 5 })
+        );
+
+        // Merged entities are counted, so spawning continues after them
+        let entity = main.spawn();
+        main.add(entity, TestVelocity { dx: 0, dy: 0 });
+        assert!(main.get::<TestVelocity>().get(Entity::new(5)).is_some());
+    }
+
+    #[test_case]
+    fn test_vec_container_raw_parts(_agb: &mut agb::Gba) {
+        let mut container = VecComponentContainer::<TestPosition>::new();
+        for i in 0..5 {
+            container.add_entity(Entity::new(i));
+        }
+        container.set(Entity::new(3), TestPosition { x: 3, y: 3 });
+        container.set(Entity::new(1), TestPosition { x: 1, y: 1 });
+
+        let (active, backing) = container.raw_parts();
+        assert_eq!(backing.len(), 5);
+
+        let live: Vec<(usize, i32)> = active
+            .iter()
+            .map(|&index| (index, backing[index].unwrap().x))
+            .collect();
+        assert_eq!(live, vec![(3, 3), (1, 1)]);
+        assert_eq!(
+            backing.iter().filter(|slot| slot.is_some()).count(),
+            active.len()
+        );
+    }
+
+    #[test_case]
+    fn test_query_write_oam(_agb: &mut agb::Gba) {
+        let mut world = World::<MacroTestWorld>::new();
+        for i in 0..6 {
+            let entity = world.spawn();
+            if i % 2 == 0 {
+                world.add(entity, TestPosition { x: i * 8, y: i * 4 });
+            }
+        }
+
+        let mut oam = [(0u16, 0u16); 4];
+        let written = <&TestPosition as Query<MacroTestWorld>>::write_oam(
+            &world,
+            &mut oam,
+            |_entity, pos| (pos.x as u16, pos.y as u16),
+        );
+
+        assert_eq!(written, 3);
+        assert_eq!(oam, [(0, 0), (16, 8), (32, 16), (0, 0)]);
+
+        let mut small_oam = [(0u16, 0u16); 2];
+        let written = <&TestPosition as Query<MacroTestWorld>>::write_oam(
+            &world,
+            &mut small_oam,
+            |_entity, pos| (pos.x as u16, pos.y as u16),
+        );
+        assert_eq!(written, 2);
+    }
+
+    #[test_case]
+    fn test_vec_container_truncate(_agb: &mut agb::Gba) {
+        let mut container = VecComponentContainer::<TestPosition>::new();
+        for i in 0..6 {
+            container.add_entity(Entity::new(i));
+            container.set(Entity::new(i), TestPosition { x: i as i32, y: 0 });
+        }
+
+        container.truncate(3);
+
+        assert_eq!(container.len(), 3);
+        assert!(container.get(Entity::new(2)).is_some());
+        assert!(container.get(Entity::new(3)).is_none());
+        assert!(container.get(Entity::new(5)).is_none());
+
+        let mut visited = Vec::new();
+        container.for_each(|index, _pos| visited.push(index));
+        assert_eq!(visited, vec![0, 1, 2]);
+    }
+
+    #[test_case]
+    fn test_disabled_entities(_agb: &mut agb::Gba) {
+        let mut world = World::<MacroTestWorld>::new();
+        let entities: Vec<Entity> = (0..3).map(|_| world.spawn()).collect();
+        for (i, &entity) in entities.iter().enumerate() {
+            world.add(entity, TestPosition { x: i as i32, y: 0 });
+        }
+
+        world.disable(entities[1]);
+        assert!(world.is_disabled(entities[1]));
+        assert!(!world.is_disabled(entities[0]));
+
+        let mut visited = Vec::new();
+        world.for_each::<&TestPosition, _>(|index, _pos| visited.push(index));
+        assert_eq!(visited, vec![0, 2]);
+
+        let mut visited = Vec::new();
+        world.for_each_sparse::<&TestPosition, _>(|index, _pos| visited.push(index));
+        assert_eq!(visited, vec![0, 2]);
+
+        let mut visited = Vec::new();
+        world.for_each_include_disabled::<&TestPosition, _>(|index, _pos| visited.push(index));
+        assert_eq!(visited, vec![0, 1, 2]);
+
+        world.enable(entities[1]);
+        assert!(!world.is_disabled(entities[1]));
+
+        let mut visited = Vec::new();
+        world.for_each::<&TestPosition, _>(|index, _pos| visited.push(index));
+        assert_eq!(visited, vec![0, 1, 2]);
+    }
+
+    #[test_case]
+    fn test_vec_container_generation_lookup(_agb: &mut agb::Gba) {
+        let mut container = VecComponentContainer::<TestPosition>::new();
+        let stale = Entity::with_generation(0, 0);
+        let current = Entity::with_generation(0, 1);
+
+        container.add_entity(current);
+        container.set(current, TestPosition { x: 4, y: 2 });
+
+        assert!(container.get(stale).is_none());
+        assert!(container.get_mut(stale).is_none());
+        assert_eq!(container.get(current).map(|pos| pos.x), Some(4));
+        assert!(container.get_mut(current).is_some());
+    }
+
+    #[test_case]
+    fn test_for_each_sorted_hash_container(_agb: &mut agb::Gba) {
+        use crate::world::MyWorldContainer;
+        use crate::Unique2;
+
+        let mut world = World::<MyWorldContainer>::new();
+        let entities: Vec<Entity> = (0..16).map(|_| world.spawn()).collect();
+        for &i in &[13, 2, 9, 0, 15, 6] {
+            world.add(entities[i], Unique2(i as i32));
+        }
+
+        let mut visited = Vec::new();
+        world.for_each_sorted::<&Unique2, _>(|index, unique| {
+            assert_eq!(unique.0, index as i32);
+            visited.push(index);
+        });
+
+        assert_eq!(visited, vec![0, 2, 6, 9, 13, 15]);
+    }
+
+    #[test_case]
+    fn test_container_clone_into(_agb: &mut agb::Gba) {
+        let mut source = VecComponentContainer::<TestPosition>::new();
+        for i in [3, 0, 5] {
+            source.add_entity(Entity::new(i));
+            source.set(Entity::new(i), TestPosition { x: i as i32, y: 1 });
+        }
+
+        let mut target = VecComponentContainer::<TestPosition>::new();
+        target.add_entity(Entity::new(1));
+        target.set(Entity::new(1), TestPosition { x: 99, y: 99 });
+
+        source.clone_into(&mut target);
+
+        assert!(target.get(Entity::new(1)).is_none());
+        let mut visited = Vec::new();
+        target.for_each(|index, pos| visited.push((index, pos.x)));
+        assert_eq!(visited, vec![(3, 3), (0, 0), (5, 5)]);
+
+        target.get_mut(Entity::new(0)).unwrap().x = 42;
+        assert_eq!(source.get(Entity::new(0)).map(|pos| pos.x), Some(0));
+
+        let mut hash_source = HashComponentContainer::<TestPosition>::new();
+        hash_source.set(Entity::new(7), TestPosition { x: 7, y: 0 });
+        let mut hash_target = HashComponentContainer::<TestPosition>::new();
+        hash_target.set(Entity::new(2), TestPosition { x: 2, y: 0 });
+
+        hash_source.clone_into(&mut hash_target);
+
+        assert!(hash_target.get(Entity::new(2)).is_none());
+        assert_eq!(hash_target.get(Entity::new(7)).map(|pos| pos.x), Some(7));
+    }
+
+    #[test_case]
+    fn test_query_mut_across_frames(_agb: &mut agb::Gba) {
+        let mut world = World::<MacroTestWorld>::new();
+        for i in 0..3 {
+            let entity = world.spawn();
+            world.add(entity, TestPosition { x: i, y: 0 });
+            if i != 1 {
+                world.add(entity, TestVelocity { dx: 2, dy: 1 });
+            }
+        }
+
+        for _frame in 0..2 {
+            world.for_each_mut::<(&mut TestPosition, &TestVelocity), _>(|_entity, (pos, vel)| {
+                pos.x += vel.dx;
+                pos.y += vel.dy;
+            });
+
+            let mut positions = Vec::new();
+            world.for_each::<&TestPosition, _>(|_entity, pos| positions.push((pos.x, pos.y)));
+            assert_eq!(positions.len(), 3);
+        }
+
+        let mut positions = Vec::new();
+        world.for_each::<&TestPosition, _>(|_entity, pos| positions.push((pos.x, pos.y)));
+        assert_eq!(positions, vec![(4, 2), (1, 0), (6, 2)]);
+
+        // Changing the world between runs is fine: each run looks it up again.
+        let entity = world.spawn();
+        world.add(entity, TestPosition { x: 10, y: 0 });
+        world.add(entity, TestVelocity { dx: 1, dy: 1 });
+        world.for_each_mut::<(&mut TestPosition, &TestVelocity), _>(|_entity, (pos, vel)| {
+            pos.x += vel.dx;
+            pos.y += vel.dy;
+        });
+
+        let mut positions = Vec::new();
+        world.for_each::<&TestPosition, _>(|_entity, pos| positions.push((pos.x, pos.y)));
+        assert_eq!(positions, vec![(6, 3), (1, 0), (8, 3), (11, 1)]);
+    }
+
+    #[test_case]
+    fn test_non_global_vec_container_takes_fallback(_agb: &mut agb::Gba) {
+        const { assert!(VecComponentContainer::<TestPosition>::VEC_FAST_PATH_SAFE) };
+        const { assert!(!VecComponentContainer::<TestPosition, ExternalAllocator>::VEC_FAST_PATH_SAFE) };
+
+        let mut world = World::<MacroTestWorld>::new();
+        assert!(!world.get::<TestPosition>().is_vec_container());
+        assert!(world.get::<TestProjectile>().is_vec_container());
+
+        for i in 0..4 {
+            let entity = world.spawn();
+            world.add(entity, TestPosition { x: i, y: i });
+            if i % 2 == 0 {
+                world.add(entity, TestVelocity { dx: i, dy: 0 });
+            }
+        }
+
+        let mut visited = Vec::new();
+        world.for_each::<(&TestPosition, &TestVelocity), _>(|entity, (pos, vel)| {
+            visited.push((entity, pos.x, vel.dx));
+        });
+        assert_eq!(visited, vec![(0, 0, 0), (2, 2, 2)]);
+    }
+
+    #[test_case]
+    fn test_query_histogram(_agb: &mut agb::Gba) {
+        let mut world = World::<MacroTestWorld>::new();
+        for x in [0, 5, 12, 17, 19, 31, 90] {
+            let entity = world.spawn();
+            world.add(entity, TestPosition { x, y: 0 });
+        }
+
+        let bins =
+            <&TestPosition as Query<MacroTestWorld>>::histogram::<4, _>(&world, |_entity, pos| {
+                (pos.x / 8) as usize
+            });
+
+        // 31 lands in bin 3 and 90 is clamped into it.
+        assert_eq!(bins, [2, 1, 2, 2]);
+    }
+
+    struct MixedTestWorld {
+        test_position: HashComponentContainer<TestPosition>,
+        test_velocity: VecComponentContainer<TestVelocity>,
+        test_projectile: VecComponentContainer<TestProjectile>,
+    }
+
+    impl gba_ecs_rs::WorldContainer for MixedTestWorld {
+        fn new() -> Self {
+            Self {
+                test_position: HashComponentContainer::new(),
+                test_velocity: VecComponentContainer::new(),
+                test_projectile: VecComponentContainer::new(),
+            }
+        }
+        fn add_entity(&mut self, entity: Entity) {
+            self.test_position.add_entity(entity);
+            self.test_velocity.add_entity(entity);
+            self.test_projectile.add_entity(entity);
+        }
+        fn remove_entity(&mut self, entity: Entity) {
+            self.test_position.remove_entity(entity);
+            self.test_velocity.remove_entity(entity);
+            self.test_projectile.remove_entity(entity);
+        }
+        fn merge_from(&mut self, other: Self, offset: usize) {
+            self.test_position.merge_from(other.test_position, offset);
+            self.test_velocity.merge_from(other.test_velocity, offset);
+            self.test_projectile
+                .merge_from(other.test_projectile, offset);
+        }
+
+        fn clear(&mut self) {
+            self.test_position.clear();
+            self.test_velocity.clear();
+            self.test_projectile.clear();
+        }
+    }
+
+    impl gba_ecs_rs::GetComponentContainer<TestPosition> for MixedTestWorld {
+        type Container = HashComponentContainer<TestPosition>;
+        fn get_components(&self) -> &Self::Container {
+            &self.test_position
+        }
+        fn get_components_mut(&mut self) -> &mut Self::Container {
+            &mut self.test_position
+        }
+    }
+
+    impl gba_ecs_rs::GetComponentContainer<TestVelocity> for MixedTestWorld {
+        type Container = VecComponentContainer<TestVelocity>;
+        fn get_components(&self) -> &Self::Container {
+            &self.test_velocity
+        }
+        fn get_components_mut(&mut self) -> &mut Self::Container {
+            &mut self.test_velocity
+        }
+    }
+
+    impl gba_ecs_rs::GetComponentContainer<TestProjectile> for MixedTestWorld {
+        type Container = VecComponentContainer<TestProjectile>;
+        fn get_components(&self) -> &Self::Container {
+            &self.test_projectile
+        }
+        fn get_components_mut(&mut self) -> &mut Self::Container {
+            &mut self.test_projectile
+        }
+    }
+
+    #[test_case]
+    fn test_two_vec_one_hash_query(_agb: &mut agb::Gba) {
+        let mut world = World::<MixedTestWorld>::new();
+        let entities: Vec<Entity> = (0..8).map(|_| world.spawn()).collect();
+        for (i, &entity) in entities.iter().enumerate() {
+            world.add(entity, TestPosition { x: i as i32, y: 0 });
+            world.add(
+                entity,
+                TestVelocity {
+                    dx: i as i32 * 2,
+                    dy: 0,
+                },
+            );
+        }
+        for i in [6, 2, 4] {
+            world.add(entities[i], TestProjectile);
+        }
+
+        let mut visited = Vec::new();
+        world.for_each::<(&TestPosition, &TestVelocity, &TestProjectile), _>(
+            |entity, (pos, vel, _projectile)| {
+                assert_eq!(pos.x, entity as i32);
+                assert_eq!(vel.dx, entity as i32 * 2);
+                visited.push(entity);
+            },
+        );
+
+        // The sparser vec container drives, so its insertion order is kept.
+        assert_eq!(visited, vec![6, 2, 4]);
+    }
+
+    #[test_case]
+    fn test_fallback_uses_index_lookups(_agb: &mut agb::Gba) {
+        let mut world = World::<MixedTestWorld>::new();
+        let stale: Vec<Entity> = (0..6).map(|_| world.spawn()).collect();
+        for entity in stale {
+            world.despawn(entity);
+        }
+
+        // Respawned entities reuse the indices with a later generation, and their
+        // components are still found by index.
+        for i in 0..6 {
+            let entity = world.spawn();
+            world.add(entity, TestPosition { x: i, y: 0 });
+            if i % 2 == 0 {
+                world.add(entity, TestVelocity { dx: 1, dy: 0 });
+            }
+        }
+
+        let mut expected = Vec::new();
+        let positions = world.get::<TestPosition>();
+        let velocities = world.get::<TestVelocity>();
+        for index in positions.iter_indices() {
+            if let (Some(pos), Some(vel)) =
+                (positions.get_index(index), velocities.get_index(index))
+            {
+                expected.push((index, pos.x, vel.dx));
+            }
+        }
+
+        let mut visited = Vec::new();
+        world.for_each::<(&TestPosition, &TestVelocity), _>(|entity, (pos, vel)| {
+            visited.push((entity, pos.x, vel.dx));
+        });
+
+        assert_eq!(visited.len(), 3);
+        assert_eq!(visited, expected);
+    }
+
+    struct ConstTestWorld {
+        test_position: VecComponentContainer<TestPosition>,
+    }
+
+    impl gba_ecs_rs::WorldContainer for ConstTestWorld {
+        fn new() -> Self {
+            Self {
+                test_position: VecComponentContainer::new(),
+            }
+        }
+        fn add_entity(&mut self, entity: Entity) {
+            self.test_position.add_entity(entity);
+        }
+        fn remove_entity(&mut self, entity: Entity) {
+            self.test_position.remove_entity(entity);
+        }
+        fn merge_from(&mut self, other: Self, offset: usize) {
+            self.test_position.merge_from(other.test_position, offset);
+        }
+
+        fn clear(&mut self) {
+            self.test_position.clear();
+        }
+    }
+
+    impl gba_ecs_rs::GetComponentContainer<TestPosition> for ConstTestWorld {
+        type Container = VecComponentContainer<TestPosition>;
+        fn get_components(&self) -> &Self::Container {
+            &self.test_position
+        }
+        fn get_components_mut(&mut self) -> &mut Self::Container {
+            &mut self.test_position
+        }
+    }
+
+    static mut CONST_WORLD: World<ConstTestWorld> = World::with_containers(ConstTestWorld {
+        test_position: VecComponentContainer::new_empty(),
+    });
+
+    #[test_case]
+    fn test_world_in_static(_agb: &mut agb::Gba) {
+        // SAFETY: only this test touches CONST_WORLD, and tests run on one thread.
+        let world = unsafe { &mut *core::ptr::addr_of_mut!(CONST_WORLD) };
+
+        let entity = world.spawn();
+        world.add(entity, TestPosition { x: 3, y: 4 });
+
+        assert_eq!(
+            world.get::<TestPosition>().get(entity),
+            Some(&TestPosition { x: 3, y: 4 })
+        );
+    }
+
+    #[test_case]
+    fn test_component_storage_kind(_agb: &mut agb::Gba) {
+        use crate::world::MyWorldContainer;
+        use crate::{Modulo1, Unique2};
+
+        let world = World::<MyWorldContainer>::new();
+
+        assert_eq!(
+            world.component_storage_kind::<Modulo1>(),
+            StorageKind::VecInAllocator
+        );
+        assert_eq!(world.component_storage_kind::<Unique2>(), StorageKind::Hash);
+
+        let world = World::<MacroTestWorld>::new();
+        assert_eq!(
+            world.component_storage_kind::<TestPosition>(),
+            StorageKind::VecInAllocator
+        );
+        assert_eq!(
+            world.component_storage_kind::<TestProjectile>(),
+            StorageKind::Vec
+        );
+    }
+
+    #[test_case]
+    fn test_query_instrumentation_counters(_agb: &mut agb::Gba) {
+        let mut world = World::<MixedTestWorld>::new();
+        for i in 0..4 {
+            let entity = world.spawn();
+            world.add(entity, TestPosition { x: i, y: 0 });
+            if i % 2 == 0 {
+                world.add(entity, TestVelocity { dx: 1, dy: 0 });
+            }
+        }
+
+        gba_ecs_rs::reset_query_counters();
+        world.for_each::<(&TestPosition, &TestVelocity), _>(|_entity, _item| {});
+
+        // Velocities are rarer, so they drive and each costs one position lookup.
+        let counters = gba_ecs_rs::query_counters();
+        assert_eq!(counters.visited, 2);
+        assert_eq!(counters.lookups, 2);
+
+        gba_ecs_rs::reset_query_counters();
+        assert_eq!(gba_ecs_rs::query_counters(), Default::default());
+    }
+
+    #[test_case]
+    fn test_world_despawn(_agb: &mut agb::Gba) {
+        let mut world = World::<MixedTestWorld>::new();
+        let entities: Vec<Entity> = (0..3).map(|_| world.spawn()).collect();
+        for (i, &entity) in entities.iter().enumerate() {
+            world.add(entity, TestPosition { x: i as i32, y: 0 });
+            world.add(entity, TestVelocity { dx: 1, dy: 0 });
+        }
+
+        world.despawn(entities[1]);
+
+        assert!(world.get::<TestPosition>().get(entities[1]).is_none());
+        assert!(world.get::<TestVelocity>().get(entities[1]).is_none());
+        assert!(world.get::<TestVelocity>().check_invariants());
+
+        let mut visited = Vec::new();
+        world.for_each_sparse::<&TestVelocity, _>(|entity, _vel| visited.push(entity));
+        assert_eq!(visited, vec![0, 2]);
+
+        let mut visited = Vec::new();
+        world.for_each_sorted::<(&TestPosition, &TestVelocity), _>(|entity, _item| {
+            visited.push(entity)
+        });
+        assert_eq!(visited, vec![0, 2]);
+    }
+
+    #[test_case]
+    fn test_remove_single_component(_agb: &mut agb::Gba) {
+        let mut world = World::<MixedTestWorld>::new();
+        let entities: Vec<Entity> = (0..3).map(|_| world.spawn()).collect();
+        for (i, &entity) in entities.iter().enumerate() {
+            world.add(entity, TestPosition { x: i as i32, y: 0 });
+            world.add(
+                entity,
+                TestVelocity {
+                    dx: i as i32,
+                    dy: 0,
+                },
+            );
+        }
+
+        assert_eq!(
+            world.remove::<TestVelocity>(entities[1]),
+            Some(TestVelocity { dx: 1, dy: 0 })
+        );
+        assert_eq!(world.remove::<TestVelocity>(entities[1]), None);
+        assert_eq!(
+            world.remove::<TestPosition>(entities[2]),
+            Some(TestPosition { x: 2, y: 0 })
+        );
+
+        // The other components of those entities are untouched.
+        assert!(world.get::<TestPosition>().get(entities[1]).is_some());
+        assert!(world.get::<TestVelocity>().get(entities[2]).is_some());
+
+        let mut visited = Vec::new();
+        world.for_each_sparse::<&TestVelocity, _>(|entity, _vel| visited.push(entity));
+        assert_eq!(visited, vec![0, 2]);
+    }
+
+    #[test_case]
+    fn test_query_mut_variants(_agb: &mut agb::Gba) {
+        let mut world = World::<MixedTestWorld>::new();
+        for i in 0..4 {
+            let entity = world.spawn();
+            world.add(entity, TestPosition { x: i, y: 0 });
+            world.add(entity, TestVelocity { dx: i * 10, dy: 0 });
+            if i >= 2 {
+                world.add(entity, TestProjectile);
+            }
+        }
+
+        <&mut TestPosition as QueryMut<MixedTestWorld>>::for_each_mut(
+            &mut world,
+            |_entity, pos| pos.y = 1,
+        );
+
+        // Both containers are Global vecs here, so this takes the zipped path.
+        <(&mut TestVelocity, &TestProjectile) as QueryMut<MixedTestWorld>>::for_each_mut(
+            &mut world,
+            |_entity, (vel, _projectile)| vel.dy = 5,
+        );
+
+        <(&mut TestPosition, &mut TestVelocity) as QueryMut<MixedTestWorld>>::for_each_mut(
+            &mut world,
+            |_entity, (pos, vel)| core::mem::swap(&mut pos.x, &mut vel.dx),
+        );
+
+        let mut visited = Vec::new();
+        world.for_each_sorted::<(&TestPosition, &TestVelocity), _>(|entity, (pos, vel)| {
+            visited.push((entity, pos.x, pos.y, vel.dx, vel.dy));
+        });
+        assert_eq!(
+            visited,
+            vec![
+                (0, 0, 1, 0, 0),
+                (1, 10, 1, 1, 0),
+                (2, 20, 1, 2, 5),
+                (3, 30, 1, 3, 5)
+            ]
+        );
+    }
+
+    #[test_case]
+    fn test_container_kind_flags(_agb: &mut agb::Gba) {
+        let vec = VecComponentContainer::<TestPosition>::new();
+        let hash = HashComponentContainer::<TestPosition>::new();
+
+        assert!(vec.is_vec_container());
+        assert!(!vec.is_hash_container());
+        assert!(hash.is_hash_container());
+        assert!(!hash.is_vec_container());
+    }
+
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    struct TestHealth(i32);
+
+    gba_ecs_rs::world!(QuadTestWorld {
+        TestPosition,
+        TestVelocity,
+        TestProjectile,
+        TestHealth,
+    });
+
+    #[test_case]
+    fn test_four_component_query(_agb: &mut agb::Gba) {
+        let mut world = World::<QuadTestWorld>::new();
+        for i in 0..8 {
+            let entity = world.spawn();
+            world.add(entity, TestPosition { x: i, y: 0 });
+            world.add(entity, TestVelocity { dx: i, dy: 0 });
+            if i % 2 == 0 {
+                world.add(entity, TestProjectile);
+            }
+            if i % 4 != 3 {
+                world.add(entity, TestHealth(i * 10));
+            }
+        }
+
+        let mut visited = Vec::new();
+        world.for_each::<(&TestPosition, &TestVelocity, &TestProjectile, &TestHealth), _>(
+            |entity, (pos, vel, _projectile, health)| {
+                assert_eq!(pos.x, vel.dx);
+                visited.push((entity, health.0));
+            },
+        );
+        assert_eq!(visited, vec![(0, 0), (2, 20), (4, 40), (6, 60)]);
+
+        let mut sparse = Vec::new();
+        world.for_each_sparse::<(&TestHealth, &TestProjectile, &TestPosition, &TestVelocity), _>(
+            |entity, _item| sparse.push(entity),
+        );
+        assert_eq!(sparse, vec![0, 2, 4, 6]);
+
+        let mut zipped = Vec::new();
+        gba_ecs_rs::zip4(
+            world.get::<TestPosition>(),
+            world.get::<TestVelocity>(),
+            world.get::<TestProjectile>(),
+            world.get::<TestHealth>(),
+        )
+        .for_each_sparse(|entity, _pos, _vel, _projectile, _health| zipped.push(entity));
+        assert_eq!(zipped, vec![0, 2, 4, 6]);
+    }
+
+    #[test_case]
+    fn test_entity_generations_after_despawn(_agb: &mut agb::Gba) {
+        let mut world = World::<MixedTestWorld>::new();
+        let first = world.spawn();
+        let kept = world.spawn();
+        world.add(first, TestPosition { x: 1, y: 0 });
+        world.add(first, TestVelocity { dx: 1, dy: 0 });
+        world.add(kept, TestPosition { x: 2, y: 0 });
+
+        world.despawn(first);
+        let second = world.spawn();
+        assert_eq!(second.generation(), first.generation() + 1);
+
+        world.add(second, TestPosition { x: 10, y: 0 });
+        world.add(first, TestVelocity { dx: 99, dy: 0 });
+
+        assert!(world.get::<TestPosition>().get(first).is_none());
+        assert!(world.get::<TestVelocity>().get(second).is_none());
+        assert_eq!(
+            world.get::<TestPosition>().get(second),
+            Some(&TestPosition { x: 10, y: 0 })
+        );
+        assert_eq!(world.remove::<TestPosition>(first), None);
+
+        // Despawning through the stale handle leaves the new occupant alone.
+        world.despawn(first);
+        assert!(world.get::<TestPosition>().get(second).is_some());
+
+        let mut visited = Vec::new();
+        world.for_each_sorted::<&TestPosition, _>(|entity, pos| visited.push((entity, pos.x)));
+        assert_eq!(visited, vec![(0, 10), (1, 2)]);
+    }
+
+    #[test_case]
+    fn test_world_has_and_is_alive(_agb: &mut agb::Gba) {
+        let mut world = World::<MixedTestWorld>::new();
+        let a = world.spawn();
+        let b = world.spawn();
+        world.add(a, TestPosition { x: 0, y: 0 });
+        world.add(b, TestVelocity { dx: 0, dy: 0 });
+
+        assert!(world.has::<TestPosition>(a));
+        assert!(!world.has::<TestVelocity>(a));
+        assert!(world.has::<TestVelocity>(b));
+        assert!(!world.has::<TestPosition>(b));
+
+        assert!(world.is_alive(a));
+        assert!(world.is_alive(b));
+        assert!(!world.is_alive(Entity::new(2)));
+
+        world.despawn(a);
+        assert!(!world.is_alive(a));
+        assert!(!world.has::<TestPosition>(a));
+
+        let c = world.spawn();
+        assert!(world.is_alive(c));
+        assert!(!world.is_alive(a));
+    }
+
+    #[test_case]
+    fn test_spawn_recycles_despawned_indices(_agb: &mut agb::Gba) {
+        let mut world = World::<MixedTestWorld>::new();
+        let mut bullets: Vec<Entity> = (0..4).map(|_| world.spawn()).collect();
+        let slots = world.get::<TestVelocity>().len();
+
+        for frame in 0..32 {
+            let expired = bullets.remove(0);
+            world.despawn(expired);
+
+            let bullet = world.spawn();
+            assert_eq!(bullet.generation() as usize, frame / 4 + 1);
+            world.add(
+                bullet,
+                TestVelocity {
+                    dx: frame as i32,
+                    dy: 0,
+                },
+            );
+            bullets.push(bullet);
+        }
+
+        assert_eq!(world.get::<TestVelocity>().len(), slots);
+        assert_eq!(world.get::<TestVelocity>().iter_indices().count(), 4);
+    }
+
+    #[test_case]
+    fn test_with_without_filters(_agb: &mut agb::Gba) {
+        use gba_ecs_rs::{With, Without};
+
+        let mut world = World::<MixedTestWorld>::new();
+        for i in 0..6 {
+            let entity = world.spawn();
+            world.add(entity, TestPosition { x: i, y: 0 });
+            world.add(entity, TestVelocity { dx: 1, dy: 0 });
+            if i % 3 == 0 {
+                world.add(entity, TestProjectile);
+            }
+        }
+
+        let mut moving = Vec::new();
+        world.for_each_sorted::<(&TestPosition, &TestVelocity, Without<TestProjectile>), _>(
+            |entity, (pos, _vel)| moving.push((entity, pos.x)),
+        );
+        assert_eq!(moving, vec![(1, 1), (2, 2), (4, 4), (5, 5)]);
+
+        let mut projectiles = Vec::new();
+        world.for_each_sorted::<(&TestPosition, With<TestProjectile>), _>(|entity, pos| {
+            projectiles.push((entity, pos.x))
+        });
+        assert_eq!(projectiles, vec![(0, 0), (3, 3)]);
+
+        let mut sparse = Vec::new();
+        world.for_each_sparse::<(&TestVelocity, With<TestProjectile>), _>(|entity, _vel| {
+            sparse.push(entity)
+        });
+        assert_eq!(sparse, vec![0, 3]);
+    }
+
+    #[test_case]
+    fn test_change_detection(_agb: &mut agb::Gba) {
+        let mut world = World::<MixedTestWorld>::new();
+        let entities: Vec<Entity> = (0..4).map(|_| world.spawn()).collect();
+        for &entity in &entities {
+            world.add(entity, TestVelocity { dx: 0, dy: 0 });
+        }
+
+        let mut changed = Vec::new();
+        world.for_each_changed::<TestVelocity, _>(|entity, _vel| changed.push(entity));
+        assert_eq!(changed, vec![0, 1, 2, 3]);
+
+        world.get_mut::<TestVelocity>().clear_change_ticks();
+        let mut changed = Vec::new();
+        world.for_each_changed::<TestVelocity, _>(|entity, _vel| changed.push(entity));
+        assert!(changed.is_empty());
+
+        world.add(entities[3], TestVelocity { dx: 1, dy: 0 });
+        if let Some(vel) = world.get_mut::<TestVelocity>().get_mut(entities[1]) {
+            vel.dx = 5;
+        }
+
+        let mut changed = Vec::new();
+        world.for_each_changed::<TestVelocity, _>(|entity, vel| changed.push((entity, vel.dx)));
+        assert_eq!(changed, vec![(1, 5), (3, 1)]);
+    }
+
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    struct CameraOffset {
+        x: i32,
+        y: i32,
+    }
+
+    #[derive(Debug, PartialEq)]
+    struct FrameCount(u32);
+
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    struct TestCollision(usize, usize);
+
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    struct TestInput(u8);
+
+    gba_ecs_rs::world!(ResourceTestWorld {
+        TestPosition,
+        resources { CameraOffset, FrameCount },
+        events { TestCollision, (TestInput, ExternalAllocator) },
+    });
+
+    #[test_case]
+    fn test_resources(_agb: &mut agb::Gba) {
+        let mut world = World::<ResourceTestWorld>::new();
+        assert!(world.resource::<CameraOffset>().is_none());
+
+        assert_eq!(world.insert_resource(CameraOffset { x: 1, y: 2 }), None);
+        world.insert_resource(FrameCount(0));
+
+        if let Some(frames) = world.resource_mut::<FrameCount>() {
+            frames.0 += 1;
+        }
+        assert_eq!(world.resource::<FrameCount>(), Some(&FrameCount(1)));
+
+        let previous = world.insert_resource(CameraOffset { x: 3, y: 4 });
+        assert_eq!(previous, Some(CameraOffset { x: 1, y: 2 }));
+        assert_eq!(
+            world.resource::<CameraOffset>(),
+            Some(&CameraOffset { x: 3, y: 4 })
+        );
+
+        let entity = world.spawn();
+        world.add(entity, TestPosition { x: 5, y: 6 });
+        assert!(world.has::<TestPosition>(entity));
+
+        assert_eq!(world.remove_resource::<FrameCount>(), Some(FrameCount(1)));
+        assert!(world.resource::<FrameCount>().is_none());
+    }
+
+    #[test_case]
+    fn test_event_channels(_agb: &mut agb::Gba) {
+        let mut world = World::<ResourceTestWorld>::new();
+
+        world.send_event(TestCollision(0, 1));
+        world.send_event(TestInput(3));
+        assert_eq!(world.events::<TestCollision>().read().count(), 0);
+
+        world.update_events();
+        world.send_event(TestCollision(2, 3));
+        let collisions: Vec<TestCollision> =
+            world.events::<TestCollision>().read().copied().collect();
+        assert_eq!(collisions, vec![TestCollision(0, 1)]);
+
+        let inputs: Vec<TestInput> = world.events_mut::<TestInput>().drain().collect();
+        assert_eq!(inputs, vec![TestInput(3)]);
+        assert_eq!(world.events::<TestInput>().read().count(), 0);
+
+        world.update_events();
+        let collisions: Vec<TestCollision> =
+            world.events::<TestCollision>().read().copied().collect();
+        assert_eq!(collisions, vec![TestCollision(2, 3)]);
+
+        world.events_mut::<TestCollision>().clear();
+        world.update_events();
+        assert_eq!(world.events::<TestCollision>().read().count(), 0);
+    }
+
+    #[test_case]
+    fn test_container_iterators(_agb: &mut agb::Gba) {
+        let mut world = World::<MixedTestWorld>::new();
+        for i in 0..6 {
+            let entity = world.spawn();
+            if i % 2 == 0 {
+                world.add(entity, TestVelocity { dx: i, dy: 0 });
+            }
+            if i % 3 == 0 {
+                world.add(entity, TestPosition { x: i, y: 0 });
+            }
+        }
+        world.remove::<TestVelocity>(Entity::new(0));
+        world.add(Entity::new(0), TestVelocity { dx: 10, dy: 0 });
+
+        let velocities = world.get::<TestVelocity>();
+        let dense: Vec<(usize, i32)> = velocities.iter().map(|(i, v)| (i, v.dx)).collect();
+        assert_eq!(dense, vec![(0, 10), (2, 2), (4, 4)]);
+
+        let sparse: Vec<usize> = velocities.sparse_iter().map(|(i, _)| i).collect();
+        assert_eq!(sparse, vec![2, 4, 0]);
+
+        for (_, velocity) in world.get_mut::<TestVelocity>().iter_mut() {
+            velocity.dy = 1;
+        }
+        assert!(world.get::<TestVelocity>().iter().all(|(_, v)| v.dy == 1));
+
+        for (index, position) in world.get_mut::<TestPosition>().iter_mut() {
+            position.y = index as i32;
+        }
+        let mut positions: Vec<(usize, i32)> = world
+            .get::<TestPosition>()
+            .iter()
+            .map(|(i, p)| (i, p.y))
+            .collect();
+        positions.sort_unstable();
+        assert_eq!(positions, vec![(0, 0), (3, 3)]);
+    }
+
+    #[test_case]
+    fn test_world_clear(_agb: &mut agb::Gba) {
+        let mut world = World::<MixedTestWorld>::new();
+        let old: Vec<Entity> = (0..8).map(|_| world.spawn()).collect();
+        for &entity in &old {
+            world.add(entity, TestVelocity { dx: 1, dy: 1 });
+            world.add(entity, TestPosition { x: 1, y: 1 });
+        }
+        world.disable(old[2]);
+        world.despawn(old[5]);
+
+        world.clear();
+
+        assert!(world.get::<TestVelocity>().is_empty());
+        assert!(world.get::<TestPosition>().is_empty());
+        assert!(old.iter().all(|&entity| !world.is_alive(entity)));
+
+        let fresh = world.spawn();
+        assert_eq!(fresh.generation(), 1);
+        assert!(world.is_alive(fresh));
+        assert!(!world.is_alive(old[0]));
+        assert!(!world.is_disabled(fresh));
+
+        world.add(old[0], TestVelocity { dx: 9, dy: 9 });
+        assert!(!world.has::<TestVelocity>(fresh));
+        world.add(fresh, TestVelocity { dx: 2, dy: 2 });
+
+        let mut count = 0;
+        world.for_each::<&TestVelocity, _>(|_, _| count += 1);
+        assert_eq!(count, 1);
+    }
+
+    #[test_case]
+    fn test_sparse_query_drives_smallest_container(_agb: &mut agb::Gba) {
+        let mut world = World::<MixedTestWorld>::new();
+        for i in 0..32 {
+            let entity = world.spawn();
+            world.add(entity, TestVelocity { dx: i, dy: 0 });
+            if i == 17 {
+                world.add(entity, TestPosition { x: i, y: 0 });
+            }
+        }
+
+        gba_ecs_rs::reset_query_counters();
+        let mut matches = Vec::new();
+        world.for_each_sparse::<(&TestVelocity, &TestPosition), _>(|entity, (vel, pos)| {
+            matches.push((entity, vel.dx, pos.x));
+        });
+
+        assert_eq!(matches, vec![(17, 17, 17)]);
+        assert_eq!(gba_ecs_rs::query_counters().lookups, 1);
+
+        world.add(Entity::new(3), TestProjectile);
+        gba_ecs_rs::reset_query_counters();
+        let mut count = 0;
+        world.for_each_sparse::<(&TestVelocity, &TestProjectile, &TestPosition), _>(
+            |_entity, _item| count += 1,
+        );
+        // The lone projectile drives: one velocity hit, one position miss.
+        assert_eq!(count, 0);
+        assert_eq!(gba_ecs_rs::query_counters().lookups, 2);
+    }
+
+    #[test_case]
+    fn test_spawn_batch(_agb: &mut agb::Gba) {
+        let mut world = World::<MacroTestWorld>::new();
+        let first = world.spawn();
+
+        let batch = world.spawn_batch(100);
+        assert_eq!(batch.len(), 100);
+        assert!(batch.iter().all(|&entity| world.is_alive(entity)));
+
+        for (i, &entity) in batch.iter().enumerate() {
+            world.add(entity, TestPosition { x: i as i32, y: 0 });
+        }
+        world.add(first, TestVelocity { dx: 1, dy: 1 });
+
+        let mut count = 0;
+        world.for_each::<&TestPosition, _>(|_, _| count += 1);
+        assert_eq!(count, 100);
+
+        let next = world.spawn();
+        world.add(next, TestPosition { x: -1, y: 0 });
+        assert_eq!(world.get::<TestPosition>().len(), 102);
+        assert!(world.spawn_batch(0).is_empty());
+    }
+
+    #[test_case]
+    fn test_container_capacity(_agb: &mut agb::Gba) {
+        let mut positions =
+            VecComponentContainer::<TestPosition, ExternalAllocator>::with_capacity_in(
+                16,
+                ExternalAllocator,
+            );
+        let (active, slots) = positions.raw_parts();
+        assert!(active.is_empty() && slots.is_empty());
+
+        positions.reserve(8);
+        let entity = Entity::new(20);
+        positions.add_entity(entity);
+        positions.set(entity, TestPosition { x: 1, y: 2 });
+        assert_eq!(positions.get(entity), Some(&TestPosition { x: 1, y: 2 }));
+
+        let mut hash = HashComponentContainer::<TestPosition, InternalAllocator>::with_capacity_in(
+            4,
+            InternalAllocator,
+        );
+        hash.set(entity, TestPosition { x: 3, y: 4 });
+        assert_eq!(hash.len(), 1);
+
+        let mut world = World::<MacroTestWorld>::new();
+        world.reserve(64);
+        let entities = world.spawn_batch(64);
+        world.add(entities[63], TestVelocity { dx: 1, dy: 0 });
+        assert!(world.has::<TestVelocity>(entities[63]));
+    }
+
+    #[test_case]
+    fn test_world_count(_agb: &mut agb::Gba) {
+        let mut world = World::<MixedTestWorld>::new();
+        let entities: Vec<Entity> = (0..10).map(|_| world.spawn()).collect();
+        assert_eq!(world.count::<TestVelocity>(), 0);
+
+        for &entity in &entities[..4] {
+            world.add(entity, TestVelocity { dx: 0, dy: 0 });
+            world.add(entity, TestVelocity { dx: 1, dy: 0 });
+            world.add(entity, TestPosition { x: 0, y: 0 });
+        }
+        assert_eq!(world.count::<TestVelocity>(), 4);
+        assert_eq!(world.count::<TestPosition>(), 4);
+        assert_eq!(world.get::<TestVelocity>().len(), 10);
+
+        world.remove::<TestVelocity>(entities[1]);
+        world.remove::<TestVelocity>(entities[1]);
+        world.add(entities[1], TestVelocity { dx: 2, dy: 0 });
+        world.despawn(entities[2]);
+        assert_eq!(world.count::<TestVelocity>(), 3);
+        assert_eq!(world.count::<TestPosition>(), 3);
+        assert!(world.get::<TestVelocity>().check_invariants());
+    }
+
+    #[test_case]
+    fn test_get_two_mut(_agb: &mut agb::Gba) {
+        let mut world = World::<MixedTestWorld>::new();
+        let entity = world.spawn();
+        world.add(entity, TestPosition { x: 10, y: 20 });
+        world.add(entity, TestVelocity { dx: 1, dy: -1 });
+
+        let (positions, velocities) = world.get_two_mut::<TestPosition, TestVelocity>();
+        if let (Some(position), Some(velocity)) =
+            (positions.get_mut(entity), velocities.get_mut(entity))
+        {
+            position.x += velocity.dx;
+            position.y += velocity.dy;
+            velocity.dx = 0;
+        }
+
+        assert_eq!(
+            world.get::<TestPosition>().get(entity),
+            Some(&TestPosition { x: 11, y: 19 })
+        );
+        assert_eq!(world.get::<TestVelocity>().get(entity).unwrap().dx, 0);
+    }
+
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    struct TestDamage(i32);
+
+    #[derive(Debug, PartialEq)]
+    struct TestTagged<T: Copy + PartialEq>(T, u8)
+    where
+        T: core::fmt::Debug;
+
+    #[test_case]
+    fn test_tuple_and_generic_components(_agb: &mut agb::Gba) {
+        let entity = Entity::new(3);
+
+        let mut damage = VecComponentContainer::<TestDamage>::new();
+        damage.add_entity(entity);
+        damage.set(entity, TestDamage(7));
+        assert_eq!(damage.get(entity), Some(&TestDamage(7)));
+
+        let mut tagged = HashComponentContainer::<TestTagged<u16>>::new();
+        tagged.set(entity, TestTagged(500, 2));
+        if let Some(tag) = tagged.get_mut(entity) {
+            tag.1 += 1;
+        }
+        assert_eq!(tagged.get(entity), Some(&TestTagged(500, 3)));
+    }
+
+    struct PackedTestWorld {
+        test_position: PackedComponentContainer<TestPosition>,
+        test_velocity: VecComponentContainer<TestVelocity>,
+    }
+
+    impl gba_ecs_rs::WorldContainer for PackedTestWorld {
+        fn new() -> Self {
+            Self {
+                test_position: PackedComponentContainer::new(),
+                test_velocity: VecComponentContainer::new(),
+            }
+        }
+        fn add_entity(&mut self, entity: Entity) {
+            self.test_position.add_entity(entity);
+            self.test_velocity.add_entity(entity);
+        }
+        fn remove_entity(&mut self, entity: Entity) {
+            self.test_position.remove_entity(entity);
+            self.test_velocity.remove_entity(entity);
+        }
+        fn merge_from(&mut self, other: Self, offset: usize) {
+            self.test_position.merge_from(other.test_position, offset);
+            self.test_velocity.merge_from(other.test_velocity, offset);
+        }
+        fn clear(&mut self) {
+            self.test_position.clear();
+            self.test_velocity.clear();
+        }
+    }
+
+    impl gba_ecs_rs::GetComponentContainer<TestPosition> for PackedTestWorld {
+        type Container = PackedComponentContainer<TestPosition>;
+        fn get_components(&self) -> &Self::Container {
+            &self.test_position
+        }
+        fn get_components_mut(&mut self) -> &mut Self::Container {
+            &mut self.test_position
+        }
+    }
+
+    impl gba_ecs_rs::GetComponentContainer<TestVelocity> for PackedTestWorld {
+        type Container = VecComponentContainer<TestVelocity>;
+        fn get_components(&self) -> &Self::Container {
+            &self.test_velocity
+        }
+        fn get_components_mut(&mut self) -> &mut Self::Container {
+            &mut self.test_velocity
+        }
+    }
+
+    #[test_case]
+    fn test_packed_container(_agb: &mut agb::Gba) {
+        let mut world = World::<PackedTestWorld>::new();
+        let entities: Vec<Entity> = (0..5).map(|_| world.spawn()).collect();
+        for (i, &entity) in entities.iter().enumerate() {
+            world.add(entity, TestPosition { x: i as i32, y: 0 });
+        }
+        world.add(entities[1], TestVelocity { dx: 1, dy: 0 });
+        world.add(entities[4], TestVelocity { dx: 4, dy: 0 });
+
+        assert_eq!(
+            world.remove::<TestPosition>(entities[1]),
+            Some(TestPosition { x: 1, y: 0 })
+        );
+        assert_eq!(
+            world.component_storage_kind::<TestPosition>(),
+            StorageKind::Packed
+        );
+
+        // The last component was swapped into the freed slot.
+        let (indices, positions) = world.get::<TestPosition>().raw_parts();
+        assert_eq!(indices, &[0, 4, 2, 3]);
+        assert_eq!(positions[1], TestPosition { x: 4, y: 0 });
+        assert!(world.get::<TestPosition>().check_invariants());
+
+        let mut matches = Vec::new();
+        world.for_each::<(&TestPosition, &TestVelocity), _>(|entity, (pos, vel)| {
+            matches.push((entity, pos.x, vel.dx));
+        });
+        assert_eq!(matches, vec![(4, 4, 4)]);
+
+        world.despawn(entities[0]);
+        let respawned = world.spawn();
+        assert!(world.get::<TestPosition>().get(entities[0]).is_none());
+        assert!(world.get::<TestPosition>().get(respawned).is_none());
+        world.add(respawned, TestPosition { x: 9, y: 9 });
+        assert_eq!(world.count::<TestPosition>(), 4);
+        assert!(world.get::<TestPosition>().check_invariants());
+    }
+
+    #[test_case]
+    fn test_signature_matching(_agb: &mut agb::Gba) {
+        let mut world = World::<QuadTestWorld>::new();
+        let entities: Vec<Entity> = (0..6).map(|_| world.spawn()).collect();
+        for (i, &entity) in entities.iter().enumerate() {
+            world.add(entity, TestPosition { x: i as i32, y: 0 });
+            if i % 2 == 0 {
+                world.add(entity, TestVelocity { dx: 1, dy: 0 });
+            }
+            if i == 4 {
+                world.add(entity, TestHealth(10));
+            }
+        }
+
+        let position = world.component_mask::<TestPosition>();
+        let velocity = world.component_mask::<TestVelocity>();
+        let health = world.component_mask::<TestHealth>();
+        assert_eq!(position, 1);
+        assert_eq!(health, 1 << 3);
+        assert_eq!(world.signature(entities[4]), position | velocity | health);
+
+        let mut matches = Vec::new();
+        world.for_each_matching(position | velocity, |entity| matches.push(entity));
+        assert_eq!(matches, vec![0, 2, 4]);
+
+        world.remove::<TestVelocity>(entities[2]);
+        world.despawn(entities[0]);
+        world.disable(entities[4]);
+        let mut matches = Vec::new();
+        world.for_each_matching(position | velocity, |entity| matches.push(entity));
+        assert!(matches.is_empty());
+
+        let mut matches = Vec::new();
+        world.for_each_matching(position, |entity| matches.push(entity));
+        assert_eq!(matches, vec![1, 2, 3, 5]);
+        assert_eq!(world.signature(entities[0]), 0);
+    }
+
+    impl Serialize for TestHealth {
+        const SIZE: usize = 4;
+        fn to_bytes(&self, out: &mut [u8]) {
+            out.copy_from_slice(&self.0.to_le_bytes());
+        }
+    }
+
+    impl Deserialize for TestHealth {
+        fn from_bytes(bytes: &[u8]) -> Self {
+            TestHealth(i32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+        }
+    }
+
+    impl ComponentId for TestPosition {
+        const ID: u16 = 3;
+    }
+
+    impl ComponentId for TestHealth {
+        const ID: u16 = 8;
+    }
+
+    gba_ecs_rs::world!(SavedTestWorld {
+        TestPosition,
+        TestHealth,
+        TestVelocity,
+        snapshot { TestPosition, TestHealth },
+    });
+
+    gba_ecs_rs::world!(ReorderedSavedTestWorld {
+        TestVelocity,
+        TestHealth,
+        TestPosition,
+        snapshot { TestHealth, TestPosition },
+    });
+
+    #[test_case]
+    fn test_world_macro_snapshot(_agb: &mut agb::Gba) {
+        let mut world = World::<SavedTestWorld>::new();
+        let entities: Vec<Entity> = (0..4).map(|_| world.spawn()).collect();
+        for &i in &[2, 0, 3] {
+            world.add(entities[i], TestPosition { x: i as i32, y: 1 });
+        }
+        world.add(entities[1], TestHealth(50));
+        world.add(entities[3], TestHealth(75));
+        world.add(entities[0], TestVelocity { dx: 1, dy: 1 });
+
+        let mut bytes = Vec::new();
+        world.save(&mut bytes);
+
+        let loaded = World::<SavedTestWorld>::load(&bytes).unwrap();
+        let order: Vec<usize> = loaded.get::<TestPosition>().iter_indices().collect();
+        assert_eq!(order, vec![2, 0, 3]);
+        assert_eq!(
+            loaded.get::<TestHealth>().get(Entity::new(3)),
+            Some(&TestHealth(75))
+        );
+        assert_eq!(loaded.count::<TestHealth>(), 2);
+        assert_eq!(loaded.count::<TestVelocity>(), 0);
+
+        let mut matches = 0;
+        let mask = loaded.component_mask::<TestPosition>() | loaded.component_mask::<TestHealth>();
+        loaded.for_each_matching(mask, |_| matches += 1);
+        assert_eq!(matches, 1);
+
+        assert_eq!(
+            World::<SavedTestWorld>::load(&bytes[..3]).err(),
+            Some(RestoreError::Truncated)
+        );
+    }
+
+    #[test_case]
+    fn test_query_count(_agb: &mut agb::Gba) {
+        let mut world = World::<MixedTestWorld>::new();
+        let entities: Vec<Entity> = (0..10).map(|_| world.spawn()).collect();
+        for (i, &entity) in entities.iter().enumerate() {
+            world.add(
+                entity,
+                TestVelocity {
+                    dx: i as i32,
+                    dy: 0,
+                },
+            );
+            if i % 3 == 0 {
+                world.add(entity, TestPosition { x: 0, y: 0 });
+            }
+        }
+
+        assert_eq!(<&TestVelocity as Query<MixedTestWorld>>::count(&world), 10);
+        assert_eq!(
+            <(&TestVelocity, &TestPosition) as Query<MixedTestWorld>>::count(&world),
+            4
+        );
+
+        world.disable(entities[3]);
+        assert_eq!(<&TestVelocity as Query<MixedTestWorld>>::count(&world), 9);
+        assert_eq!(
+            <(&TestVelocity, &TestPosition) as Query<MixedTestWorld>>::count(&world),
+            3
+        );
+    }
+
+    #[test_case]
+    fn test_query_find_and_first(_agb: &mut agb::Gba) {
+        let mut world = World::<MixedTestWorld>::new();
+        let entities: Vec<Entity> = (0..6).map(|_| world.spawn()).collect();
+        for (i, &entity) in entities.iter().enumerate() {
+            world.add(
+                entity,
+                TestVelocity {
+                    dx: i as i32,
+                    dy: 0,
+                },
+            );
+        }
+        world.add(entities[4], TestPosition { x: 40, y: 0 });
+        world.add(entities[5], TestPosition { x: 50, y: 0 });
+
+        let (index, player) =
+            <(&TestVelocity, &TestPosition) as Query<MixedTestWorld>>::first(&world).unwrap();
+        assert!(index == 4 || index == 5);
+        assert_eq!(player.0.dx as usize, index);
+
+        let mut tried = 0;
+        let found = <&TestVelocity as Query<MixedTestWorld>>::find(&world, |_, vel| {
+            tried += 1;
+            vel.dx == 2
+        });
+        assert_eq!(found.map(|(index, _)| index), Some(2));
+        assert_eq!(tried, 3);
+
+        world.disable(entities[2]);
+        assert!(
+            <&TestVelocity as Query<MixedTestWorld>>::find(&world, |_, vel| vel.dx == 2).is_none()
+        );
+        assert!(
+            <(&TestVelocity, gba_ecs_rs::Without<TestPosition>) as Query<MixedTestWorld>>::find(
+                &world,
+                |index, _| index >= 4
+            )
+            .is_none()
+        );
+    }
+
+    #[test_case]
+    fn test_vec_container_get_or_insert_with(_agb: &mut agb::Gba) {
+        let mut world = World::<MixedTestWorld>::new();
+        let a = world.spawn();
+        let b = world.spawn();
+        world.add(b, TestVelocity { dx: 1, dy: 1 });
+
+        let container = world.get_mut::<TestVelocity>();
+        container
+            .get_or_insert_with(a, || TestVelocity { dx: 5, dy: 0 })
+            .unwrap()
+            .dx += 1;
+        container
+            .get_or_insert_with(b, || TestVelocity { dx: 9, dy: 9 })
+            .unwrap()
+            .dx += 1;
+        assert!(container.validate());
+
+        let mut seen = Vec::new();
+        world.for_each_sparse::<&TestVelocity, _>(|index, vel| seen.push((index, vel.dx)));
+        seen.sort();
+        assert_eq!(seen, [(0, 6), (1, 2)]);
+    }
+
+    #[test_case]
+    fn test_tuple_query_sparse_vec_path(_agb: &mut agb::Gba) {
+        let mut world = World::<MixedTestWorld>::new();
+        let entities: Vec<Entity> = (0..20).map(|_| world.spawn()).collect();
+        for &index in &[15, 3] {
+            world.add(
+                entities[index],
+                TestVelocity {
+                    dx: index as i32,
+                    dy: 0,
+                },
+            );
+            world.add(entities[index], TestProjectile);
+        }
+        world.add(entities[7], TestVelocity { dx: 7, dy: 0 });
+
+        let zipped = gba_ecs_rs::zip(world.get::<TestVelocity>(), world.get::<TestProjectile>());
+        assert!(zipped.is_sparse());
+        drop(zipped);
+
+        let mut seen = Vec::new();
+        world.for_each::<(&TestVelocity, &TestProjectile), _>(|index, (vel, _)| {
+            seen.push((index, vel.dx))
+        });
+        assert_eq!(seen, [(15, 15), (3, 3)]);
+    }
+
+    #[test_case]
+    fn test_zip3_unequal_lengths(_agb: &mut agb::Gba) {
+        let mut positions = VecComponentContainer::<TestPosition>::new();
+        let mut velocities = VecComponentContainer::<TestVelocity>::new();
+        let mut projectiles = VecComponentContainer::<TestProjectile>::new();
+        for index in 0..6 {
+            let entity = Entity::new(index);
+            positions.add_entity(entity);
+            positions.set(
+                entity,
+                TestPosition {
+                    x: index as i32,
+                    y: 0,
+                },
+            );
+            velocities.add_entity(entity);
+            velocities.set(entity, TestVelocity { dx: 0, dy: 0 });
+        }
+        for index in 0..2 {
+            let entity = Entity::new(index);
+            projectiles.add_entity(entity);
+            projectiles.set(entity, TestProjectile);
+        }
+
+        let mut dense = Vec::new();
+        gba_ecs_rs::zip3(&positions, &velocities, &projectiles)
+            .for_each(|index, _, _, _| dense.push(index));
+        assert_eq!(dense, [0, 1]);
+
+        // The shortest active list points past the end of `projectiles`.
+        let mut far = VecComponentContainer::<TestProjectile>::new();
+        far.add_entity(Entity::new(8));
+        far.set(Entity::new(8), TestProjectile);
+        let mut sparse = Vec::new();
+        gba_ecs_rs::zip3(&projectiles, &positions, &far)
+            .for_each_sparse(|index, _, _, _| sparse.push(index));
+        assert!(sparse.is_empty());
+    }
+
+    #[test_case]
+    fn test_zip3_for_each_mut2(_agb: &mut agb::Gba) {
+        let mut positions = VecComponentContainer::<TestPosition>::new();
+        let mut velocities = VecComponentContainer::<TestVelocity>::new();
+        let mut projectiles = VecComponentContainer::<TestProjectile>::new();
+        for i in 0..3 {
+            let entity = Entity::new(i as usize);
+            positions.set(entity, TestPosition { x: 0, y: 0 });
+            velocities.set(entity, TestVelocity { dx: i, dy: 0 });
+            projectiles.set(entity, TestProjectile);
+        }
+
+        gba_ecs_rs::zip3_mut(&mut positions, &mut velocities, &projectiles).for_each_mut2(
+            |_, position, velocity, _| {
+                velocity.dx += 1;
+                position.x += velocity.dx;
+            },
+        );
+
+        let mut xs = Vec::new();
+        positions.for_each(|_, position| xs.push(position.x));
+        assert_eq!(xs, [1, 2, 3]);
+        assert_eq!(velocities.get(Entity::new(2)).map(|vel| vel.dx), Some(3));
+    }
+
+    #[test_case]
+    fn test_for_each_entity(_agb: &mut agb::Gba) {
+        let mut world = World::<MacroTestWorld>::new();
+        let first = world.spawn();
+        world.despawn(first);
+        let reused = world.spawn();
+        world.add(reused, TestPosition { x: 3, y: 4 });
+        assert_eq!(reused.generation(), 1);
+
+        let mut generations = Vec::new();
+        world.for_each_entity::<&TestPosition, _>(|entity, position| {
+            assert!(world.is_alive(entity));
+            assert_eq!(world.get::<TestPosition>().get(entity), Some(position));
+            generations.push(entity.generation());
+        });
+        assert_eq!(generations, [1]);
+
+        world.get::<TestPosition>().for_each_entity(|entity, _| {
+            generations.push(entity.generation());
+        });
+        assert_eq!(generations, [1, 1]);
+    }
+
+    #[test_case]
+    fn test_hash_container_for_each_ordered(_agb: &mut agb::Gba) {
+        let mut container = HashComponentContainer::<TestPosition>::new();
+        for &index in &[42, 7, 19, 0, 3] {
+            let entity = Entity::new(index);
+            container.add_entity(entity);
+            container.set(
+                entity,
+                TestPosition {
+                    x: index as i32,
+                    y: 0,
+                },
+            );
+        }
+
+        let mut visited = Vec::new();
+        container.for_each_ordered(|index, position| {
+            assert_eq!(position.x as usize, index);
+            visited.push(index);
+        });
+        assert_eq!(visited, [0, 3, 7, 19, 42]);
+    }
+
+    #[test_case]
+    fn test_vec_container_for_each_sorted_by(_agb: &mut agb::Gba) {
+        let mut world = World::<MixedTestWorld>::new();
+        for dy in [3, -1, 3, 0] {
+            let entity = world.spawn();
+            world.add(entity, TestVelocity { dx: 0, dy });
+        }
+
+        let mut scratch = Vec::new();
+        let mut order = Vec::new();
+        world.get::<TestVelocity>().for_each_sorted_by(
+            &mut scratch,
+            |velocity| velocity.dy,
+            |index, _| order.push(index),
+        );
+        assert_eq!(order, [1, 3, 0, 2]);
+
+        let (active, _) = world.get::<TestVelocity>().raw_parts();
+        assert_eq!(active, [0, 1, 2, 3]);
+    }
+
+    #[test_case]
+    fn test_for_each_copied(_agb: &mut agb::Gba) {
+        let mut world = World::<MixedTestWorld>::new();
+        for dx in 1..=3 {
+            let entity = world.spawn();
+            world.add(entity, TestVelocity { dx, dy: 0 });
+        }
+        let disabled = world.spawn();
+        world.add(disabled, TestVelocity { dx: 100, dy: 0 });
+        world.disable(disabled);
+
+        let mut total = 0;
+        world.for_each_copied::<TestVelocity, _>(|_, velocity| total += velocity.dx);
+        assert_eq!(total, 6);
+
+        let mut count = 0;
+        <&TestVelocity as Query<MixedTestWorld>>::for_each_copied(&world, |_, _| count += 1);
+        assert_eq!(count, 3);
+    }
+
+    #[test_case]
+    fn test_world_for_each_mut(_agb: &mut agb::Gba) {
+        let mut world = World::<MixedTestWorld>::new();
+        for i in 0..3 {
+            let entity = world.spawn();
+            world.add(entity, TestVelocity { dx: i, dy: 0 });
+            world.add(entity, TestProjectile);
+        }
+
+        world.for_each_mut::<&mut TestVelocity, _>(|_, velocity| velocity.dx += 1);
+        world.for_each_mut::<(&mut TestVelocity, &TestProjectile), _>(|_, (velocity, _)| {
+            velocity.dy = velocity.dx * 2;
+        });
+
+        let mut seen = Vec::new();
+        world.for_each::<&TestVelocity, _>(|_, velocity| seen.push((velocity.dx, velocity.dy)));
+        assert_eq!(seen, [(1, 2), (2, 4), (3, 6)]);
+    }
+
+    #[test_case]
+    fn test_world_drain(_agb: &mut agb::Gba) {
+        let mut world = World::<MixedTestWorld>::new();
+        for i in 0..6 {
+            let entity = world.spawn();
+            world.add(entity, TestVelocity { dx: i, dy: 0 });
+            world.add(entity, TestPosition { x: i, y: 0 });
+        }
+
+        let mut drained = Vec::new();
+        world.drain::<TestVelocity, _, _>(
+            |velocity| velocity.dx % 2 == 0,
+            |index, velocity| drained.push((index, velocity.dx)),
+        );
+        assert_eq!(drained, [(0, 0), (2, 2), (4, 4)]);
+        assert_eq!(world.count::<TestVelocity>(), 3);
+        assert!(world.get::<TestVelocity>().validate());
+
+        drained.clear();
+        world.drain::<TestPosition, _, _>(
+            |position| position.x > 3,
+            |index, _| drained.push((index, 0)),
+        );
+        drained.sort();
+        assert_eq!(drained, [(4, 0), (5, 0)]);
+        assert_eq!(world.count::<TestPosition>(), 4);
+    }
+
+    #[test_case]
+    fn test_array_container(_agb: &mut agb::Gba) {
+        let mut container = gba_ecs_rs::ArrayComponentContainer::<TestPosition, 8>::new();
+        assert_eq!(container.storage_kind(), StorageKind::Array);
+
+        for index in [5, 1, 3] {
+            container.set(
+                Entity::new(index),
+                TestPosition {
+                    x: index as i32,
+                    y: 0,
+                },
+            );
+        }
+        container.set(Entity::new(3), TestPosition { x: 30, y: 0 });
+        assert_eq!(container.count(), 3);
+        assert_eq!(container.len(), 6);
+
+        let mut visited = Vec::new();
+        container.for_each(|index, position| visited.push((index, position.x)));
+        assert_eq!(visited, [(1, 1), (3, 30), (5, 5)]);
+
+        assert_eq!(container.remove(Entity::new(1)).map(|p| p.x), Some(1));
+        assert!(container.get(Entity::with_generation(5, 1)).is_none());
+        assert_eq!(container.count(), 2);
+        assert!(container.check_invariants());
+
+        container.clear();
+        assert_eq!(container.count(), 0);
+        assert!(container.check_invariants());
+    }
+
+    #[test_case]
+    fn test_try_set_and_try_add(_agb: &mut agb::Gba) {
+        let mut container = VecComponentContainer::<TestVelocity>::new();
+        let far = Entity::new(10);
+        assert_eq!(
+            container.try_set(far, TestVelocity { dx: 1, dy: 0 }),
+            Ok(())
+        );
+        assert_eq!(container.get(far).map(|v| v.dx), Some(1));
+        assert!(container.validate());
+        assert_eq!(
+            container.try_set(Entity::new(usize::MAX), TestVelocity { dx: 0, dy: 0 }),
+            Err(gba_ecs_rs::SetError::IndexOutOfRange(usize::MAX))
+        );
+        assert_eq!(
+            container.try_set(Entity::new(usize::MAX / 2), TestVelocity { dx: 0, dy: 0 }),
+            Err(gba_ecs_rs::SetError::AllocationFailed)
+        );
+
+        let mut packed = PackedComponentContainer::<TestVelocity>::new();
+        assert!(packed.try_set(far, TestVelocity { dx: 2, dy: 0 }).is_ok());
+        assert!(packed.check_invariants());
+
+        // A handle to a previous occupant of the index leaves the slot alone.
+        let stale = Entity::with_generation(10, 1);
+        assert_eq!(
+            container.try_set(stale, TestVelocity { dx: 9, dy: 0 }),
+            Err(gba_ecs_rs::SetError::StaleEntity)
+        );
+        assert_eq!(container.get(far).map(|v| v.dx), Some(1));
+        assert_eq!(
+            packed.try_set(stale, TestVelocity { dx: 9, dy: 0 }),
+            Err(gba_ecs_rs::SetError::StaleEntity)
+        );
+        assert_eq!(packed.get(far).map(|v| v.dx), Some(2));
+        let mut hash = HashComponentContainer::<TestVelocity>::new();
+        hash.set(far, TestVelocity { dx: 3, dy: 0 });
+        assert_eq!(
+            hash.try_set(stale, TestVelocity { dx: 9, dy: 0 }),
+            Err(gba_ecs_rs::SetError::StaleEntity)
+        );
+        assert_eq!(hash.get(far).map(|v| v.dx), Some(3));
+
+        let mut world = World::<MixedTestWorld>::new();
+        let entity = world.spawn();
+        assert_eq!(world.try_add(entity, TestVelocity { dx: 3, dy: 0 }), Ok(()));
+        world.despawn(entity);
+        assert_eq!(
+            world.try_add(entity, TestVelocity { dx: 4, dy: 0 }),
+            Err(gba_ecs_rs::SetError::StaleEntity)
+        );
+
+        let mut other = World::<MixedTestWorld>::new();
+        assert_eq!(
+            other.try_add(Entity::new(7), TestPosition { x: 0, y: 0 }),
+            Err(gba_ecs_rs::SetError::StaleEntity)
+        );
+    }
+
+    #[test_case]
+    fn test_try_for_each_stops_early(_agb: &mut agb::Gba) {
+        use core::ops::ControlFlow;
+
+        let mut world = World::<MixedTestWorld>::new();
+        for i in 0..8 {
+            let entity = world.spawn();
+            world.add(entity, TestVelocity { dx: i, dy: 0 });
+            world.add(entity, TestPosition { x: i, y: 0 });
+        }
+
+        let mut visited = 0;
+        let found =
+            world.try_for_each::<(&TestVelocity, &TestPosition), _, _>(|index, (velocity, _)| {
+                visited += 1;
+                if velocity.dx == 2 {
+                    ControlFlow::Break(index)
+                } else {
+                    ControlFlow::Continue(())
+                }
+            });
+        assert_eq!(found, ControlFlow::Break(2));
+        assert_eq!(visited, 3);
+
+        let mut visited = 0;
+        let result = world.get::<TestVelocity>().try_for_each(|_, _| {
+            visited += 1;
+            ControlFlow::Break(())
+        });
+        assert_eq!(result, ControlFlow::Break(()));
+        assert_eq!(visited, 1);
+
+        let mut visited = 0;
+        let result: ControlFlow<()> = world.get::<TestPosition>().try_for_each(|_, _| {
+            visited += 1;
+            ControlFlow::Continue(())
+        });
+        assert_eq!(result, ControlFlow::Continue(()));
+        assert_eq!(visited, 8);
+    }
+
+    #[test_case]
+    fn test_vec_container_hooks(_agb: &mut agb::Gba) {
+        use portable_atomic::{AtomicUsize, Ordering};
+
+        static ADDED: AtomicUsize = AtomicUsize::new(0);
+        static REMOVED: AtomicUsize = AtomicUsize::new(0);
+
+        let mut container = VecComponentContainer::<TestVelocity>::new();
+        container.set_on_add(Some(|index, velocity: &TestVelocity| {
+            assert_eq!(velocity.dx as usize, index);
+            ADDED.fetch_add(1, Ordering::Relaxed);
+        }));
+        container.set_on_remove(Some(|_, _: &mut TestVelocity| {
+            REMOVED.fetch_add(1, Ordering::Relaxed);
+        }));
+
+        for index in 0..4 {
+            let entity = Entity::new(index);
+            container.add_entity(entity);
+            container.set(
+                entity,
+                TestVelocity {
+                    dx: index as i32,
+                    dy: 0,
+                },
+            );
+        }
+        container.set(Entity::new(1), TestVelocity { dx: 1, dy: 1 });
+        assert_eq!(ADDED.load(Ordering::Relaxed), 4);
+
+        container.remove(Entity::new(0));
+        container.drain_where(|velocity| velocity.dx == 1, |_, _| {});
+        assert_eq!(REMOVED.load(Ordering::Relaxed), 2);
+
+        // Moving onto an occupied slot removes its component through the hook;
+        // the moved one fires nothing.
+        container.set(Entity::new(0), TestVelocity { dx: 0, dy: 0 });
+        assert!(container.move_component(Entity::new(3), Entity::new(0)));
+        assert_eq!(REMOVED.load(Ordering::Relaxed), 3);
+        assert_eq!(container.get_index(0).map(|velocity| velocity.dx), Some(3));
+        assert!(container.validate());
+
+        // Cloning over the container removes its components and adds the
+        // copies through the hooks.
+        let mut source = VecComponentContainer::<TestVelocity>::new();
+        for index in [1, 3] {
+            let entity = Entity::new(index);
+            source.add_entity(entity);
+            source.set(
+                entity,
+                TestVelocity {
+                    dx: index as i32,
+                    dy: 0,
+                },
+            );
+        }
+        source.clone_into(&mut container);
+        assert_eq!(REMOVED.load(Ordering::Relaxed), 5);
+        assert_eq!(ADDED.load(Ordering::Relaxed), 7);
+        assert!(container.validate());
+
+        container.clear();
+        assert_eq!(REMOVED.load(Ordering::Relaxed), 7);
+        assert!(container.validate());
+    }
+
+    #[test_case]
+    fn test_vec_set_grows_container(_agb: &mut agb::Gba) {
+        let mut container = VecComponentContainer::<TestVelocity>::new();
+        container.set(Entity::new(42), TestVelocity { dx: 42, dy: 0 });
+
+        assert_eq!(container.len(), 43);
+        assert_eq!(container.get(Entity::new(42)).map(|v| v.dx), Some(42));
+        assert!(container.validate());
+    }
+
+    #[test_case]
+    fn test_schedule_runs_stages_in_order(_agb: &mut agb::Gba) {
+        use gba_ecs_rs::{Schedule, Stage};
+
+        fn movement(world: &mut World<MixedTestWorld>) {
+            world.for_each_mut::<&mut TestVelocity, _>(|_, velocity| velocity.dx *= 10);
+        }
+
+        let mut world = World::<MixedTestWorld>::new();
+        let entity = world.spawn();
+        world.add(entity, TestVelocity { dx: 1, dy: 0 });
+
+        let mut schedule = Schedule::new();
+        schedule
+            .add_system_to_stage(Stage::PostUpdate, |world: &mut World<MixedTestWorld>| {
+                world.for_each_mut::<&mut TestVelocity, _>(|_, velocity| velocity.dy = velocity.dx);
+            })
+            .add_system(movement)
+            .add_system_to_stage(Stage::PreUpdate, |world: &mut World<MixedTestWorld>| {
+                world.for_each_mut::<&mut TestVelocity, _>(|_, velocity| velocity.dx += 1);
+            });
+        assert_eq!(schedule.len(), 3);
+
+        schedule.run_all(&mut world);
+        let velocity = world.get::<TestVelocity>().get(entity).copied();
+        assert_eq!(velocity, Some(TestVelocity { dx: 20, dy: 20 }));
+
+        world.run(movement);
+        assert_eq!(
+            world.get::<TestVelocity>().get(entity).map(|v| v.dx),
+            Some(200)
+        );
+    }
+
+    #[test_case]
+    fn test_run_fused(_agb: &mut agb::Gba) {
+        let mut world = World::<MixedTestWorld>::new();
+        for i in 0..6 {
+            let entity = world.spawn();
+            if i != 4 {
+                world.add(entity, TestVelocity { dx: i, dy: 0 });
+            }
+            if i % 2 == 0 {
+                world.add(entity, TestProjectile);
+            }
+        }
+        world.disable(Entity::new(5));
+
+        let mut velocities = Vec::new();
+        let mut projectiles = Vec::new();
+        world.run_fused::<TestVelocity, TestProjectile, _, _>(
+            |index, velocity| {
+                velocity.dy = 1;
+                velocities.push(index);
+            },
+            |index, _| projectiles.push(index),
+        );
+        assert_eq!(velocities, [0, 1, 2, 3]);
+        assert_eq!(projectiles, [0, 2, 4]);
+
+        let mut mixed = World::<MixedTestWorld>::new();
+        let entity = mixed.spawn();
+        mixed.add(entity, TestVelocity { dx: 0, dy: 0 });
+        mixed.add(entity, TestPosition { x: 0, y: 0 });
+        let (mut calls_a, mut calls_b) = (0, 0);
+        mixed.run_fused::<TestVelocity, TestPosition, _, _>(
+            |_, _| calls_a += 1,
+            |_, _| calls_b += 1,
+        );
+        assert_eq!((calls_a, calls_b), (1, 1));
+    }
+
+    #[test_case]
+    fn test_adaptive_container_migrates(_agb: &mut agb::Gba) {
+        use gba_ecs_rs::AdaptiveComponentContainer;
+
+        let mut container = AdaptiveComponentContainer::<TestVelocity>::new();
+        for index in 0..16 {
+            container.add_entity(Entity::new(index));
+        }
+        container.set(Entity::new(3), TestVelocity { dx: 3, dy: 0 });
+        assert_eq!(container.storage_kind(), StorageKind::Hash);
+
+        for index in 0..8 {
+            container.set(
+                Entity::new(index),
+                TestVelocity {
+                    dx: index as i32,
+                    dy: 0,
+                },
+            );
+        }
+        assert_eq!(container.storage_kind(), StorageKind::Vec);
+        assert_eq!(container.density(), 0.5);
+        assert_eq!(container.get(Entity::new(3)).map(|v| v.dx), Some(3));
+
+        for index in 1..8 {
+            container.remove(Entity::new(index));
+        }
+        assert_eq!(container.storage_kind(), StorageKind::Hash);
+        assert_eq!(container.count(), 1);
+        assert_eq!(container.get(Entity::new(0)).map(|v| v.dx), Some(0));
+        assert!(container.check_invariants());
+
+        container.set(Entity::new(1), TestVelocity { dx: 1, dy: 0 });
+        container.set(Entity::new(2), TestVelocity { dx: 2, dy: 0 });
+        container.set(Entity::new(4), TestVelocity { dx: 4, dy: 0 });
+        assert_eq!(container.storage_kind(), StorageKind::Hash);
+        container.compact();
+        assert_eq!(container.storage_kind(), StorageKind::Vec);
+
+        let mut indices: Vec<usize> = container.iter_indices().collect();
+        indices.sort();
+        assert_eq!(indices, [0, 1, 2, 4]);
+    }
+
+    #[test_case]
+    fn test_cached_query(_agb: &mut agb::Gba) {
+        use gba_ecs_rs::CachedQuery;
+
+        let mut world = World::<MixedTestWorld>::new();
+        for i in 0..4 {
+            let entity = world.spawn();
+            world.add(entity, TestPosition { x: i, y: 0 });
+            if i != 2 {
+                world.add(entity, TestVelocity { dx: i, dy: 0 });
+            }
+        }
+
+        let mut cached: CachedQuery<(&TestPosition, &TestVelocity), _> =
+            world.cached_query::<(&TestPosition, &TestVelocity)>();
+        assert_eq!(cached.len(), 3);
+        assert!(!cached.is_dirty(&world));
+
+        world
+            .get_mut::<TestPosition>()
+            .get_mut(Entity::new(1))
+            .unwrap()
+            .x = 10;
+        world.disable(Entity::new(3));
+        assert!(!cached.is_dirty(&world));
+
+        let mut seen = Vec::new();
+        cached.for_each(&world, |index, (position, _)| {
+            seen.push((index, position.x))
+        });
+        assert_eq!(seen, [(0, 0), (1, 10)]);
+
+        world.add(Entity::new(2), TestVelocity { dx: 2, dy: 0 });
+        world.remove::<TestPosition>(Entity::new(0));
+        assert!(cached.is_dirty(&world));
+
+        let mut seen = Vec::new();
+        cached.for_each(&world, |index, _| seen.push(index));
+        seen.sort();
+        assert_eq!(seen, [1, 2]);
+        assert_eq!(cached.len(), 3);
+        assert!(!cached.is_dirty(&world));
+    }
+
+    #[test_case]
+    fn test_container_version(_agb: &mut agb::Gba) {
+        let mut world = World::<MixedTestWorld>::new();
+        let a = world.spawn();
+        let b = world.spawn();
+        let vec_start = world.version::<TestVelocity>();
+        let hash_start = world.version::<TestPosition>();
+
+        world.add(a, TestVelocity { dx: 1, dy: 0 });
+        world.add(a, TestPosition { x: 1, y: 0 });
+        assert_eq!(world.version::<TestVelocity>(), vec_start + 1);
+        assert_eq!(world.version::<TestPosition>(), hash_start + 1);
+
+        world.add(a, TestVelocity { dx: 2, dy: 0 });
+        world.add(a, TestPosition { x: 2, y: 0 });
+        world.get_mut::<TestVelocity>().get_mut(a).unwrap().dx = 3;
+        world.get_mut::<TestPosition>().get_mut(a).unwrap().x = 3;
+        assert_eq!(world.version::<TestVelocity>(), vec_start + 1);
+        assert_eq!(world.version::<TestPosition>(), hash_start + 1);
+
+        assert_eq!(world.remove::<TestVelocity>(b), None);
+        assert_eq!(world.remove::<TestPosition>(b), None);
+        assert_eq!(world.version::<TestVelocity>(), vec_start + 1);
+        assert_eq!(world.version::<TestPosition>(), hash_start + 1);
+
+        world.remove::<TestVelocity>(a);
+        world.remove::<TestPosition>(a);
+        assert_eq!(world.version::<TestVelocity>(), vec_start + 2);
+        assert_eq!(world.version::<TestPosition>(), hash_start + 2);
+    }
+
+    #[test_case]
+    fn test_swap_components(_agb: &mut agb::Gba) {
+        let mut world = World::<MixedTestWorld>::new();
+        let entities: Vec<Entity> = (0..4).map(|_| world.spawn()).collect();
+        world.add(entities[0], TestVelocity { dx: 0, dy: 0 });
+        world.add(entities[1], TestVelocity { dx: 1, dy: 0 });
+        world.add(entities[0], TestPosition { x: 0, y: 0 });
+
+        let velocity = |world: &World<MixedTestWorld>, entity| {
+            world.get::<TestVelocity>().get(entity).map(|v| v.dx)
+        };
+
+        world.swap::<TestVelocity>(entities[0], entities[1]);
+        assert_eq!(velocity(&world, entities[0]), Some(1));
+        assert_eq!(velocity(&world, entities[1]), Some(0));
+
+        let version = world.version::<TestVelocity>();
+        world.swap::<TestVelocity>(entities[1], entities[3]);
+        assert_eq!(velocity(&world, entities[1]), None);
+        assert_eq!(velocity(&world, entities[3]), Some(0));
+        assert!(!world.has::<TestVelocity>(entities[1]));
+        assert!(world.has::<TestVelocity>(entities[3]));
+        assert_eq!(world.version::<TestVelocity>(), version + 1);
+        assert!(world.get::<TestVelocity>().validate());
+
+        world.swap::<TestVelocity>(entities[1], entities[2]);
+        assert_eq!(world.count::<TestVelocity>(), 2);
+
+        world.swap::<TestPosition>(entities[0], entities[2]);
+        assert!(!world.has::<TestPosition>(entities[0]));
+        assert!(world.has::<TestPosition>(entities[2]));
+
+        let mut indices = Vec::new();
+        <&TestVelocity as Query<_>>::for_each_sparse(&world, |index, _| indices.push(index));
+        indices.sort();
+        assert_eq!(indices, [0, 3]);
+    }
+
+    #[test_case]
+    fn test_move_component(_agb: &mut agb::Gba) {
+        let mut world = World::<MixedTestWorld>::new();
+        let player = world.spawn();
+        let chest = world.spawn();
+        let other = world.spawn();
+        world.add(player, TestVelocity { dx: 1, dy: 0 });
+        world.add(other, TestVelocity { dx: 2, dy: 0 });
+        world.add(player, TestPosition { x: 1, y: 0 });
+
+        assert!(world.move_component::<TestVelocity>(player, chest));
+        assert!(!world.has::<TestVelocity>(player));
+        assert_eq!(
+            world.get::<TestVelocity>().get(chest),
+            Some(&TestVelocity { dx: 1, dy: 0 })
+        );
+        assert!(!world.move_component::<TestVelocity>(player, chest));
+
+        assert!(world.move_component::<TestVelocity>(other, chest));
+        assert_eq!(world.count::<TestVelocity>(), 1);
+        assert_eq!(
+            world.get::<TestVelocity>().get(chest).map(|v| v.dx),
+            Some(2)
+        );
+        assert!(world.get::<TestVelocity>().validate());
+        assert_eq!(world.signature(other), 0);
+
+        assert!(world.move_component::<TestPosition>(player, other));
+        assert_eq!(world.get::<TestPosition>().get(other).map(|p| p.x), Some(1));
+        assert!(!world.has::<TestPosition>(player));
+
+        world.despawn(chest);
+        assert!(!world.move_component::<TestPosition>(other, chest));
+    }
+
+    #[test_case]
+    fn test_triple_query_drives_rare_middle_component(_agb: &mut agb::Gba) {
+        let mut world = World::<MixedTestWorld>::new();
+        for i in 0..32 {
+            let entity = world.spawn();
+            world.add(entity, TestVelocity { dx: i, dy: 0 });
+            world.add(entity, TestProjectile);
+            if i == 17 {
+                world.add(entity, TestPosition { x: i, y: 0 });
+            }
+        }
+
+        gba_ecs_rs::reset_query_counters();
+        let mut matches = Vec::new();
+        world.for_each::<(&TestVelocity, &TestPosition, &TestProjectile), _>(
+            |entity, (vel, pos, _)| matches.push((entity, vel.dx, pos.x)),
+        );
+        // The lone position drives: one lookup each for velocity and projectile.
+        assert_eq!(matches, vec![(17, 17, 17)]);
+        assert_eq!(gba_ecs_rs::query_counters().lookups, 2);
+    }
+
+    #[test_case]
+    fn test_vec_container_slices(_agb: &mut agb::Gba) {
+        let mut world = World::<MixedTestWorld>::new();
+        for i in 0..5 {
+            let entity = world.spawn();
+            if i != 2 {
+                world.add(entity, TestVelocity { dx: i, dy: 1 });
+            }
+        }
+        world.remove::<TestVelocity>(Entity::new(0));
+
+        let velocities = world.get_mut::<TestVelocity>();
+        assert_eq!(velocities.active_indices(), [1, 3, 4]);
+        assert_eq!(velocities.as_slice().len(), 5);
+        assert!(velocities.as_slice()[2].is_none());
+
+        for velocity in velocities.as_mut_slice().iter_mut().flatten() {
+            velocity.dx += velocity.dy;
+        }
+        assert_eq!(
+            velocities.as_slice()[4],
+            Some(TestVelocity { dx: 5, dy: 1 })
+        );
+        assert!(velocities.validate());
+    }
+
+    #[test_case]
+    fn test_snapshot_matches_components_by_id(_agb: &mut agb::Gba) {
+        let mut world = World::<SavedTestWorld>::new();
+        let entities: Vec<Entity> = (0..3).map(|_| world.spawn()).collect();
+        world.add(entities[0], TestPosition { x: 4, y: 5 });
+        world.add(entities[2], TestHealth(30));
+
+        let mut bytes = Vec::new();
+        world.save(&mut bytes);
+        assert_eq!(bytes[6..8], TestPosition::ID.to_le_bytes());
+
+        let loaded = World::<ReorderedSavedTestWorld>::load(&bytes).unwrap();
+        assert_eq!(
+            loaded.get::<TestPosition>().get(Entity::new(0)),
+            Some(&TestPosition { x: 4, y: 5 })
+        );
+        assert_eq!(
+            loaded.get::<TestHealth>().get(Entity::new(2)),
+            Some(&TestHealth(30))
+        );
+        assert_eq!(loaded.count::<TestHealth>(), 1);
+    }
+
+    #[test_case]
+    fn test_for_each_any(_agb: &mut agb::Gba) {
+        let mut world = World::<MixedTestWorld>::new();
+        let entities: Vec<Entity> = (0..6).map(|_| world.spawn()).collect();
+        world.add(entities[0], TestPosition { x: 0, y: 0 });
+        world.add(entities[1], TestVelocity { dx: 1, dy: 0 });
+        world.add(entities[1], TestProjectile);
+        world.add(entities[3], TestPosition { x: 3, y: 0 });
+        world.add(entities[3], TestVelocity { dx: 3, dy: 0 });
+        world.add(entities[3], TestProjectile);
+        world.add(entities[4], TestProjectile);
+        world.add(entities[5], TestVelocity { dx: 5, dy: 0 });
+        world.disable(entities[5]);
+
+        let mut visits = Vec::new();
+        world.for_each_any::<(TestPosition, TestVelocity, TestProjectile), _>(
+            |index, (position, velocity, projectile)| {
+                visits.push((
+                    index,
+                    position.map(|p| p.x),
+                    velocity.map(|v| v.dx),
+                    projectile.is_some(),
+                ));
+            },
+        );
+        visits.sort();
+        assert_eq!(
+            visits,
+            [
+                (0, Some(0), None, false),
+                (1, None, Some(1), true),
+                (3, Some(3), Some(3), true),
+                (4, None, None, true),
+            ]
+        );
+
+        let mut count = 0;
+        world.for_each_any::<(TestPosition, TestProjectile), _>(|_, _| count += 1);
+        assert_eq!(count, 4);
+    }
+
+    #[test_case]
+    fn test_optional_query_component(_agb: &mut agb::Gba) {
+        use gba_ecs_rs::{Opt, With};
+
+        let mut world = World::<MixedTestWorld>::new();
+        for i in 0..4 {
+            let entity = world.spawn();
+            world.add(entity, TestVelocity { dx: i, dy: 0 });
+            if i % 2 == 0 {
+                world.add(entity, TestPosition { x: i * 10, y: 0 });
+            }
+            if i >= 2 {
+                world.add(entity, TestProjectile);
+            }
+        }
+        world.add(Entity::new(0), TestProjectile);
+        world.remove::<TestVelocity>(Entity::new(0));
+
+        let mut seen = Vec::new();
+        world.for_each::<(&TestVelocity, Opt<TestPosition>), _>(|index, (velocity, position)| {
+            seen.push((index, velocity.dx, position.map(|p| p.x)));
+        });
+        seen.sort();
+        assert_eq!(seen, [(1, 1, None), (2, 2, Some(20)), (3, 3, None)]);
+
+        let mut seen = Vec::new();
+        world.for_each::<(&TestVelocity, Opt<TestPosition>, With<TestProjectile>), _>(
+            |index, (_, position)| seen.push((index, position.is_some())),
+        );
+        seen.sort();
+        assert_eq!(seen, [(2, true), (3, false)]);
+
+        let mut cached = world.cached_query::<(&TestVelocity, Opt<TestPosition>)>();
+        world.add(Entity::new(1), TestPosition { x: 10, y: 0 });
+        assert!(!cached.is_dirty(&world));
+        let mut positions = 0;
+        cached.for_each(&world, |_, (_, position)| {
+            positions += position.is_some() as usize
+        });
+        assert_eq!(positions, 2);
+    }
+
+    #[test_case]
+    fn test_compact_after_mass_despawn(_agb: &mut agb::Gba) {
+        let mut world = World::<MacroTestWorld>::new();
+        let entities: Vec<Entity> = (0..64).map(|_| world.spawn()).collect();
+        for &entity in &entities {
+            world.add(entity, TestProjectile);
+        }
+        for &entity in &entities[3..] {
+            world.despawn(entity);
+        }
+        world.despawn(entities[1]);
+
+        world.compact_all();
+        let projectiles = world.get::<TestProjectile>();
+        assert_eq!(projectiles.len(), 3);
+        assert_eq!(projectiles.active_indices().len(), 2);
+        assert!(projectiles.validate());
+        assert!(world.has::<TestProjectile>(entities[2]));
+
+        let entity = world.spawn();
+        world.add(entity, TestProjectile);
+        assert!(world.has::<TestProjectile>(entity));
+        assert_eq!(world.count::<TestProjectile>(), 3);
+
+        // Each container is truncated to its own last component, so two Global
+        // vecs zipped by pair queries end up with different lengths.
+        let mut world = World::<OrderTestWorld>::new();
+        let entities: Vec<Entity> = (0..16).map(|_| world.spawn()).collect();
+        for (i, &entity) in entities.iter().enumerate() {
+            world.add(entity, TestPosition { x: i as i32, y: 0 });
+            world.add(entity, TestVelocity { dx: 1, dy: 1 });
+        }
+        for &entity in &entities[2..] {
+            world.remove::<TestVelocity>(entity);
+        }
+        for &entity in &entities[4..] {
+            world.despawn(entity);
+        }
+        world.compact_all();
+        assert_eq!(world.get::<TestPosition>().len(), 4);
+        assert_eq!(world.get::<TestVelocity>().len(), 2);
+
+        world.for_each_mut::<(&mut TestPosition, &TestVelocity), _>(|_, (pos, vel)| {
+            pos.x += vel.dx;
+        });
+        world.for_each_mut::<(&mut TestPosition, &mut TestVelocity), _>(|_, (pos, vel)| {
+            vel.dy = pos.x;
+        });
+        let mut visited = Vec::new();
+        world.for_each::<(&TestPosition, &TestVelocity), _>(|entity, (pos, vel)| {
+            visited.push((entity, pos.x, vel.dy))
+        });
+        assert_eq!(visited, vec![(0, 1, 1), (1, 2, 2)]);
+    }
+
+    #[test_case]
+    fn test_spawn_observer(_agb: &mut agb::Gba) {
+        use portable_atomic::{AtomicUsize, Ordering};
+
+        static SPAWNS: AtomicUsize = AtomicUsize::new(0);
+        static LIVE: AtomicUsize = AtomicUsize::new(usize::MAX);
+
+        fn on_spawn(_entity: Entity, live: usize) {
+            SPAWNS.fetch_add(1, Ordering::Relaxed);
+            LIVE.store(live, Ordering::Relaxed);
+        }
+
+        fn on_despawn(_entity: Entity, live: usize) {
+            LIVE.store(live, Ordering::Relaxed);
+        }
+
+        let mut world = World::<MixedTestWorld>::new();
+        let early = world.spawn();
+        world.set_spawn_observer(Some(on_spawn), Some(on_despawn));
+
+        let entity = world.spawn();
+        assert_eq!(LIVE.load(Ordering::Relaxed), 2);
+        world.spawn_batch(3);
+        assert_eq!(SPAWNS.load(Ordering::Relaxed), 4);
+        assert_eq!(LIVE.load(Ordering::Relaxed), 5);
+
+        world.despawn(early);
+        assert_eq!(LIVE.load(Ordering::Relaxed), 4);
+        world.despawn(early);
+        world.despawn(entity);
+        assert_eq!(LIVE.load(Ordering::Relaxed), 3);
+        assert_eq!(world.live_count(), 3);
+
+        world.spawn();
+        assert_eq!(LIVE.load(Ordering::Relaxed), 4);
+
+        world.set_spawn_observer(None, None);
+        world.spawn();
+        assert_eq!(SPAWNS.load(Ordering::Relaxed), 5);
+        assert_eq!(world.live_count(), 5);
+    }
+
+    #[test_case]
+    fn test_get_checked(_agb: &mut agb::Gba) {
+        use gba_ecs_rs::EntityError;
+
+        let mut world = World::<MixedTestWorld>::new();
+        let entity = world.spawn();
+        let bare = world.spawn();
+        world.add(entity, TestVelocity { dx: 1, dy: 0 });
+
+        assert_eq!(
+            world.get_checked::<TestVelocity>(entity),
+            Ok(Some(&TestVelocity { dx: 1, dy: 0 }))
+        );
+        assert_eq!(world.get_checked::<TestVelocity>(bare), Ok(None));
+        assert_eq!(
+            world.get_checked::<TestVelocity>(Entity::new(9)),
+            Err(EntityError::OutOfRange(9))
+        );
+
+        world.despawn(entity);
+        let reused = world.spawn();
+        world.add(reused, TestVelocity { dx: 2, dy: 0 });
+        assert_eq!(
+            world.get_checked::<TestVelocity>(entity),
+            Err(EntityError::Stale)
+        );
+
+        let velocities = world.get::<TestVelocity>();
+        assert_eq!(velocities.get_checked(entity), Err(EntityError::Stale));
+        assert_eq!(velocities.get_checked(bare), Ok(None));
+        assert_eq!(
+            velocities.get_checked(Entity::new(9)),
+            Err(EntityError::OutOfRange(9))
+        );
+    }
+
+    #[test_case]
+    fn test_for_each_through_trait(_agb: &mut agb::Gba) {
+        fn bump_and_sum<S: ComponentContainer<TestVelocity>>(container: &mut S) -> i32 {
+            container.for_each_mut(|_, velocity| velocity.dx += 1);
+            let mut sum = 0;
+            container.for_each(|index, velocity| sum += index as i32 * velocity.dx);
+            sum
+        }
+
+        fn fill<S: ComponentContainer<TestVelocity>>(container: &mut S) {
+            for index in [1, 3] {
+                container.set(Entity::new(index), TestVelocity { dx: 1, dy: 0 });
+            }
+        }
+
+        let mut vec = VecComponentContainer::new();
+        let mut hash = HashComponentContainer::new();
+        let mut packed = PackedComponentContainer::new();
+        let mut array = gba_ecs_rs::ArrayComponentContainer::<TestVelocity, 4>::new();
+        fill(&mut vec);
+        fill(&mut hash);
+        fill(&mut packed);
+        fill(&mut array);
+
+        assert_eq!(bump_and_sum(&mut vec), 8);
+        assert_eq!(bump_and_sum(&mut hash), 8);
+        assert_eq!(bump_and_sum(&mut packed), 8);
+        assert_eq!(bump_and_sum(&mut array), 8);
+    }
+
+    #[test_case]
+    fn test_spawn_with_builder(_agb: &mut agb::Gba) {
+        let mut world = World::<MixedTestWorld>::new();
+        let player = world
+            .spawn_with()
+            .with(TestPosition { x: 1, y: 2 })
+            .with(TestVelocity { dx: 3, dy: 4 })
+            .build();
+        let bullet = world.spawn_with().with(TestProjectile).build();
+
+        assert_eq!(
+            world.get::<TestPosition>().get(player),
+            Some(&TestPosition { x: 1, y: 2 })
+        );
+        assert!(world.has::<TestVelocity>(player));
+        assert!(!world.has::<TestProjectile>(player));
+        assert!(world.has::<TestProjectile>(bullet));
+
+        let mut matches = 0;
+        world.for_each::<(&TestPosition, &TestVelocity), _>(|_, _| matches += 1);
+        assert_eq!(matches, 1);
+    }
+
+    gba_ecs_rs::world!(HierarchyTestWorld {
+        TestPosition,
+        Parent,
+        Children,
+    });
+
+    #[test_case]
+    fn test_parent_child_relation(_agb: &mut agb::Gba) {
+        let mut world = World::<HierarchyTestWorld>::new();
+        let ship = world.spawn();
+        let turret = world.spawn();
+        let barrel = world.spawn();
+        let other = world.spawn();
+        world.set_parent(turret, ship);
+        world.set_parent(barrel, turret);
+        world.set_parent(other, ship);
+
+        let children = |world: &World<HierarchyTestWorld>, entity| {
+            world.get::<Children>().get(entity).map_or(0, Children::len)
+        };
+        assert_eq!(children(&world, ship), 2);
+        assert_eq!(children(&world, turret), 1);
+
+        world.set_parent(other, turret);
+        assert_eq!(children(&world, ship), 1);
+        assert_eq!(children(&world, turret), 2);
+
+        assert_eq!(
+            world.remove_parent(other).map(|parent| parent.generation()),
+            Some(turret.generation())
+        );
+        assert!(!world.has::<Parent>(other));
+        assert_eq!(children(&world, turret), 1);
+
+        world.set_parent(other, barrel);
+        world.despawn_recursive(turret);
+        assert!(world.is_alive(ship));
+        assert!(!world.is_alive(turret));
+        assert!(!world.is_alive(barrel));
+        assert!(!world.is_alive(other));
+        assert_eq!(children(&world, ship), 0);
+    }
+
+    #[test_case]
+    fn test_reserve_entity(_agb: &mut agb::Gba) {
+        let mut world = World::<MacroTestWorld>::new();
+        let first = world.spawn();
+        let reserved = world.reserve_entity();
+        assert!(world.is_alive(reserved));
+        assert!(world.is_reserved(reserved));
+        assert_eq!(world.get::<TestVelocity>().len(), 1);
+        assert!(world.get::<TestVelocity>().get(reserved).is_none());
+
+        world.add(reserved, TestVelocity { dx: 1, dy: 2 });
+        assert!(!world.is_reserved(reserved));
+        assert_eq!(world.get::<TestVelocity>().len(), 2);
+        assert_eq!(world.get::<TestVelocity>().get(reserved).unwrap().dy, 2);
+
+        let explicit = world.reserve_entity();
+        world.materialize(explicit);
+        assert!(!world.is_reserved(explicit));
+        assert_eq!(world.get::<TestVelocity>().len(), 3);
+
+        let dropped = world.reserve_entity();
+        world.despawn(dropped);
+        assert!(!world.is_alive(dropped));
+        assert!(!world.is_reserved(dropped));
+        assert!(!world.is_reserved(first));
+    }
+
+    #[test_case]
+    fn test_for_each_sparse_ordered(_agb: &mut agb::Gba) {
+        let mut world = World::<MacroTestWorld>::new();
+        let entities: Vec<Entity> = (0..6).map(|_| world.spawn()).collect();
+        for &i in &[4, 1, 5, 2] {
+            world.add(
+                entities[i],
+                TestVelocity {
+                    dx: i as i32,
+                    dy: 0,
+                },
+            );
+        }
+        world.disable(entities[5]);
+
+        let mut visited = Vec::new();
+        world.for_each_sparse::<&TestVelocity, _>(|entity, _| visited.push(entity));
+        assert_eq!(visited, vec![4, 1, 2]);
+
+        let mut scratch = Vec::new();
+        visited.clear();
+        world.for_each_sparse_ordered::<&TestVelocity, _>(&mut scratch, |entity, vel| {
+            assert_eq!(vel.dx, entity as i32);
+            visited.push(entity);
+        });
+        assert_eq!(visited, vec![1, 2, 4]);
+    }
+
+    #[test_case]
+    fn test_take_all(_agb: &mut agb::Gba) {
+        let mut world = World::<MacroTestWorld>::new();
+        let entities: Vec<Entity> = (0..4).map(|_| world.spawn()).collect();
+        world.add(entities[3], TestVelocity { dx: 3, dy: 0 });
+        world.add(entities[1], TestVelocity { dx: 1, dy: 0 });
+
+        let taken = world.take_all::<TestVelocity>();
+        let taken: Vec<(usize, i32)> = taken.iter().map(|(index, vel)| (*index, vel.dx)).collect();
+        assert_eq!(taken, vec![(3, 3), (1, 1)]);
+        assert_eq!(world.get::<TestVelocity>().count(), 0);
+        assert!(!world.has::<TestVelocity>(entities[3]));
+
+        world.add(entities[2], TestVelocity { dx: 2, dy: 0 });
+        let taken = world.get_mut::<TestVelocity>().take_all();
+        assert_eq!(taken.len(), 1);
+        assert_eq!(taken[0].0, 2);
+        assert!(world.get::<TestVelocity>().len() >= 4);
+
+        let mut mixed = World::<MixedTestWorld>::new();
+        let entity = mixed.spawn();
+        mixed.add(entity, TestPosition { x: 5, y: 6 });
+        let taken = mixed.get_mut::<TestPosition>().take_all();
+        assert_eq!(taken.len(), 1);
+        assert_eq!(taken[0].1.y, 6);
+        assert!(mixed.get::<TestPosition>().get(entity).is_none());
+    }
+
+    #[test_case]
+    fn test_world_view(_agb: &mut agb::Gba) {
+        fn total_dx(view: WorldView<'_, MacroTestWorld>) -> i32 {
+            let mut total = 0;
+            view.for_each::<&TestVelocity, _>(|_, vel| total += vel.dx);
+            total
+        }
+
+        let mut world = World::<MacroTestWorld>::new();
+        let a = world.spawn();
+        let b = world.spawn();
+        world.add(a, TestVelocity { dx: 2, dy: 0 });
+        world.add(b, TestVelocity { dx: 5, dy: 0 });
+        world.add(b, TestPosition { x: 1, y: 1 });
+
+        let view = world.as_view();
+        assert_eq!(total_dx(view), 7);
+        assert!(view.has::<TestPosition>(b));
+        assert!(!view.has::<TestPosition>(a));
+        assert_eq!(view.count::<TestVelocity>(), 2);
+        assert_eq!(view.get::<TestVelocity>().get(a).unwrap().dx, 2);
+        assert_eq!(view.live_count(), 2);
+    }
+
+    #[derive(Clone, Default)]
+    struct Score(i32);
+
+    gba_ecs_rs::world!(ScoreTestWorld { Score });
+
+    #[test_case]
+    fn test_for_each_or_default(_agb: &mut agb::Gba) {
+        let mut world = World::<ScoreTestWorld>::new();
+        let entities: Vec<Entity> = (0..5).map(|_| world.spawn()).collect();
+        world.add(entities[1], Score(10));
+        world.add(entities[3], Score(30));
+        world.despawn(entities[2]);
+        world.disable(entities[4]);
+
+        let mut rows = Vec::new();
+        world.for_each_or_default::<Score, _>(|index, score| rows.push((index, score.0)));
+        assert_eq!(rows, vec![(0, 0), (1, 10), (3, 30)]);
+    }
+
+    #[test_case]
+    fn test_zip_vec_hash(_agb: &mut agb::Gba) {
+        let mut world = World::<MixedTestWorld>::new();
+        for i in 0..8 {
+            let entity = world.spawn();
+            world.add(entity, TestVelocity { dx: i, dy: 0 });
+            if i % 4 == 1 {
+                world.add(entity, TestPosition { x: i, y: 0 });
+            }
+        }
+
+        let mut zipped = Vec::new();
+        gba_ecs_rs::zip_vec_hash(world.get::<TestVelocity>(), world.get::<TestPosition>())
+            .for_each(|entity, vel, pos| zipped.push((entity, vel.dx, pos.x)));
+        zipped.sort_unstable();
+        assert_eq!(zipped, vec![(1, 1, 1), (5, 5, 5)]);
+
+        // The two positions drive, one lookup each, whichever side comes first.
+        gba_ecs_rs::reset_query_counters();
+        let mut visited = Vec::new();
+        world.for_each::<(&TestPosition, &TestVelocity), _>(|entity, (pos, vel)| {
+            visited.push((entity, vel.dx, pos.x));
+        });
+        visited.sort_unstable();
+        assert_eq!(visited, zipped);
+        assert_eq!(gba_ecs_rs::query_counters().lookups, 2);
+    }
+
+    gba_ecs_rs::world!(OrderTestWorld {
+        TestPosition,
+        TestVelocity
+    });
+
+    #[test_case]
+    fn test_storage_swap_keeps_query_set(_agb: &mut agb::Gba) {
+        fn fill<WC>(world: &mut World<WC>)
+        where
+            WC: gba_ecs_rs::WorldContainer
+                + gba_ecs_rs::GetComponentContainer<TestPosition>
+                + gba_ecs_rs::GetComponentContainer<TestVelocity>,
+        {
+            for i in [5, 2, 7, 0, 3, 6, 1, 4] {
+                let entity = world.spawn();
+                world.add(entity, TestPosition { x: i, y: 0 });
+                if i % 2 == 1 {
+                    world.add(entity, TestVelocity { dx: i, dy: 0 });
+                }
+            }
+        }
+
+        const {
+            assert!(
+                <VecComponentContainer<TestPosition> as ComponentContainer<TestPosition>>::ORDERED
+            )
+        };
+        const {
+            assert!(!<HashComponentContainer<TestPosition> as ComponentContainer<TestPosition>>::ORDERED)
+        };
+
+        let mut vec_world = World::<OrderTestWorld>::new();
+        let mut mixed_world = World::<MixedTestWorld>::new();
+        fill(&mut vec_world);
+        fill(&mut mixed_world);
+
+        let mut from_vec = Vec::new();
+        vec_world.for_each::<(&TestPosition, &TestVelocity), _>(|entity, (pos, vel)| {
+            from_vec.push((entity, pos.x, vel.dx));
+        });
+        let mut from_mixed = Vec::new();
+        mixed_world.for_each::<(&TestPosition, &TestVelocity), _>(|entity, (pos, vel)| {
+            from_mixed.push((entity, pos.x, vel.dx));
+        });
+
+        assert_eq!(from_vec.len(), 4);
+        from_vec.sort_unstable();
+        from_mixed.sort_unstable();
+        assert_eq!(from_vec, from_mixed);
+    }
+
+    #[test_case]
+    fn test_zip_for_each_mut2(_agb: &mut agb::Gba) {
+        let mut world = World::<OrderTestWorld>::new();
+        for i in 0..4 {
+            let entity = world.spawn();
+            world.add(entity, TestPosition { x: i, y: 0 });
+            if i != 2 {
+                world.add(entity, TestVelocity { dx: 1, dy: i });
+            }
+        }
+        world.disable(Entity::new(3));
+
+        <(&mut TestPosition, &mut TestVelocity) as QueryMut<OrderTestWorld>>::for_each_mut(
+            &mut world,
+            |_entity, (pos, vel)| {
+                pos.x += vel.dx;
+                vel.dy = -vel.dy;
+            },
+        );
+
+        let mut visited = Vec::new();
+        world.for_each_include_disabled::<(&TestPosition, &TestVelocity), _>(
+            |entity, (pos, vel)| visited.push((entity, pos.x, vel.dy)),
+        );
+        assert_eq!(visited, vec![(0, 1, 0), (1, 2, -1), (3, 3, 3)]);
+
+        let (positions, velocities) = world.get_two_mut::<TestPosition, TestVelocity>();
+        gba_ecs_rs::zip_mut(positions, velocities).for_each_mut2(|_, pos, vel| {
+            core::mem::swap(&mut pos.x, &mut vel.dy);
+        });
+        assert_eq!(world.get::<TestPosition>().get_index(1).unwrap().x, -1);
+    }
+
+    #[derive(Default)]
+    struct TestPath(Vec<i32>);
+
+    impl gba_ecs_rs::Reusable for TestPath {
+        fn reset(&mut self) {
+            self.0.clear();
+        }
+    }
+
+    struct PooledTestWorld {
+        test_path: gba_ecs_rs::PooledComponentContainer<TestPath>,
+    }
+
+    impl gba_ecs_rs::WorldContainer for PooledTestWorld {
+        fn new() -> Self {
+            Self {
+                test_path: gba_ecs_rs::PooledComponentContainer::new(),
+            }
+        }
+        fn add_entity(&mut self, entity: Entity) {
+            self.test_path.add_entity(entity);
+        }
+        fn remove_entity(&mut self, entity: Entity) {
+            self.test_path.remove_entity(entity);
+        }
+        fn merge_from(&mut self, other: Self, offset: usize) {
+            self.test_path.merge_from(other.test_path, offset);
+        }
+        fn clear(&mut self) {
+            self.test_path.clear();
+        }
+    }
+
+    impl gba_ecs_rs::GetComponentContainer<TestPath> for PooledTestWorld {
+        type Container = gba_ecs_rs::PooledComponentContainer<TestPath>;
+        fn get_components(&self) -> &Self::Container {
+            &self.test_path
+        }
+        fn get_components_mut(&mut self) -> &mut Self::Container {
+            &mut self.test_path
+        }
+    }
+
+    #[test_case]
+    fn test_pooled_container_reuses_components(_agb: &mut agb::Gba) {
+        let mut world = World::<PooledTestWorld>::new();
+        let first = world.spawn();
+        world.add_pooled::<TestPath, _, _>(first, |path| path.0.extend(0..32));
+        let capacity = world.get::<TestPath>().get(first).unwrap().0.capacity();
+
+        world.despawn(first);
+        assert_eq!(world.get::<TestPath>().pooled(), 1);
+
+        let second = world.spawn();
+        world.add_pooled::<TestPath, _, _>(second, |path| path.0.push(7));
+        let path = world.get::<TestPath>().get(second).unwrap();
+        assert_eq!(path.0, vec![7]);
+        assert_eq!(path.0.capacity(), capacity);
+        assert_eq!(world.get::<TestPath>().pooled(), 0);
+
+        // Components handed back to the caller are not pooled.
+        assert!(world.remove::<TestPath>(second).is_some());
+        assert_eq!(world.get::<TestPath>().pooled(), 0);
+
+        let third = world.spawn();
+        world.add_pooled::<TestPath, _, _>(third, |path| path.0.push(1));
+        world.clear();
+        assert_eq!(world.get::<TestPath>().pooled(), 1);
+        assert_eq!(
+            world.component_storage_kind::<TestPath>(),
+            StorageKind::Other
+        );
+    }
+
+    #[test_case]
+    fn test_world_entities(_agb: &mut agb::Gba) {
+        let mut world = World::<MacroTestWorld>::new();
+        let entities: Vec<Entity> = (0..4).map(|_| world.spawn()).collect();
+        world.despawn(entities[1]);
+        let reserved = world.reserve_entity();
+        world.spawn_batch(2);
+
+        let indices: Vec<usize> = world.entities().map(|entity| entity.index()).collect();
+        assert_eq!(indices, vec![0, 2, 3, 4, 5, 6]);
+        assert!(world.entities().all(|entity| world.is_alive(entity)));
+        assert!(world.is_alive(reserved));
+
+        let respawned = world.spawn();
+        assert_eq!(world.entities().count(), 7);
+        assert!(world
+            .entities()
+            .any(|entity| entity.index() == 1 && entity.generation() == respawned.generation()));
+
+        let mut other = World::<MacroTestWorld>::new();
+        let dead = other.spawn();
+        other.spawn();
+        other.despawn(dead);
+        world.merge(other);
+        assert_eq!(world.entities().count(), 8);
+        assert_eq!(
+            world.entities().last().map(|entity| entity.index()),
+            Some(8)
+        );
+
+        world.clear();
+        assert_eq!(world.entities().count(), 0);
+    }
+
+    #[test_case]
+    fn test_vec_container_debug_dump(_agb: &mut agb::Gba) {
+        let mut container = VecComponentContainer::<TestVelocity>::new();
+        for i in [3, 0, 5] {
+            container.set(
+                Entity::new(i),
+                TestVelocity {
+                    dx: i as i32,
+                    dy: 0,
+                },
+            );
+        }
+        container.remove(Entity::new(0));
+
+        // Consistent, so this only prints.
+        container.debug_dump();
+        assert_eq!(container.active_indices(), &[3, 5]);
+    }
+
+    #[test_case]
+    fn test_dense_hash_query(_agb: &mut agb::Gba) {
+        let mut world = World::<MixedTestWorld>::new();
+        for i in 0..8 {
+            let entity = world.spawn();
+            if i != 5 {
+                world.add(entity, TestPosition { x: i, y: 0 });
+            }
+        }
+        // Stored past the last spawned entity, so the dense walk misses it.
+        world
+            .get_mut::<TestPosition>()
+            .set(Entity::new(20), TestPosition { x: 20, y: 0 });
+
+        let mut visited = Vec::new();
+        world.for_each::<&TestPosition, _>(|entity, pos| visited.push((entity, pos.x)));
+        assert_eq!(
+            visited,
+            vec![
+                (0, 0),
+                (1, 1),
+                (2, 2),
+                (3, 3),
+                (4, 4),
+                (6, 6),
+                (7, 7),
+                (20, 20)
+            ]
+        );
+    }
+
+    #[test_case]
+    fn test_entry_rejects_stale_entity(_agb: &mut agb::Gba) {
+        let mut world = World::<MacroTestWorld>::new();
+        let stale = world.spawn();
+        world.despawn(stale);
+        let current = world.spawn();
+        assert_eq!(current.index(), stale.index());
+        world.add(current, TestPosition { x: 1, y: 1 });
+
+        let positions = world.get_mut::<TestPosition>();
+        assert!(positions
+            .entry(stale)
+            .or_insert_with(|| TestPosition { x: 9, y: 9 })
+            .is_none());
+        assert_eq!(positions.get(current), Some(&TestPosition { x: 1, y: 1 }));
+
+        let mut hash = HashComponentContainer::<TestPosition>::new();
+        hash.set(current, TestPosition { x: 2, y: 2 });
+        assert!(hash
+            .entry(stale)
+            .or_insert(TestPosition { x: 9, y: 9 })
+            .is_none());
+        assert_eq!(hash.get(current), Some(&TestPosition { x: 2, y: 2 }));
+    }
+
+    #[test_case]
+    fn test_merge_after_clear_keeps_stale_handles(_agb: &mut agb::Gba) {
+        let mut world = World::<MacroTestWorld>::new();
+        let stale = world.spawn();
+        world.add(stale, TestPosition { x: 1, y: 1 });
+        world.spawn();
+        world.clear();
+
+        let mut other = World::<MacroTestWorld>::new();
+        let merged = other.spawn();
+        other.add(merged, TestPosition { x: 2, y: 2 });
+        world.merge(other);
+
+        assert!(!world.is_alive(stale));
+        assert!(world.get::<TestPosition>().get(stale).is_none());
+        assert_eq!(world.live_count(), 1);
+
+        let mut visited = Vec::new();
+        world.for_each_entity::<&TestPosition, _>(|entity, pos| {
+            assert!(world.is_alive(entity));
+            visited.push((entity.index(), pos.x));
+        });
+        assert_eq!(visited, vec![(2, 2)]);
+
+        // The indices `clear` freed are spawned again with their bumped generation.
+        let respawned = world.spawn();
+        assert!(respawned.index() < 2);
+        assert!(world.is_alive(respawned));
+        let respawned = world.spawn();
+        assert!(respawned.index() < 2);
+        assert!(!world.is_alive(stale));
+    }
+
+    #[test_case]
+    fn test_get_or_insert_with_rejects_stale_entity(_agb: &mut agb::Gba) {
+        let mut world = World::<MixedTestWorld>::new();
+        let stale = world.spawn();
+        world.despawn(stale);
+        let current = world.spawn();
+        assert_eq!(current.index(), stale.index());
+        world.add(current, TestVelocity { dx: 1, dy: 1 });
+
+        let container = world.get_mut::<TestVelocity>();
+        assert!(container
+            .get_or_insert_with(stale, || TestVelocity { dx: 9, dy: 9 })
+            .is_none());
+        assert_eq!(container.get(current), Some(&TestVelocity { dx: 1, dy: 1 }));
+    }
+
+    #[test_case]
+    fn test_pair_query_after_auto_grow(_agb: &mut agb::Gba) {
+        let mut world = World::<OrderTestWorld>::new();
+        for i in 0..3 {
+            let entity = world.spawn();
+            world.add(entity, TestPosition { x: i, y: 0 });
+            world.add(entity, TestVelocity { dx: i, dy: 0 });
+        }
+        // Grows the velocity container alone, past the position one.
+        world
+            .get_mut::<TestVelocity>()
+            .set(Entity::new(40), TestVelocity { dx: 40, dy: 0 });
+        assert!(world.get::<TestVelocity>().len() > world.get::<TestPosition>().len());
+
+        let mut visited = Vec::new();
+        world.for_each::<(&TestPosition, &TestVelocity), _>(|entity, (pos, vel)| {
+            visited.push((entity, pos.x, vel.dx))
+        });
+        assert_eq!(visited, vec![(0, 0, 0), (1, 1, 1), (2, 2, 2)]);
+
+        // The sparse walk follows the far slot's list, past the shorter container.
+        let mut far = VecComponentContainer::<TestVelocity>::new();
+        far.set(Entity::new(40), TestVelocity { dx: 40, dy: 0 });
+        let mut sparse = Vec::new();
+        gba_ecs_rs::zip(&far, world.get::<TestPosition>())
+            .for_each_sparse(|entity, _, _| sparse.push(entity));
+        assert!(sparse.is_empty());
+
+        <(&mut TestPosition, &TestVelocity) as QueryMut<OrderTestWorld>>::for_each_mut(
+            &mut world,
+            |_, (pos, vel)| pos.x += vel.dx,
+        );
+        assert_eq!(
+            world.get::<TestPosition>().get_index(2).map(|pos| pos.x),
+            Some(4)
+        );
+    }
 }
@@ -32,6 +32,38 @@ impl WorldContainer for MyWorldContainer {
         self.unique_vec.add_entity(entity);
         self.unique_hash.add_entity(entity);
     }
+
+    fn remove_entity(&mut self, entity: Entity) {
+        self.modulo1.remove_entity(entity);
+        self.modulo2.remove_entity(entity);
+        self.modulo8.remove_entity(entity);
+        self.unique_vec.remove_entity(entity);
+        self.unique_hash.remove_entity(entity);
+    }
+
+    fn merge_from(&mut self, other: Self, offset: usize) {
+        self.modulo1.merge_from(other.modulo1, offset);
+        self.modulo2.merge_from(other.modulo2, offset);
+        self.modulo8.merge_from(other.modulo8, offset);
+        self.unique_vec.merge_from(other.unique_vec, offset);
+        self.unique_hash.merge_from(other.unique_hash, offset);
+    }
+
+    fn reserve(&mut self, additional: usize) {
+        self.modulo1.reserve(additional);
+        self.modulo2.reserve(additional);
+        self.modulo8.reserve(additional);
+        self.unique_vec.reserve(additional);
+        self.unique_hash.reserve(additional);
+    }
+
+    fn clear(&mut self) {
+        self.modulo1.clear();
+        self.modulo2.clear();
+        self.modulo8.clear();
+        self.unique_vec.clear();
+        self.unique_hash.clear();
+    }
 }
 
 impl GetComponentContainer<Modulo1> for MyWorldContainer {